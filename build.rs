@@ -60,6 +60,8 @@ fn parse_config_yaml() -> Vec<ConfigOptions> {
             value.to_string()
         } else if let Some(value) = values["value"].as_i64() {
             value.to_string()
+        } else if let Some(value) = values["value"].as_bool() {
+            value.to_string()
         } else {
             panic!("Unable to process value of configuration!");
         };
@@ -515,6 +517,8 @@ fn main() {
     println!("cargo:rerun-if-changed=./level.yaml");
     println!("cargo:rerun-if-changed=./src/boot/head.S");
     println!("cargo:rerun-if-changed=./src/trap/entry.S");
+    println!("cargo:rerun-if-changed=./src/mm/uaccess.S");
+    println!("cargo:rerun-if-changed=./src/trap/user_test.S");
 
     // Parse config file
     let configs_options = parse_config_yaml();
@@ -533,4 +537,10 @@ fn main() {
 
     // Build ./src/trap/entry.S
     compile_assembly_file(path::Path::new("./src/trap/entry.S"), &configs_options);
+
+    // Build ./src/mm/uaccess.S
+    compile_assembly_file(path::Path::new("./src/mm/uaccess.S"), &configs_options);
+
+    // Build ./src/trap/user_test.S
+    compile_assembly_file(path::Path::new("./src/trap/user_test.S"), &configs_options);
 }
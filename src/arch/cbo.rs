@@ -0,0 +1,45 @@
+//! Cache-Block Management Instructions (`Zicbom`).
+//!
+//! #See
+//! `19.5 Cache-Block Management Instructions` of `Volume I: RISC-V Unprivileged ISA`
+
+use core::arch::asm;
+
+use crate::kernel::address::Address;
+use crate::kernel::address::VirtualAddress;
+
+/// Write back the cache block containing `virt_addr` to memory (if modified), keeping it cached.
+pub fn cbo_clean<T>(virt_addr: VirtualAddress<T>) {
+    let addr = virt_addr.addr();
+    unsafe {
+        // `cbo.clean {addr}`
+        asm!(
+            ".insn i 0x0f, 2, x0, {addr}, 1",
+            addr = in(reg) addr,
+        );
+    }
+}
+
+/// Invalidate the cache block containing `virt_addr` (discarding modifications).
+pub fn cbo_inval<T>(virt_addr: VirtualAddress<T>) {
+    let addr = virt_addr.addr();
+    unsafe {
+        // `cbo.inval {addr}`
+        asm!(
+            ".insn i 0x0f, 2, x0, {addr}, 0",
+            addr = in(reg) addr,
+        );
+    }
+}
+
+/// Write back the cache block containing `virt_addr` to memory (if modified) and invalidate it.
+pub fn cbo_flush<T>(virt_addr: VirtualAddress<T>) {
+    let addr = virt_addr.addr();
+    unsafe {
+        // `cbo.flush {addr}`
+        asm!(
+            ".insn i 0x0f, 2, x0, {addr}, 2",
+            addr = in(reg) addr,
+        );
+    }
+}
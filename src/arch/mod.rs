@@ -1,5 +1,6 @@
 //! Architecture specifics.
 
+pub mod cbo;
 pub mod cpu;
 pub mod csr;
 pub mod cycle;
@@ -10,6 +11,7 @@ pub mod satp;
 pub mod scause;
 pub mod scounteren;
 pub mod sepc;
+pub mod sfence;
 pub mod sie;
 pub mod sip;
 pub mod sscratch;
@@ -19,3 +21,4 @@ pub mod stval;
 pub mod stvec;
 pub mod time;
 pub mod tp;
+pub mod units;
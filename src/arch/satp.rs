@@ -11,6 +11,54 @@ use crate::kernel::address::Address;
 use crate::kernel::address::PhysicalAddress;
 use crate::mm::pte::PageTableEntry;
 
+/// Paging mode (`MODE` field of [`SATP`]).
+///
+/// #See
+/// `4.1.11 Supervisor Address Translation and Protection (satp) Register` of `Volume II: RISC-V Privileged Architectures`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PagingMode {
+    /// No translation or protection.
+    Bare = 0,
+    /// Page-based 39-bit virtual addressing.
+    Sv39 = 8,
+    /// Page-based 48-bit virtual addressing.
+    Sv48 = 9,
+    /// Page-based 57-bit virtual addressing.
+    Sv57 = 10,
+}
+
+impl PagingMode {
+    /// Get [`PagingMode`] for virtual addresses with a width of `bits` (`39`, `48` or `57`).
+    pub const fn from_virtual_address_bits(bits: usize) -> Option<Self> {
+        match bits {
+            39 => Some(PagingMode::Sv39),
+            48 => Some(PagingMode::Sv48),
+            57 => Some(PagingMode::Sv57),
+            _ => None,
+        }
+    }
+
+    /// Get number of page table levels.
+    pub const fn levels(self) -> usize {
+        match self {
+            PagingMode::Bare => 0,
+            PagingMode::Sv39 => 3,
+            PagingMode::Sv48 => 4,
+            PagingMode::Sv57 => 5,
+        }
+    }
+
+    /// Get next smaller [`PagingMode`] (used as fallback, if unsupported).
+    pub const fn fallback(self) -> Option<Self> {
+        match self {
+            PagingMode::Bare => None,
+            PagingMode::Sv39 => None,
+            PagingMode::Sv48 => Some(PagingMode::Sv39),
+            PagingMode::Sv57 => Some(PagingMode::Sv48),
+        }
+    }
+}
+
 /// Supervisor Address Translation and Protection Register.
 ///
 /// #See
@@ -31,6 +79,33 @@ impl SATP {
         self.0 &= !0xFFF_FFFF_FFFF;
         self.0 |= ppn as u64;
     }
+
+    /// Get address space identifier
+    pub const fn get_asid(&self) -> usize {
+        ((self.0 >> 44) & 0xFFFF) as usize
+    }
+
+    /// Set address space identifier
+    pub fn set_asid(&mut self, asid: usize) {
+        self.0 &= !(0xFFFF << 44);
+        self.0 |= ((asid as u64) & 0xFFFF) << 44;
+    }
+
+    /// Get paging mode
+    pub const fn get_mode(&self) -> PagingMode {
+        match self.0 >> 60 {
+            8 => PagingMode::Sv39,
+            9 => PagingMode::Sv48,
+            10 => PagingMode::Sv57,
+            _ => PagingMode::Bare,
+        }
+    }
+
+    /// Set paging mode
+    pub fn set_mode(&mut self, mode: PagingMode) {
+        self.0 &= !(0xF << 60);
+        self.0 |= (mode as u64) << 60;
+    }
 }
 
 impl CSR for SATP {
@@ -0,0 +1,54 @@
+//! Supervisor Memory-Management Fence Instruction.
+//!
+//! #See
+//! `4.2.1 Supervisor Memory-Management Fence Instruction` of `Volume II: RISC-V Privileged Architectures`
+
+use core::arch::asm;
+
+use crate::kernel::address::Address;
+use crate::kernel::address::VirtualAddress;
+
+/// Order all previous stores to page tables and invalidate all address-translation cache entries
+/// (including non-leaf page table entries).
+pub fn sfence_vma() {
+    unsafe {
+        asm!("sfence.vma x0, x0");
+    }
+}
+
+/// Order all previous stores to page tables and invalidate address-translation cache entries
+/// of the leaf page table entry corresponding to `virt_addr`.
+pub fn sfence_vma_addr<T>(virt_addr: VirtualAddress<T>) {
+    let addr = virt_addr.addr();
+    unsafe {
+        asm!(
+            "sfence.vma {addr}, x0",
+            addr = in(reg) addr,
+        );
+    }
+}
+
+/// Order all previous stores to page tables and invalidate all address-translation cache entries
+/// (including non-leaf page table entries) of address space `asid`, except for global mappings.
+pub fn sfence_vma_asid(asid: usize) {
+    unsafe {
+        asm!(
+            "sfence.vma x0, {asid}",
+            asid = in(reg) asid,
+        );
+    }
+}
+
+/// Order all previous stores to page tables and invalidate address-translation cache entries
+/// of the leaf page table entry corresponding to `virt_addr` within address space `asid`, except
+/// for global mappings.
+pub fn sfence_vma_addr_asid<T>(virt_addr: VirtualAddress<T>, asid: usize) {
+    let addr = virt_addr.addr();
+    unsafe {
+        asm!(
+            "sfence.vma {addr}, {asid}",
+            addr = in(reg) addr,
+            asid = in(reg) asid,
+        );
+    }
+}
@@ -125,6 +125,12 @@ impl SStatus {
         }
     }
 
+    /// Set `Vector Unit Extension Status`.
+    pub fn set_vs(&mut self, value: SStatusUnitStatus) {
+        self.0 &= !(0b11 << 9);
+        self.0 |= ((value as u64) & 0b11) << 9;
+    }
+
     /// Get `Floating-Point Unit Extension Status`.
     pub fn get_fs(&self) -> SStatusUnitStatus {
         match (self.0 >> 13) & 0b11 {
@@ -136,6 +142,12 @@ impl SStatus {
         }
     }
 
+    /// Set `Floating-Point Unit Extension Status`.
+    pub fn set_fs(&mut self, value: SStatusUnitStatus) {
+        self.0 &= !(0b11 << 13);
+        self.0 |= ((value as u64) & 0b11) << 13;
+    }
+
     /// Get `Addtional User-Mode Unit Extension Status`.
     pub fn get_xs(&self) -> SStatusUnitStatus {
         match (self.0 >> 15) & 0b11 {
@@ -236,7 +248,7 @@ impl Display for SStatusPrivLevel {
 ///
 /// #See
 /// Section `3.1.6.6 Extension Context Status in sstatus Register` of `Volume II: RISC-V Privileged Architectures`
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SStatusUnitStatus {
     /// Offline state.
     Off = 0b00,
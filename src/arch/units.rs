@@ -0,0 +1,232 @@
+//! Register state of the floating-point (`F`/`D`) and vector (`V`) units.
+//!
+//! The kernel itself is built without using these units (`-Csoft-float`), thus their registers only
+//! hold state of user mode. Accessing them requires the corresponding unit to be enabled (i.e.
+//! `sstatus.FS`/`sstatus.VS` must not be [`SStatusUnitStatus::Off`]).
+//!
+//! #See
+//! Section `3.1.6.6 Extension Context Status in sstatus Register` of `Volume II: RISC-V Privileged
+//! Architectures`
+
+use core::arch::asm;
+
+use crate::arch::csr::CSR;
+use crate::arch::sstatus::{SStatus, SStatusUnitStatus};
+
+/// Number of saved floating-point registers (`f0`..`f31` and `fcsr`).
+pub const NUM_FP_REGISTERS: usize = 33;
+
+/// Number of saved vector control/status registers (`vstart`, `vl`, `vtype` and `vcsr`).
+pub const NUM_VECTOR_CSRS: usize = 4;
+
+/// Number of vector registers (`v0`..`v31`).
+pub const NUM_VECTOR_REGISTERS: usize = 32;
+
+/// Probe whether the floating-point unit is implemented and get the length of vector registers in
+/// bytes (`vlenb`, if the vector unit is implemented).
+///
+/// `sstatus.FS`/`sstatus.VS` are read-only zero for units which are not implemented.
+pub fn probe() -> (bool, Option<usize>) {
+    let mut sstatus = SStatus::new(0);
+    sstatus.read();
+    let original = SStatus::new(sstatus.inner());
+
+    // Step 1: Try to enable both units
+    sstatus.set_fs(SStatusUnitStatus::Initial);
+    sstatus.set_vs(SStatusUnitStatus::Initial);
+    sstatus.write();
+    sstatus.read();
+    let fp = sstatus.get_fs() != SStatusUnitStatus::Off;
+    let vector = sstatus.get_vs() != SStatusUnitStatus::Off;
+
+    // Step 2: Read `vlenb` (only accessible while the vector unit is enabled)
+    let vlenb = match vector {
+        true => {
+            let mut x: usize;
+            unsafe {
+                asm!(
+                    "csrr {x}, 0xc22",
+                    x = out(reg) x,
+                );
+            }
+            Some(x)
+        }
+        false => None,
+    };
+
+    original.write();
+    (fp, vlenb)
+}
+
+/// Save floating-point registers to `regs`.
+///
+/// # Safety
+/// The floating-point unit must be enabled and `regs` must refer to [`NUM_FP_REGISTERS`] values.
+pub unsafe fn save_fp(regs: *mut u64) {
+    unsafe {
+        asm!(
+                ".option push",
+                ".option arch, +d",
+                "fsd f0, 0({regs})",
+                "fsd f1, 8({regs})",
+                "fsd f2, 16({regs})",
+                "fsd f3, 24({regs})",
+                "fsd f4, 32({regs})",
+                "fsd f5, 40({regs})",
+                "fsd f6, 48({regs})",
+                "fsd f7, 56({regs})",
+                "fsd f8, 64({regs})",
+                "fsd f9, 72({regs})",
+                "fsd f10, 80({regs})",
+                "fsd f11, 88({regs})",
+                "fsd f12, 96({regs})",
+                "fsd f13, 104({regs})",
+                "fsd f14, 112({regs})",
+                "fsd f15, 120({regs})",
+                "fsd f16, 128({regs})",
+                "fsd f17, 136({regs})",
+                "fsd f18, 144({regs})",
+                "fsd f19, 152({regs})",
+                "fsd f20, 160({regs})",
+                "fsd f21, 168({regs})",
+                "fsd f22, 176({regs})",
+                "fsd f23, 184({regs})",
+                "fsd f24, 192({regs})",
+                "fsd f25, 200({regs})",
+                "fsd f26, 208({regs})",
+                "fsd f27, 216({regs})",
+                "fsd f28, 224({regs})",
+                "fsd f29, 232({regs})",
+                "fsd f30, 240({regs})",
+                "fsd f31, 248({regs})",
+                "frcsr {tmp}",
+                "sd {tmp}, 256({regs})",
+                ".option pop",
+                regs = in(reg) regs,
+                tmp = out(reg) _,
+        );
+    }
+}
+
+/// Restore floating-point registers from `regs`.
+///
+/// # Safety
+/// The floating-point unit must be enabled and `regs` must refer to [`NUM_FP_REGISTERS`] values.
+pub unsafe fn restore_fp(regs: *const u64) {
+    unsafe {
+        asm!(
+                ".option push",
+                ".option arch, +d",
+                "fld f0, 0({regs})",
+                "fld f1, 8({regs})",
+                "fld f2, 16({regs})",
+                "fld f3, 24({regs})",
+                "fld f4, 32({regs})",
+                "fld f5, 40({regs})",
+                "fld f6, 48({regs})",
+                "fld f7, 56({regs})",
+                "fld f8, 64({regs})",
+                "fld f9, 72({regs})",
+                "fld f10, 80({regs})",
+                "fld f11, 88({regs})",
+                "fld f12, 96({regs})",
+                "fld f13, 104({regs})",
+                "fld f14, 112({regs})",
+                "fld f15, 120({regs})",
+                "fld f16, 128({regs})",
+                "fld f17, 136({regs})",
+                "fld f18, 144({regs})",
+                "fld f19, 152({regs})",
+                "fld f20, 160({regs})",
+                "fld f21, 168({regs})",
+                "fld f22, 176({regs})",
+                "fld f23, 184({regs})",
+                "fld f24, 192({regs})",
+                "fld f25, 200({regs})",
+                "fld f26, 208({regs})",
+                "fld f27, 216({regs})",
+                "fld f28, 224({regs})",
+                "fld f29, 232({regs})",
+                "fld f30, 240({regs})",
+                "fld f31, 248({regs})",
+                "ld {tmp}, 256({regs})",
+                "fscsr {tmp}",
+                ".option pop",
+                regs = in(reg) regs,
+                tmp = out(reg) _,
+        );
+    }
+}
+
+/// Save vector registers to `regs` and vector control/status registers to `csrs`.
+///
+/// # Safety
+/// The vector unit must be enabled, `regs` must refer to [`NUM_VECTOR_REGISTERS`]` * vlenb` bytes
+/// and `csrs` to [`NUM_VECTOR_CSRS`] values.
+pub unsafe fn save_vector(regs: *mut u8, csrs: *mut u64) {
+    unsafe {
+        asm!(
+            ".option push",
+            ".option arch, +v",
+            "csrr {tmp}, vstart",
+            "sd {tmp}, 0({csrs})",
+            "csrr {tmp}, vl",
+            "sd {tmp}, 8({csrs})",
+            "csrr {tmp}, vtype",
+            "sd {tmp}, 16({csrs})",
+            "csrr {tmp}, vcsr",
+            "sd {tmp}, 24({csrs})",
+            // Whole register stores ignore `vtype` and `vl`
+            "csrr {tmp}, vlenb",
+            "slli {tmp}, {tmp}, 3",
+            "vs8r.v v0, ({regs})",
+            "add {regs}, {regs}, {tmp}",
+            "vs8r.v v8, ({regs})",
+            "add {regs}, {regs}, {tmp}",
+            "vs8r.v v16, ({regs})",
+            "add {regs}, {regs}, {tmp}",
+            "vs8r.v v24, ({regs})",
+            ".option pop",
+            regs = inout(reg) regs => _,
+            csrs = in(reg) csrs,
+            tmp = out(reg) _,
+        );
+    }
+}
+
+/// Restore vector registers from `regs` and vector control/status registers from `csrs`.
+///
+/// # Safety
+/// The vector unit must be enabled, `regs` must refer to [`NUM_VECTOR_REGISTERS`]` * vlenb` bytes
+/// and `csrs` to [`NUM_VECTOR_CSRS`] values.
+pub unsafe fn restore_vector(regs: *const u8, csrs: *const u64) {
+    unsafe {
+        asm!(
+            ".option push",
+            ".option arch, +v",
+            // Whole register loads ignore `vtype` and `vl`
+            "csrr {tmp}, vlenb",
+            "slli {tmp}, {tmp}, 3",
+            "vl8r.v v0, ({regs})",
+            "add {regs}, {regs}, {tmp}",
+            "vl8r.v v8, ({regs})",
+            "add {regs}, {regs}, {tmp}",
+            "vl8r.v v16, ({regs})",
+            "add {regs}, {regs}, {tmp}",
+            "vl8r.v v24, ({regs})",
+            "ld {tmp}, 8({csrs})",
+            "ld {tmp2}, 16({csrs})",
+            "vsetvl x0, {tmp}, {tmp2}",
+            "ld {tmp}, 24({csrs})",
+            "csrw vcsr, {tmp}",
+            // Vector instructions reset `vstart`, thus restore it last
+            "ld {tmp}, 0({csrs})",
+            "csrw vstart, {tmp}",
+            ".option pop",
+            regs = inout(reg) regs => _,
+            csrs = in(reg) csrs,
+            tmp = out(reg) _,
+            tmp2 = out(reg) _,
+        );
+    }
+}
@@ -17,6 +17,7 @@ static DEVICE_TREE: InitCell<DeviceTree> = InitCell::new();
 #[derive(Debug)]
 pub struct DeviceTree {
     parser: Parser,
+    blob: (PhysicalAddress<c_void>, usize),
 }
 
 unsafe impl Sync for DeviceTree {}
@@ -54,7 +55,13 @@ impl DeviceTree {
 
         // Update DEVICE_TREE
         let mut device_tree = DEVICE_TREE.get_mut(token);
-        *device_tree = DeviceTree { parser };
+        *device_tree = DeviceTree {
+            parser,
+            blob: (
+                PhysicalAddress::new(dtb_ptr as *mut c_void),
+                dtb_size as usize,
+            ),
+        };
         let token = device_tree.destroy();
 
         // Finalize InitCell
@@ -78,6 +85,158 @@ impl DeviceTree {
             .count()
     }
 
+    /// Check if all enumerated CPUs support ISA `extension` (e.g. `svpbmt`).
+    ///
+    /// The extensions are taken from the `riscv,isa-extensions` property or, as fallback, from
+    /// the (underscore-separated) multi-letter extensions of the `riscv,isa` property.
+    pub fn has_isa_extension(&self, extension: &str) -> bool {
+        let mut cpus = self
+            .parser
+            .node_iter()
+            .filter(|node| node.name().starts_with("cpu@"))
+            .peekable();
+        if cpus.peek().is_none() {
+            return false;
+        }
+
+        cpus.all(|node| {
+            // Try list of extensions (`\0`-separated strings)
+            if let Some(property) = node
+                .property_iter()
+                .find(|p| p.name == "riscv,isa-extensions")
+            {
+                return property
+                    .value
+                    .split(|byte| *byte == 0)
+                    .any(|name| name.eq_ignore_ascii_case(extension.as_bytes()));
+            }
+
+            // Fall back to ISA string (e.g. `rv64imafdc_zicsr_svpbmt`)
+            match node.property_iter().find(|p| p.name == "riscv,isa") {
+                Some(property) => property
+                    .value
+                    .split(|byte| *byte == 0 || *byte == b'_')
+                    .skip(1)
+                    .any(|name| name.eq_ignore_ascii_case(extension.as_bytes())),
+                None => false,
+            }
+        })
+    }
+
+    /// Get physical address and size of the device tree blob.
+    pub fn get_blob_region(&self) -> (PhysicalAddress<c_void>, usize) {
+        self.blob
+    }
+
+    /// Get iterator for <address, length> pairs of physical memory (as described by `/memory`
+    /// nodes).
+    pub fn memory_iter(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.parser
+            .node_iter()
+            .filter(|node| node.depth == 1)
+            .filter(|node| node.name() == "memory" || node.name().starts_with("memory@"))
+            .filter_map(|node| node.property_iter().find(|p| p.name == "reg"))
+            .flat_map(|property| property.into_addr_length_iter())
+    }
+
+    /// Get iterator for <address, length> pairs of reserved physical memory (as described by the
+    /// memory reservation block and `/reserved-memory` nodes).
+    pub fn reserved_memory_iter(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let mem_reservations = self
+            .parser
+            .mem_reservation_iter()
+            .map(|(address, size)| (address as usize, size as usize));
+
+        let reserved_memory_nodes = self
+            .parser
+            .node_iter()
+            .filter(|node| node.depth == 1 && node.name() == "reserved-memory")
+            .flat_map(|node| node.children_node_iter())
+            .filter_map(|node| node.property_iter().find(|p| p.name == "reg"))
+            .flat_map(|property| property.into_addr_length_iter());
+
+        mem_reservations.chain(reserved_memory_nodes)
+    }
+
+    /// Get iterator for <bus address, physical address, length> triples of physical memory
+    /// accessible by DMA (as described by `dma-ranges` properties).
+    ///
+    /// The bus address is encoded using `#address-cells` of the node (i.e. its child bus), the
+    /// physical address using `#address-cells` of its parent.
+    pub fn dma_ranges_iter(&self) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        self.parser
+            .node_iter()
+            .filter_map(|node| {
+                let property = node.property_iter().find(|p| p.name == "dma-ranges")?;
+                let parent = node.get_parent_node();
+
+                let bus_cells = Self::get_cells(Some(&node), "#address-cells", 2);
+                let phys_cells = Self::get_cells(parent.as_ref(), "#address-cells", 2);
+                let size_cells = Self::get_cells(Some(&node), "#size-cells", 1);
+                let entry_size = 4 * (bus_cells + phys_cells + size_cells);
+                if entry_size == 0 {
+                    return None;
+                }
+
+                Some((property.value, bus_cells, phys_cells, entry_size))
+            })
+            .flat_map(|(value, bus_cells, phys_cells, entry_size)| {
+                value.chunks_exact(entry_size).map(move |entry| {
+                    let (bus, entry) = entry.split_at(4 * bus_cells);
+                    let (phys, size) = entry.split_at(4 * phys_cells);
+                    (
+                        Self::read_cells(bus),
+                        Self::read_cells(phys),
+                        Self::read_cells(size),
+                    )
+                })
+            })
+    }
+
+    /// Check if DMA is coherent with the caches of the harts (as indicated by a `dma-coherent`
+    /// property of the root node or a top-level bus).
+    pub fn is_dma_coherent(&self) -> bool {
+        self.parser
+            .node_iter()
+            .filter(|node| node.depth <= 1)
+            .any(|node| node.property_iter().any(|p| p.name == "dma-coherent"))
+    }
+
+    /// Get size of cache blocks managed by `Zicbom` instructions (as described by the
+    /// `riscv,cbom-block-size` property of the first CPU).
+    pub fn get_cbom_block_size(&self) -> Option<usize> {
+        let node = self
+            .parser
+            .node_iter()
+            .find(|node| node.name().starts_with("cpu@"))?;
+        let property = node
+            .property_iter()
+            .find(|p| p.name == "riscv,cbom-block-size")?;
+
+        match property.get_value() {
+            PropertyValue::U32(size) => Some(size as usize),
+            _ => None,
+        }
+    }
+
+    /// Get value of cell-count property `name` of `node` (or `default`, if not present).
+    fn get_cells(node: Option<&Node>, name: &str, default: usize) -> usize {
+        match node.and_then(|node| node.property_iter().find(|p| p.name == name)) {
+            Some(property) => match property.get_value() {
+                PropertyValue::U32(cells) => cells as usize,
+                _ => default,
+            },
+            None => default,
+        }
+    }
+
+    /// Read big-endian number spanning (possibly multiple) cells.
+    fn read_cells(cells: &[u8]) -> usize {
+        cells
+            .iter()
+            .fold(0, |value, byte| (value << 8) | *byte as usize)
+    }
+
     /// Get node by matching `compatible` property
     pub fn get_node_by_compatible_property(&self, compatible: &str) -> Option<Node> {
         for node in self.parser.node_iter() {
@@ -58,7 +58,7 @@ impl<'a> Node<'a> {
     }
 
     /// Get iterator for properties associated with node.
-    pub fn property_iter(&self) -> PropertyIter {
+    pub fn property_iter(&self) -> PropertyIter<'a> {
         /* Align current token pointer */
         let alignment_offset = self.curr_token.as_ptr().cast::<u8>().align_offset(4);
         let curr_token = unsafe {
@@ -87,7 +87,7 @@ impl<'a> Node<'a> {
     }
 
     /// Get iterator for (direct) children associated with given node.
-    pub fn children_node_iter(&self) -> ChildNodeIter {
+    pub fn children_node_iter(&self) -> ChildNodeIter<'a> {
         /* Align current token pointer */
         let alignment_offset = self.curr_token.as_ptr().cast::<u8>().align_offset(4);
         let curr_token = unsafe {
@@ -84,6 +84,7 @@ impl<'a> Property<'a> {
                 || self.name == "max-frame-size"
                 || self.name == "max-speed"
                 || self.name == "riscv,ndev"
+                || self.name == "riscv,cbom-block-size"
             {
                 return PropertyValue::U32(
                     (self.value[0] as u32) << 24
@@ -156,7 +157,7 @@ impl<'a> Property<'a> {
     ///
     /// The `reg` property defines a list of <address, length> pairs of the device’s resources
     /// within the address space defined by its parent bus.
-    pub fn into_addr_length_iter(&self) -> AddrLengthArrayIter {
+    pub fn into_addr_length_iter(&self) -> AddrLengthArrayIter<'a> {
         assert!(self.name == "reg");
 
         let parent_node = match self.node.get_parent_node() {
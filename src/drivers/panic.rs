@@ -4,6 +4,7 @@ use crate::drivers::driver::Driver;
 use crate::sync::level::{LevelEpilogue, LevelPrologue};
 use crate::trap::cause::Trap;
 use crate::trap::handler_interface::TrapContext;
+use crate::trap::handlers::PrologueResult;
 use crate::trap::handlers::TrapHandler;
 
 /// Panic handler for unexpected interupts.
@@ -31,7 +32,7 @@ impl TrapHandler for Panic {
         panic!("The panic driver must never be Driver::cause()");
     }
 
-    fn prologue(&self, token: LevelPrologue) -> (bool, LevelPrologue) {
+    fn prologue(&self, token: LevelPrologue) -> (PrologueResult, LevelPrologue) {
         let _ = token;
         panic!("PANIC! Unexpected interrupt");
     }
@@ -18,6 +18,7 @@ use crate::sync::level::LevelPrologue;
 use crate::trap::cause::Interrupt;
 use crate::trap::cause::Trap;
 use crate::trap::handler_interface::TrapContext;
+use crate::trap::handlers::PrologueResult;
 use crate::trap::handlers::TrapHandler;
 use crate::trap::handlers::TrapHandlers;
 
@@ -108,7 +109,7 @@ impl TrapHandler for Timer {
         Trap::Interrupt(Interrupt::TimerInterrupt)
     }
 
-    fn prologue(&self, token: LevelPrologue) -> (bool, LevelPrologue) {
+    fn prologue(&self, token: LevelPrologue) -> (PrologueResult, LevelPrologue) {
         // Disable timer interrupts
         let mut sie = SIE::new(0);
         sie.read();
@@ -121,7 +122,7 @@ impl TrapHandler for Timer {
         sip.clear_timer_interrupt_pending();
         sip.write();
 
-        (true, token)
+        (PrologueResult::EpilogueRequired, token)
     }
 
     fn epilogue(&self, _state: Option<&mut TrapContext>, token: LevelEpilogue) -> LevelEpilogue {
@@ -5,10 +5,11 @@
 //! Considerations](https://mth.st/blog/riscv-qemu/AN-491.pdf)
 //! - [(RISCV) RISC-V System, Booting, and
 //! Interrupts](https://marz.utk.edu/my-courses/cosc562/riscv/)
+//!
+//! Received bytes are buffered by the `prologue` and moved into the input buffer (consumed by
+//! [`Uart::read`]) by the `epilogue`.
 use core::ffi::c_void;
 use core::ptr;
-use core::sync::atomic::AtomicU16;
-use core::sync::atomic::Ordering;
 
 use crate::boot::device_tree::dt::DeviceTree;
 use crate::drivers::driver::Driver;
@@ -21,10 +22,13 @@ use crate::kernel::address::VirtualAddress;
 use crate::drivers::driver::DriverError;
 use crate::mm::mapping::KERNEL_VIRTUAL_MEMORY_SYSTEM;
 use crate::sync::init_cell::InitCell;
-use crate::sync::level::LevelInitialization;
-use crate::sync::ticketlock::IRQTicketlock;
+use crate::sync::level::{Adapter, AdapterDriverPrologue, AdapterGuard};
+use crate::sync::level::{LevelEpilogue, LevelInitialization};
+use crate::sync::ticketlock::{IRQTicketlock, TicketlockEpilogue};
 use crate::trap::cause::Interrupt;
 use crate::trap::cause::Trap;
+use crate::trap::handler_interface::TrapContext;
+use crate::trap::handlers::PrologueResult;
 use crate::trap::handlers::TrapHandler;
 use crate::trap::handlers::TrapHandlers;
 use crate::trap::intc::INTERRUPT_CONTROLLER;
@@ -67,6 +71,52 @@ impl Key {
 /// Global Uart instance.
 pub static UART: InitCell<Uart> = InitCell::new();
 
+/// Size of the receive FIFO of the NS16550a.
+const FIFO_SIZE: usize = 16;
+
+/// Size of the buffers holding received bytes.
+const RECEIVE_BUFFER_SIZE: usize = 64;
+
+/// Ring buffer of received bytes.
+struct ReceiveBuffer {
+    bytes: [u8; RECEIVE_BUFFER_SIZE],
+    head: usize,
+    len: usize,
+}
+
+impl ReceiveBuffer {
+    /// Create an empty buffer.
+    const fn new() -> Self {
+        Self {
+            bytes: [0; RECEIVE_BUFFER_SIZE],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Append `byte` (which is dropped, if the buffer is full).
+    fn push(&mut self, byte: u8) {
+        if self.len == RECEIVE_BUFFER_SIZE {
+            return;
+        }
+
+        self.bytes[(self.head + self.len) % RECEIVE_BUFFER_SIZE] = byte;
+        self.len += 1;
+    }
+
+    /// Remove oldest byte.
+    fn pop(&mut self) -> Option<u8> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let byte = self.bytes[self.head];
+        self.head = (self.head + 1) % RECEIVE_BUFFER_SIZE;
+        self.len -= 1;
+        Some(byte)
+    }
+}
+
 /// Register offsets (in bytes) relative to start of configuration space.
 #[allow(unused)]
 #[derive(Debug)]
@@ -430,7 +480,10 @@ pub struct Uart {
     locked_ns1655a: IRQTicketlock<UARTNS16550a>,
     clock_freq: usize,
     interrupt: Interrupt,
-    raw_key: AtomicU16,
+    /// Bytes received by the `prologue` (not yet moved by the `epilogue`).
+    received: IRQTicketlock<ReceiveBuffer>,
+    /// Bytes available to [`Uart::read`].
+    input: TicketlockEpilogue<ReceiveBuffer>,
 }
 
 impl Uart {
@@ -440,7 +493,8 @@ impl Uart {
             locked_ns1655a: IRQTicketlock::new(UARTNS16550a::new()),
             clock_freq: 0,
             interrupt: Interrupt::ExternalInterrupt,
-            raw_key: AtomicU16::new(0),
+            received: IRQTicketlock::new(ReceiveBuffer::new()),
+            input: TicketlockEpilogue::new(ReceiveBuffer::new()),
         }
     }
 }
@@ -594,14 +648,19 @@ impl Uart {
         Ok(())
     }
 
-    /// Try to read single byte from serial interface.
-    pub fn read(&self) -> Result<u8, DriverError> {
-        let key = Key(self.raw_key.swap(0, Ordering::Relaxed));
-        if !key.valid() {
-            return Err(DriverError::NoDataAvailable);
+    /// Try to read single (received) byte from serial interface.
+    pub fn read(
+        &self,
+        token: LevelEpilogue,
+    ) -> Result<(u8, LevelEpilogue), (DriverError, LevelEpilogue)> {
+        let (mut input, token) = self.input.lock(token);
+        let byte = input.pop();
+        let token = input.unlock(token);
+
+        match byte {
+            Some(byte) => Ok((byte, token)),
+            None => Err((DriverError::NoDataAvailable, token)),
         }
-
-        return Ok(key.raw());
     }
 }
 
@@ -616,33 +675,62 @@ impl TrapHandler for Uart {
     fn prologue(
         &self,
         token: crate::sync::level::LevelPrologue,
-    ) -> (bool, crate::sync::level::LevelPrologue) {
+    ) -> (PrologueResult, crate::sync::level::LevelPrologue) {
         // Lock driver
         let (driver, token) = self.locked_ns1655a.lock(token);
 
-        // Wait for device to finish previous transmission
-        loop {
-            let lsr: u8 = driver
+        // Check for received data (the interrupt line may be shared with other devices)
+        let lsr: u8 = driver
+            .config_space
+            .load(RegisterOffset::LSR as usize)
+            .unwrap();
+        if (lsr & (1 << LSRBitOffset::RHRNonEmpty as usize)) == 0 {
+            let token = driver.unlock(token);
+            return (PrologueResult::Unclaimed, token);
+        }
+
+        // Read received bytes (up to the size of the FIFO)
+        let mut bytes = [0u8; FIFO_SIZE];
+        let mut count = 0;
+        let mut lsr = lsr;
+        while count < FIFO_SIZE && (lsr & (1 << LSRBitOffset::RHRNonEmpty as usize)) != 0 {
+            bytes[count] = driver
+                .config_space
+                .load(RegisterOffset::RHR as usize)
+                .unwrap();
+            count += 1;
+            lsr = driver
                 .config_space
                 .load(RegisterOffset::LSR as usize)
                 .unwrap();
-            if (lsr & (1 << LSRBitOffset::RHRNonEmpty as usize)) != 0 {
-                break;
-            }
         }
 
-        // Read key
-        let raw_key: u8 = driver
-            .config_space
-            .load(RegisterOffset::RHR as usize)
-            .unwrap();
-
         // Unlock driver
         let token = driver.unlock(token);
 
-        // Save key
-        self.raw_key.store(raw_key as u16, Ordering::Relaxed);
+        // Buffer received bytes (moved into the input buffer by the epilogue)
+        let (mut received, token) = self.received.lock(token);
+        for byte in &bytes[..count] {
+            received.push(*byte);
+        }
+        let token = received.unlock(token);
+
+        (PrologueResult::EpilogueRequired, token)
+    }
 
-        (false, token)
+    fn epilogue(&self, state: Option<&mut TrapContext>, token: LevelEpilogue) -> LevelEpilogue {
+        // Ignore state
+        let _ = state;
+
+        // Move bytes received by the prologue into the input buffer
+        let (mut input, token) = self.input.lock(token);
+        let adapter = AdapterDriverPrologue::new();
+        let (guard, t) = adapter.enter(token);
+        let (mut received, t) = self.received.lock(t);
+        while let Some(byte) = received.pop() {
+            input.push(byte);
+        }
+        let token = guard.leave(received.unlock(t));
+        input.unlock(token)
     }
 }
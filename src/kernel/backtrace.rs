@@ -0,0 +1,86 @@
+//! Backtraces based on frame pointers.
+//!
+//! The kernel is compiled with frame pointers (`-Cforce-frame-pointers=yes`): `fp` (`s0`) refers
+//! to the stack pointer at function entry, while the return address and the previous frame
+//! pointer are stored right below it (at `fp - 8` and `fp - 16`). The chain is terminated by a
+//! zero frame pointer (see `head.S`).
+
+use core::arch::asm;
+use core::fmt::Display;
+
+use crate::mm::mapping::KERNEL_SPACE_START;
+
+/// Maximum number of recorded return addresses.
+pub const MAX_DEPTH: usize = 8;
+
+/// Return addresses of the active function calls (innermost first).
+#[derive(Debug, Clone, Copy)]
+pub struct Backtrace {
+    frames: [usize; MAX_DEPTH],
+    len: usize,
+}
+
+impl Backtrace {
+    /// Create an empty backtrace.
+    pub const fn empty() -> Self {
+        Self {
+            frames: [0; MAX_DEPTH],
+            len: 0,
+        }
+    }
+
+    /// Capture backtrace of the calling function (starting with its return address).
+    #[inline(always)]
+    pub fn capture() -> Self {
+        let mut fp: usize;
+        unsafe {
+            asm!(
+                "mv {fp}, s0",
+                fp = out(reg) fp,
+            );
+        }
+
+        let mut backtrace = Self::empty();
+        while backtrace.len < MAX_DEPTH {
+            // Stop at end of chain (or on corrupted frame pointers)
+            if fp < KERNEL_SPACE_START || fp % 8 != 0 {
+                break;
+            }
+
+            let (ra, prev_fp) = unsafe {
+                let frame = fp as *const usize;
+                (frame.sub(1).read(), frame.sub(2).read())
+            };
+            if ra == 0 {
+                break;
+            }
+            backtrace.frames[backtrace.len] = ra;
+            backtrace.len += 1;
+
+            // Stacks grow downwards, thus callers have higher frame pointers
+            if prev_fp <= fp {
+                break;
+            }
+            fp = prev_fp;
+        }
+
+        backtrace
+    }
+
+    /// Get recorded return addresses (innermost first).
+    pub fn frames(&self) -> &[usize] {
+        &self.frames[..self.len]
+    }
+}
+
+impl Display for Backtrace {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (i, frame) in self.frames().iter().enumerate() {
+            if i != 0 {
+                write!(f, " <- ")?;
+            }
+            write!(f, "{:#x}", frame)?;
+        }
+        Ok(())
+    }
+}
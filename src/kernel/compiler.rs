@@ -1,6 +1,7 @@
 //! Information provided by compiler/linker.
 
 use core::ffi::c_void;
+use core::ptr;
 
 use crate::kernel::address::Address;
 use crate::kernel::address::PhysicalAddress;
@@ -36,6 +37,9 @@ extern "C" {
 
     static mut __phys_pages_start: c_void;
     static mut __phys_pages_end: c_void;
+
+    static mut __boot_page_tables_start: c_void;
+    static mut __boot_page_tables_end: c_void;
 }
 
 /// Get the virtual address of the start of the `.text` segment.
@@ -162,3 +166,13 @@ pub fn pages_mem_phys_start() -> PhysicalAddress<c_void> {
 pub fn pages_mem_phys_end() -> PhysicalAddress<c_void> {
     return PhysicalAddress::from(unsafe { &mut __phys_pages_end as *mut c_void });
 }
+
+/// Get the virtual address of the start of the boot page tables (see `head.S`).
+pub fn boot_page_tables_virt_start() -> VirtualAddress<c_void> {
+    return VirtualAddress::from(ptr::addr_of_mut!(__boot_page_tables_start));
+}
+
+/// Get the virtual address of the end of the boot page tables (see `head.S`).
+pub fn boot_page_tables_virt_end() -> VirtualAddress<c_void> {
+    return VirtualAddress::from(ptr::addr_of_mut!(__boot_page_tables_end));
+}
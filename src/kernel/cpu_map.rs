@@ -155,12 +155,18 @@ pub fn online_harts() -> usize {
     CPU_MAP.as_ref().idx
 }
 
+/// Check if the CPU map was already initialized.
+pub fn is_initialized() -> bool {
+    CPU_MAP.is_initialized()
+}
+
 /// Return iterator over all registered harts, yielding [`LogicalCPUID`]s and [`HartID`]s.
 pub fn iter() -> impl Iterator<Item = (LogicalCPUID, HartID)> {
-    CPU_MAP
-        .as_ref()
+    let cpu_map = CPU_MAP.as_ref();
+    cpu_map
         .map
         .iter()
+        .take(cpu_map.idx)
         .enumerate()
         .map(|(i, hart_id)| (LogicalCPUID::new(i), *hart_id))
 }
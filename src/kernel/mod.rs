@@ -1,6 +1,7 @@
 //! Kernel Internals.
 
 pub mod address;
+pub mod backtrace;
 pub mod boot_ap;
 pub mod compiler;
 pub mod cpu;
@@ -148,26 +148,37 @@ impl Printer {
         formatter.write_fmt(args)?;
 
         // Step 4: Proceed with actual output using UART driver.
+        let result = self.output(&buffer[..*len]);
+        *len = 0;
+
+        result
+    }
+
+    /// Output raw `bytes` (e.g. on behalf of user mode) without formatting.
+    pub fn write_bytes(&self, bytes: &[u8]) -> Result<(), Error> {
+        self.output(bytes)
+    }
+
+    /// Output `bytes` using UART driver (serialized among all harts).
+    fn output(&self, bytes: &[u8]) -> Result<(), Error> {
         let interrupts_enabled = cpu::interrupts_enabled();
         unsafe { cpu::disable_interrupts() };
         let ticket = self.ticket.fetch_add(1, Ordering::Relaxed);
         while ticket != self.serving.load(Ordering::Acquire) {
             hint::spin_loop();
         }
-        for i in 0..*len {
-            unsafe {
-                UART.as_ref()
-                    .write_unchecked(buffer[i])
-                    .map_err(|_| Error::default())?
-            };
-        }
-        *len = 0;
+        // Stop at the first failed byte, but always release the ticket and restore interrupts
+        let result = bytes.iter().try_for_each(|byte| unsafe {
+            UART.as_ref()
+                .write_unchecked(*byte)
+                .map_err(|_| Error::default())
+        });
         self.serving.fetch_add(1, Ordering::Release);
         if interrupts_enabled {
             unsafe { cpu::enable_interrupts() };
         }
 
-        Ok(())
+        result
     }
 }
 
@@ -3,12 +3,15 @@
 
 use core::arch::asm;
 use core::error::Error;
+use core::ffi::c_void;
 use core::fmt::Display;
 
 use crate::kernel;
 use crate::kernel::address::Address;
 use crate::kernel::sbi::SBIFunctionID::BaseExtension;
 use crate::kernel::sbi::SBIFunctionID::HartStateManagementExtension;
+use crate::kernel::sbi::SBIFunctionID::IPIExtension;
+use crate::kernel::sbi::SBIFunctionID::RemoteFenceExtension;
 
 /// Perform `ECALL` for OpenSBI firmware without any arguments.
 ///
@@ -61,6 +64,38 @@ fn sbi_ecall_1(eid: SBIExtensionID, fid: SBIFunctionID, arg0: isize) -> Result<i
     return Ok(value as isize);
 }
 
+/// Perform `ECALL` for OpenSBI firmware with two arguments.
+///
+/// * `eid`: Extension ID.
+/// * `fid`: Function ID.
+/// * `arg0`: First argument.
+/// * `arg1`: Second argument.
+fn sbi_ecall_2(
+    eid: SBIExtensionID,
+    fid: SBIFunctionID,
+    arg0: isize,
+    arg1: isize,
+) -> Result<isize, SBIError> {
+    /* Perform ecall */
+    let mut error = arg0 as isize;
+    let mut value = arg1 as isize;
+    unsafe {
+        asm!(
+            "ecall",
+            inout("a0") error,
+            inout("a1") value,
+            in("a7") isize::from(eid),
+            in("a6") isize::from(fid),
+        );
+    }
+
+    if error != 0 {
+        return Err(SBIError::from(error));
+    }
+
+    return Ok(value as isize);
+}
+
 /// Perform `ECALL` for OpenSBI firmware with a three arguments.
 ///
 /// * `eid`: Extension ID.
@@ -96,6 +131,85 @@ fn sbi_ecall_3(
     return Ok(value as isize);
 }
 
+/// Perform `ECALL` for OpenSBI firmware with four arguments.
+///
+/// * `eid`: Extension ID.
+/// * `fid`: Function ID.
+/// * `arg0`: First argument.
+/// * `arg1`: Second argument.
+/// * `arg2`: Third argument.
+/// * `arg3`: Fourth argument.
+fn sbi_ecall_4(
+    eid: SBIExtensionID,
+    fid: SBIFunctionID,
+    arg0: isize,
+    arg1: isize,
+    arg2: isize,
+    arg3: isize,
+) -> Result<isize, SBIError> {
+    /* Perform ecall */
+    let mut error = arg0 as isize;
+    let mut value = arg1 as isize;
+    unsafe {
+        asm!(
+            "ecall",
+            inout("a0") error,
+            inout("a1") value,
+            in("a2") arg2,
+            in("a3") arg3,
+            in("a7") isize::from(eid),
+            in("a6") isize::from(fid),
+        );
+    }
+
+    if error != 0 {
+        return Err(SBIError::from(error));
+    }
+
+    return Ok(value as isize);
+}
+
+/// Perform `ECALL` for OpenSBI firmware with five arguments.
+///
+/// * `eid`: Extension ID.
+/// * `fid`: Function ID.
+/// * `arg0`: First argument.
+/// * `arg1`: Second argument.
+/// * `arg2`: Third argument.
+/// * `arg3`: Fourth argument.
+/// * `arg4`: Fifth argument.
+fn sbi_ecall_5(
+    eid: SBIExtensionID,
+    fid: SBIFunctionID,
+    arg0: isize,
+    arg1: isize,
+    arg2: isize,
+    arg3: isize,
+    arg4: isize,
+) -> Result<isize, SBIError> {
+    /* Perform ecall */
+    let mut error = arg0 as isize;
+    let mut value = arg1 as isize;
+    unsafe {
+        asm!(
+            "ecall",
+            inout("a0") error,
+            inout("a1") value,
+            in("a2") arg2,
+            in("a3") arg3,
+            in("a4") arg4,
+            in("a7") isize::from(eid),
+            in("a6") isize::from(fid),
+        );
+    }
+
+    if error != 0 {
+        return Err(SBIError::from(error));
+    }
+
+    return Ok(value as isize);
+}
+
 /// SBI Errors
 ///
 /// # See
@@ -170,11 +284,17 @@ impl From<isize> for SBIError {
 /// # See
 /// - Section `Chapter 3. Binary Encoding` of `RISC-V Supervisor Binary Interface Specification`
 /// - Section `Chapter 4. Base Extension (EID #0x10)` of `RISC-V Supervisor Binary Interface Specification`
+/// - Section `Chapter 7. IPI Extension (EID #0x735049 "sPI: s-mode IPI")` of `RISC-V Supervisor Binary Interface Specification`
+/// - Section `Chapter 8. RFENCE Extension (EID #0x52464E43 "RFNC")` of `RISC-V Supervisor Binary Interface Specification`
 /// - Section `Chapter 9. Hart State Management Extension (EID #0x48534D "HSM")` of `RISC-V Supervisor Binary Interface Specification`
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum SBIExtensionID {
     /// Functionality for probing availability/version of SBI extensions.
     BaseExtension = 0x10,
+    /// Functionality for sending inter-processor interrupts.
+    IPI = 0x735049,
+    /// Functionality for executing fence instructions on remote harts.
+    RemoteFence = 0x52464e43,
     /// Functionality for requesting hart state changes.
     HartStateManagement = 0x48534d,
 }
@@ -183,6 +303,8 @@ impl Display for SBIExtensionID {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             SBIExtensionID::BaseExtension => write!(f, "Base Extension"),
+            SBIExtensionID::IPI => write!(f, "IPI Extension"),
+            SBIExtensionID::RemoteFence => write!(f, "RFENCE Extension"),
             SBIExtensionID::HartStateManagement => write!(f, "Hart State Management Extension"),
         }
     }
@@ -203,6 +325,10 @@ pub enum SBIFunctionID {
     /// Functionality for probing which SBI extensions are available and for querying the version
     /// of the SBI.
     BaseExtension(SBIBaseFunctionID),
+    /// Functionality for sending inter-processor interrupts to a set of harts.
+    IPIExtension(SBIIPIFunctionID),
+    /// Functionality for executing remote fence instructions on a set of harts.
+    RemoteFenceExtension(SBIRFENCEFunctionID),
     /// Functionality for allowing the supervisor-mode software to request a hart state change.
     HartStateManagementExtension(SBIHSMFunctionID),
 }
@@ -211,6 +337,8 @@ impl Display for SBIFunctionID {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             SBIFunctionID::BaseExtension(id) => write!(f, "{}", id),
+            SBIFunctionID::IPIExtension(id) => write!(f, "{}", id),
+            SBIFunctionID::RemoteFenceExtension(id) => write!(f, "{}", id),
             SBIFunctionID::HartStateManagementExtension(id) => write!(f, "{}", id),
         }
     }
@@ -220,6 +348,8 @@ impl From<SBIFunctionID> for isize {
     fn from(value: SBIFunctionID) -> Self {
         match value {
             BaseExtension(extension) => isize::from(extension),
+            IPIExtension(extension) => isize::from(extension),
+            RemoteFenceExtension(extension) => isize::from(extension),
             HartStateManagementExtension(extension) => isize::from(extension),
         }
     }
@@ -252,6 +382,62 @@ impl From<SBIBaseFunctionID> for isize {
     }
 }
 
+/// SBI Function ID (`FID`) for IPI Extension
+///
+/// # See
+/// Section `Chapter 7. IPI Extension (EID #0x735049 "sPI: s-mode IPI")` of `RISC-V Supervisor Binary Interface Specification`
+#[derive(Debug, Copy, Clone)]
+pub enum SBIIPIFunctionID {
+    /// Send an inter-processor interrupt to all the harts defined in `hart_mask`.
+    SendIPI = 0x00,
+}
+
+impl Display for SBIIPIFunctionID {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SBIIPIFunctionID::SendIPI => write!(f, "Send IPI"),
+        }
+    }
+}
+
+impl From<SBIIPIFunctionID> for isize {
+    fn from(value: SBIIPIFunctionID) -> Self {
+        value as isize
+    }
+}
+
+/// SBI Function ID (`FID`) for RFENCE Extension
+///
+/// # See
+/// Section `Chapter 8. RFENCE Extension (EID #0x52464E43 "RFNC")` of `RISC-V Supervisor Binary Interface Specification`
+#[derive(Debug, Copy, Clone)]
+pub enum SBIRFENCEFunctionID {
+    /// Instruct remote harts to execute `FENCE.I` instruction.
+    RemoteFenceI = 0x00,
+    /// Instruct remote harts to execute one or more `SFENCE.VMA` instructions.
+    RemoteSFenceVMA = 0x01,
+    /// Instruct remote harts to execute one or more `SFENCE.VMA` instructions (restricted to a single ASID).
+    RemoteSFenceVMAASID = 0x02,
+}
+
+impl Display for SBIRFENCEFunctionID {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SBIRFENCEFunctionID::RemoteFenceI => write!(f, "Remote FENCE.I"),
+            SBIRFENCEFunctionID::RemoteSFenceVMA => write!(f, "Remote SFENCE.VMA"),
+            SBIRFENCEFunctionID::RemoteSFenceVMAASID => {
+                write!(f, "Remote SFENCE.VMA with ASID")
+            }
+        }
+    }
+}
+
+impl From<SBIRFENCEFunctionID> for isize {
+    fn from(value: SBIRFENCEFunctionID) -> Self {
+        value as isize
+    }
+}
+
 /// SBI Function ID (`FID`) for Hart State Management Extension
 ///
 /// # See
@@ -452,3 +638,158 @@ pub fn status_hart(hart_id: kernel::cpu_map::HartID) -> Result<SBIHartState, SBI
         }
     };
 }
+
+/// Set of harts addressed by IPI and RFENCE functions.
+///
+/// The set is encoded as a bit-vector `mask` of (at most) `XLEN` harts, starting with hart ID
+/// `base`.
+///
+/// # See
+/// Section `3.1 Hart List Parameter` of `RISC-V Supervisor Binary Interface Specification`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HartMask {
+    mask: usize,
+    base: usize,
+}
+
+impl HartMask {
+    /// Create empty hart mask starting at `base`.
+    pub const fn new(base: kernel::cpu_map::HartID) -> Self {
+        Self {
+            mask: 0,
+            base: base.raw() as usize,
+        }
+    }
+
+    /// Create hart mask addressing all available harts.
+    pub const fn all() -> Self {
+        Self {
+            mask: 0,
+            base: usize::MAX,
+        }
+    }
+
+    /// Try to add `hart_id` to the mask. Return `false` if it can not be represented.
+    pub fn insert(&mut self, hart_id: kernel::cpu_map::HartID) -> bool {
+        let hart_id = hart_id.raw() as usize;
+        if self.base == usize::MAX
+            || hart_id < self.base
+            || hart_id - self.base >= usize::BITS as usize
+        {
+            return false;
+        }
+
+        self.mask |= 1 << (hart_id - self.base);
+        return true;
+    }
+
+    /// Check if the mask addresses no harts at all.
+    pub const fn is_empty(&self) -> bool {
+        self.base != usize::MAX && self.mask == 0
+    }
+}
+
+/// Send an inter-processor interrupt (supervisor software interrupt) to all harts within `hart_mask`.
+///
+/// * `hart_mask`: Target harts.
+pub fn send_ipi(hart_mask: HartMask) -> Result<(), SBIError> {
+    match sbi_ecall_2(
+        SBIExtensionID::IPI,
+        SBIFunctionID::IPIExtension(SBIIPIFunctionID::SendIPI),
+        hart_mask.mask as isize,
+        hart_mask.base as isize,
+    ) {
+        Ok(_) => {
+            return Ok(());
+        }
+
+        Err(err) => {
+            return Err(err);
+        }
+    };
+}
+
+/// Instruct all harts within `hart_mask` to execute `FENCE.I`.
+///
+/// * `hart_mask`: Target harts.
+pub fn remote_fence_i(hart_mask: HartMask) -> Result<(), SBIError> {
+    match sbi_ecall_2(
+        SBIExtensionID::RemoteFence,
+        SBIFunctionID::RemoteFenceExtension(SBIRFENCEFunctionID::RemoteFenceI),
+        hart_mask.mask as isize,
+        hart_mask.base as isize,
+    ) {
+        Ok(_) => {
+            return Ok(());
+        }
+
+        Err(err) => {
+            return Err(err);
+        }
+    };
+}
+
+/// Instruct all harts within `hart_mask` to execute `SFENCE.VMA` covering the virtual address
+/// range `[start_addr, start_addr + size)`.
+///
+/// A `size` of `usize::MAX` requests a flush of the entire address space.
+///
+/// * `hart_mask`: Target harts.
+/// * `start_addr`: Start of virtual address range.
+/// * `size`: Size of virtual address range.
+pub fn remote_sfence_vma(
+    hart_mask: HartMask,
+    start_addr: kernel::address::VirtualAddress<c_void>,
+    size: usize,
+) -> Result<(), SBIError> {
+    match sbi_ecall_4(
+        SBIExtensionID::RemoteFence,
+        SBIFunctionID::RemoteFenceExtension(SBIRFENCEFunctionID::RemoteSFenceVMA),
+        hart_mask.mask as isize,
+        hart_mask.base as isize,
+        start_addr.addr() as isize,
+        size as isize,
+    ) {
+        Ok(_) => {
+            return Ok(());
+        }
+
+        Err(err) => {
+            return Err(err);
+        }
+    };
+}
+
+/// Instruct all harts within `hart_mask` to execute `SFENCE.VMA` covering the virtual address
+/// range `[start_addr, start_addr + size)` restricted to address space `asid`.
+///
+/// A `size` of `usize::MAX` requests a flush of the entire address space.
+///
+/// * `hart_mask`: Target harts.
+/// * `start_addr`: Start of virtual address range.
+/// * `size`: Size of virtual address range.
+/// * `asid`: Address space identifier.
+pub fn remote_sfence_vma_asid(
+    hart_mask: HartMask,
+    start_addr: kernel::address::VirtualAddress<c_void>,
+    size: usize,
+    asid: usize,
+) -> Result<(), SBIError> {
+    match sbi_ecall_5(
+        SBIExtensionID::RemoteFence,
+        SBIFunctionID::RemoteFenceExtension(SBIRFENCEFunctionID::RemoteSFenceVMAASID),
+        hart_mask.mask as isize,
+        hart_mask.base as isize,
+        start_addr.addr() as isize,
+        size as isize,
+        asid as isize,
+    ) {
+        Ok(_) => {
+            return Ok(());
+        }
+
+        Err(err) => {
+            return Err(err);
+        }
+    };
+}
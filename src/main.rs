@@ -5,6 +5,8 @@
 #![warn(missing_docs)]
 #![feature(error_in_core)]
 
+extern crate alloc;
+
 use core::panic::PanicInfo;
 
 use boot::device_tree::dt::DeviceTree;
@@ -89,6 +91,16 @@ pub extern "C" fn kernel_init(hart_id: u64, dtb_ptr: *const u8, dtb_size: u32) -
         unsafe { DeviceTree::initialize(dtb_ptr, dtb_size, level_initialization) };
     assert!(device_tree.get_cpu_count() < config::MAX_CPU_NUM);
 
+    // Detect optional paging extensions (as described by device tree)
+    let level_initialization = mm::mapping::detect_extensions(level_initialization);
+
+    // Detect DMA configuration (as described by device tree)
+    let level_initialization = mm::dma::initialize(level_initialization);
+
+    // Manage remaining physical memory (as described by device tree)
+    let level_initialization =
+        mm::page_allocator::PageFrameAllocator::discover(level_initialization);
+
     // Check availability of OpenSBI by querying specification version
     if let Err(error) = kernel::sbi::specification_version() {
         panic!("Unable to query OpenSBI version: {}", error);
@@ -106,6 +118,18 @@ pub extern "C" fn kernel_init(hart_id: u64, dtb_ptr: *const u8, dtb_size: u32) -
         panic!("OpenSBI HSM Extension: Unsupported!\n");
     }
 
+    // Check for OpenSBI RFENCE Extension
+    let sbi_rfence_state =
+        match kernel::sbi::probe_extension(kernel::sbi::SBIExtensionID::RemoteFence) {
+            Ok(state) => state,
+            Err(error) => {
+                panic!("Unable to state of OpenSBI RFENCE extension: {}", error);
+            }
+        };
+    if !sbi_rfence_state {
+        panic!("OpenSBI RFENCE Extension: Unsupported!\n");
+    }
+
     // Initialize CPU map
     let level_initialization = kernel::cpu_map::initialize(level_initialization);
 
@@ -146,6 +170,29 @@ pub extern "C" fn kernel_init(hart_id: u64, dtb_ptr: *const u8, dtb_size: u32) -
         Err((error, _)) => panic!("Unable to initialize timer driver: {}!", error),
     };
 
+    // Initialize page fault handler
+    let level_initialization =
+        match mm::page_fault::PageFaultHandler::initiailize(level_initialization) {
+            Ok(token) => token,
+            Err((error, _)) => panic!("Unable to initialize page fault handler: {}!", error),
+        };
+
+    // Detect floating-point and vector units (used by user threads)
+    let level_initialization = trap::user::initialize(level_initialization);
+
+    // Initialize system calls
+    let level_initialization =
+        match trap::syscall::SyscallHandler::initiailize(level_initialization) {
+            Ok(token) => token,
+            Err((error, _)) => panic!("Unable to initialize system calls: {}!", error),
+        };
+
+    // Initialize page reclamation (without backing store, i.e. only clean pages are reclaimed)
+    let level_initialization = mm::reclaim::initialize(None, level_initialization);
+
+    // Finalize system calls **after** registration of all system calls
+    let level_initialization = trap::syscall::Syscalls::finalize(level_initialization);
+
     // Finalize trap handlers **after** initialization of drivers
     let level_initialization = trap::handlers::TrapHandlers::finalize(level_initialization);
 
@@ -155,6 +202,17 @@ pub extern "C" fn kernel_init(hart_id: u64, dtb_ptr: *const u8, dtb_size: u32) -
         Err((error, _)) => panic!("Unable to initialize global printer: {}!", error),
     };
 
+    // Report fallback to smaller paging mode (if requested mode is unsupported)
+    let paging_mode = arch::satp::PagingMode::from_virtual_address_bits(config::PAGING_MODE);
+    if paging_mode != Some(mm::mapping::paging_mode()) {
+        printk!(
+            kernel::printer::LogLevel::Warn,
+            "Paging mode {:?} unsupported, falling back to {:?}\n",
+            paging_mode.unwrap(),
+            mm::mapping::paging_mode()
+        );
+    }
+
     // Boot application processors
     kernel::boot_ap::startup(level_initialization);
 
@@ -170,6 +228,12 @@ pub extern "C" fn kernel_init(hart_id: u64, dtb_ptr: *const u8, dtb_size: u32) -
     // Synchronize with remaining harts
     let level_epilogue = synchronize(level_epilogue);
 
+    // Tear down boot mapping and seal `.text`/`.rodata` (all harts use the fine-grained mapping)
+    // # Safety
+    // Each hart loaded the fine-grained kernel mapping before synchronizing.
+    unsafe { mm::mapping::VirtualMemorySystem::teardown_boot_mapping() };
+    mm::mapping::seal_kernel_sections();
+
     // Enable timer interrupts
     let level_epilogue = {
         let adapter = sync::level::AdapterEpilogueDriver::new();
@@ -184,6 +248,11 @@ pub extern "C" fn kernel_init(hart_id: u64, dtb_ptr: *const u8, dtb_size: u32) -
         kernel::cpu::current()
     );
 
+    // Start user test program (if configured)
+    if config::USER_TEST {
+        trap::user_test::start(level_epilogue);
+    }
+
     loop {}
 }
 
@@ -0,0 +1,221 @@
+//! Allocation of address space identifiers (ASIDs).
+//!
+//! ASIDs are assigned lazily, whenever a [`VirtualMemorySystem`] is activated on a hart, and are
+//! tagged with a generation (together forming a *context*). Once all ASIDs of the current
+//! generation are used up, a new generation is started: ASIDs currently loaded on any hart stay
+//! reserved, while every hart has to flush its address-translation caches before loading the next
+//! address space.
+//!
+//! ASID `0` is reserved for the [`KERNEL_VIRTUAL_MEMORY_SYSTEM`].
+//!
+//! [`VirtualMemorySystem`]: crate::mm::mapping::VirtualMemorySystem
+//! [`KERNEL_VIRTUAL_MEMORY_SYSTEM`]: crate::mm::mapping::KERNEL_VIRTUAL_MEMORY_SYSTEM
+
+use core::mem;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::arch::csr::CSR;
+use crate::arch::satp::SATP;
+use crate::config;
+use crate::kernel::cpu;
+use crate::sync::init_cell::InitCell;
+use crate::sync::level::{LevelInitialization, LevelMapping};
+use crate::sync::ticketlock::TicketlockMapping;
+
+/// Maximum number of ASID bits (width of `satp.ASID`).
+const MAX_ASID_BITS: usize = 16;
+
+/// ASID of the [`KERNEL_VIRTUAL_MEMORY_SYSTEM`](crate::mm::mapping::KERNEL_VIRTUAL_MEMORY_SYSTEM).
+pub const KERNEL_ASID: usize = 0;
+
+/// Context of an address space without ASID (assigned on first activation).
+pub const NO_CONTEXT: u64 = 0;
+
+/// Context of the [`KERNEL_VIRTUAL_MEMORY_SYSTEM`](crate::mm::mapping::KERNEL_VIRTUAL_MEMORY_SYSTEM)
+/// (pinned to [`KERNEL_ASID`]).
+pub const KERNEL_CONTEXT: u64 = u64::MAX;
+
+/// Number of ASID bits supported by the hardware (as detected during boot).
+static ASID_BITS: InitCell<usize> = InitCell::new();
+
+/// Global ASID allocator.
+static ASID_ALLOCATOR: TicketlockMapping<AsidAllocator> =
+    TicketlockMapping::new(AsidAllocator::new());
+
+/// Detect number of supported ASID bits by writing all-ones to `satp.ASID`.
+///
+/// If there are too few ASIDs to reserve one per hart during a generation rollover, ASIDs are
+/// treated as unsupported.
+pub fn initialize(token: LevelInitialization) -> LevelInitialization {
+    // Step 1: Write all-ones and read back implemented bits
+    let mut satp = SATP::new(0);
+    satp.read();
+    let original = satp;
+    satp.set_asid((1 << MAX_ASID_BITS) - 1);
+    satp.write();
+    satp.read();
+    original.write();
+
+    // Step 2: Check whether enough ASIDs are available
+    let mut bits = satp.get_asid().count_ones() as usize;
+    if (1 << bits) <= config::MAX_CPU_NUM + 1 {
+        bits = 0;
+    }
+
+    let mut asid_bits = ASID_BITS.get_mut(token);
+    *asid_bits = bits;
+    let token = asid_bits.destroy();
+    unsafe { ASID_BITS.finanlize(token) }
+}
+
+/// Get number of supported ASID bits (`0`, if ASIDs are unsupported).
+pub fn asid_bits() -> usize {
+    *ASID_BITS.as_ref()
+}
+
+/// Check if ASIDs are supported.
+pub fn is_supported() -> bool {
+    asid_bits() > 0
+}
+
+/// Get ASID of `context` (`None`, if no ASID was assigned yet).
+pub fn asid(context: u64) -> Option<usize> {
+    match context {
+        NO_CONTEXT => None,
+        KERNEL_CONTEXT => Some(KERNEL_ASID),
+        context => Some((context & ((1 << MAX_ASID_BITS) - 1)) as usize),
+    }
+}
+
+/// Get generation of `context`.
+fn generation(context: u64) -> u64 {
+    context >> MAX_ASID_BITS
+}
+
+/// Activate address space with `context` on the current hart.
+///
+/// If the context stems from an older generation, a new ASID is assigned (and stored in
+/// `context`). On success, the ASID to load is returned together with a flag indicating whether
+/// the local address-translation caches have to be flushed (after a rollover).
+pub fn activate(context: &AtomicU64, token: LevelMapping) -> (usize, bool, LevelMapping) {
+    let (mut allocator, token) = ASID_ALLOCATOR.lock(token);
+    let hart = cpu::current().raw();
+
+    // Step 1: Check for pinned kernel context (no flush required, as ASID `0` is never reused)
+    let mut current = context.load(Ordering::Relaxed);
+    if current == KERNEL_CONTEXT {
+        let token = allocator.unlock(token);
+        return (KERNEL_ASID, false, token);
+    }
+
+    // Step 2: Assign ASID of current generation
+    if generation(current) != allocator.generation {
+        current = allocator.new_context(current);
+        context.store(current, Ordering::Relaxed);
+    }
+
+    // Step 3: Mark as active (and consume pending flush)
+    allocator.active[hart] = current;
+    let flush = mem::replace(&mut allocator.flush_pending[hart], false);
+
+    let token = allocator.unlock(token);
+    (asid(current).unwrap(), flush, token)
+}
+
+/// Generation-based ASID allocator.
+struct AsidAllocator {
+    /// Current generation (starting at `1`).
+    generation: u64,
+    /// Bitmap of ASIDs used within the current generation.
+    used: [u64; (1 << MAX_ASID_BITS) / 64],
+    /// Next ASID to check for allocation.
+    cursor: usize,
+    /// Context last activated per hart (since the last rollover).
+    active: [u64; config::MAX_CPU_NUM],
+    /// Context reserved per hart during the last rollover.
+    reserved: [u64; config::MAX_CPU_NUM],
+    /// Harts which have to flush their address-translation caches on next activation.
+    flush_pending: [bool; config::MAX_CPU_NUM],
+}
+
+impl AsidAllocator {
+    const fn new() -> Self {
+        // Reserve ASID of kernel
+        let mut used = [0; (1 << MAX_ASID_BITS) / 64];
+        used[KERNEL_ASID / 64] = 1 << (KERNEL_ASID % 64);
+
+        Self {
+            generation: 1,
+            used,
+            cursor: 1,
+            active: [NO_CONTEXT; config::MAX_CPU_NUM],
+            reserved: [NO_CONTEXT; config::MAX_CPU_NUM],
+            flush_pending: [false; config::MAX_CPU_NUM],
+        }
+    }
+
+    /// Create context of current generation for address space with `old` context.
+    fn new_context(&mut self, old: u64) -> u64 {
+        // Step 1: Keep ASID reserved during rollover
+        if let Some(asid) = asid(old) {
+            let new = (self.generation << MAX_ASID_BITS) | asid as u64;
+
+            let mut is_reserved = false;
+            for reserved in self.reserved.iter_mut() {
+                if *reserved == old {
+                    *reserved = new;
+                    is_reserved = true;
+                }
+            }
+            if is_reserved {
+                return new;
+            }
+        }
+
+        // Step 2: Allocate free ASID (starting a new generation if exhausted)
+        let asid = match self.allocate() {
+            Some(asid) => asid,
+            None => {
+                self.rollover();
+                self.allocate().unwrap()
+            }
+        };
+
+        (self.generation << MAX_ASID_BITS) | asid as u64
+    }
+
+    /// Allocate unused ASID of the current generation.
+    fn allocate(&mut self) -> Option<usize> {
+        let num_asids = 1 << asid_bits();
+        let asid = (self.cursor..num_asids)
+            .chain(1..self.cursor)
+            .find(|asid| self.used[asid / 64] & (1 << (asid % 64)) == 0)?;
+
+        self.used[asid / 64] |= 1 << (asid % 64);
+        self.cursor = asid + 1;
+        Some(asid)
+    }
+
+    /// Start a new generation.
+    ///
+    /// The ASIDs activated on each hart are kept (or the previous reservation, if a hart did not
+    /// activate any address space since the last rollover), while all other ASIDs become free.
+    fn rollover(&mut self) {
+        self.generation += 1;
+        self.used.fill(0);
+        self.used[KERNEL_ASID / 64] |= 1 << (KERNEL_ASID % 64);
+        self.cursor = 1;
+
+        for hart in 0..config::MAX_CPU_NUM {
+            let active = mem::replace(&mut self.active[hart], NO_CONTEXT);
+            if active != NO_CONTEXT {
+                self.reserved[hart] = active;
+            }
+            if let Some(asid) = asid(self.reserved[hart]) {
+                self.used[asid / 64] |= 1 << (asid % 64);
+            }
+
+            self.flush_pending[hart] = true;
+        }
+    }
+}
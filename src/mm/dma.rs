@@ -0,0 +1,393 @@
+//! Buffers for direct memory access (DMA) of devices.
+//!
+//! Coherent buffers (see [`alloc_coherent`]) consist of physically contiguous page frames and are
+//! shared between the kernel and a device for their whole lifetime. Streaming mappings (see
+//! [`map`]) expose existing (physically contiguous) buffers to a device for a single transfer.
+//!
+//! Physical addresses are translated into bus addresses according to the `dma-ranges` of the
+//! device tree (or identity-mapped, if there are none). If DMA is not coherent with the caches of
+//! the harts, caches are maintained by `Zicbom` instructions (if supported), while coherent buffers
+//! are additionally mapped as [`MemoryType::NC`] (if `Svpbmt` is supported).
+
+use core::ffi::c_void;
+use core::sync::atomic::{fence, Ordering};
+
+use crate::arch::cbo;
+use crate::boot::device_tree::dt::DeviceTree;
+use crate::kernel::address::{Address, PhysicalAddress, VirtualAddress};
+use crate::kernel::cpu;
+use crate::mm::error::MemoryError;
+use crate::mm::mapping::{self, MemoryType, KERNEL_VIRTUAL_MEMORY_SYSTEM};
+use crate::mm::page_allocator::{PageFrameAllocator, MAX_ORDER, PAGE_FRAME_ALLOCATOR};
+use crate::mm::vmap::KERNEL_VIRTUAL_RANGES;
+use crate::sync::init_cell::InitCell;
+use crate::sync::level::{
+    Adapter, AdapterGuard, AdapterMappingPaging, LevelInitialization, LevelMapping,
+};
+
+/// Maximum number of considered `dma-ranges` entries.
+const MAX_DMA_RANGES: usize = 8;
+
+/// Size of cache blocks, if `Zicbom` is supported without a `riscv,cbom-block-size` property.
+const DEFAULT_CBOM_BLOCK_SIZE: usize = 64;
+
+/// DMA configuration of the platform (as detected during boot).
+static DMA_CONFIG: InitCell<DmaConfig> = InitCell::new();
+
+/// Translation of `size` bytes of physical memory at `phys` to bus address `bus`.
+#[derive(Debug, Clone, Copy)]
+struct DmaRange {
+    bus: usize,
+    phys: usize,
+    size: usize,
+}
+
+/// DMA configuration of the platform.
+struct DmaConfig {
+    /// Translations to bus addresses (identity, if empty).
+    ranges: [Option<DmaRange>; MAX_DMA_RANGES],
+    /// DMA is coherent with the caches of the harts.
+    coherent: bool,
+    /// Size of cache blocks (`None`, if `Zicbom` is not supported).
+    cbom_block_size: Option<usize>,
+}
+
+/// Detect DMA configuration of the platform (as described by device tree).
+pub fn initialize(token: LevelInitialization) -> LevelInitialization {
+    let (dt, token) = DeviceTree::get_dt(token);
+
+    // Step 1: Collect translations to bus addresses
+    let mut ranges = [None; MAX_DMA_RANGES];
+    for (slot, (bus, phys, size)) in ranges.iter_mut().zip(dt.dma_ranges_iter()) {
+        *slot = Some(DmaRange { bus, phys, size });
+    }
+
+    // Step 2: Detect cache maintenance
+    let cbom_block_size = match dt.has_isa_extension("zicbom") {
+        true => Some(dt.get_cbom_block_size().unwrap_or(DEFAULT_CBOM_BLOCK_SIZE)),
+        false => None,
+    };
+
+    let mut config = DMA_CONFIG.get_mut(token);
+    *config = DmaConfig {
+        ranges,
+        coherent: dt.is_dma_coherent(),
+        cbom_block_size,
+    };
+    let token = config.destroy();
+    unsafe { DMA_CONFIG.finanlize(token) }
+}
+
+/// Address of memory as seen by devices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BusAddress(usize);
+
+impl BusAddress {
+    /// Get raw bus address.
+    pub fn addr(&self) -> usize {
+        self.0
+    }
+}
+
+/// Direction of data transfers of a DMA buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DmaDirection {
+    /// Device reads from memory.
+    ToDevice,
+    /// Device writes to memory.
+    FromDevice,
+    /// Device reads from and writes to memory.
+    Bidirectional,
+}
+
+/// Physically contiguous buffer shared between kernel and devices (see [`alloc_coherent`]).
+#[derive(Debug)]
+pub struct DmaBuffer {
+    virt_addr: VirtualAddress<c_void>,
+    phys_addr: PhysicalAddress<c_void>,
+    bus_addr: BusAddress,
+    num_pages: usize,
+    /// Buffer is mapped by [`KERNEL_VIRTUAL_RANGES`] (instead of the direct map).
+    remapped: bool,
+}
+
+impl DmaBuffer {
+    /// Get kernel-virtual address of the buffer.
+    pub fn virt_addr(&self) -> VirtualAddress<c_void> {
+        self.virt_addr
+    }
+
+    /// Get bus address of the buffer.
+    pub fn bus_addr(&self) -> BusAddress {
+        self.bus_addr
+    }
+
+    /// Get size of the buffer (in bytes).
+    pub fn size(&self) -> usize {
+        self.num_pages * cpu::page_size()
+    }
+
+    /// Make writes of the kernel visible to the device (only required if DMA is not coherent and
+    /// the buffer could not be mapped as [`MemoryType::NC`]).
+    pub fn sync_for_device(&self, direction: DmaDirection) {
+        sync_for_device(self.virt_addr.addr(), self.size(), direction);
+    }
+
+    /// Make writes of the device visible to the kernel (only required if DMA is not coherent and
+    /// the buffer could not be mapped as [`MemoryType::NC`]).
+    pub fn sync_for_cpu(&self, direction: DmaDirection) {
+        sync_for_cpu(self.virt_addr.addr(), self.size(), direction);
+    }
+}
+
+/// Existing buffer exposed to devices for a single transfer (see [`map`]).
+#[derive(Debug)]
+pub struct DmaMapping {
+    virt_addr: VirtualAddress<c_void>,
+    bus_addr: BusAddress,
+    size: usize,
+    direction: DmaDirection,
+}
+
+impl DmaMapping {
+    /// Get bus address of the mapped buffer.
+    pub fn bus_addr(&self) -> BusAddress {
+        self.bus_addr
+    }
+
+    /// Get size of the mapped buffer (in bytes).
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+/// Allocate (zeroed) buffer of `num_pages` physically contiguous pages for DMA.
+///
+/// The buffer is backed by a block of the [`PAGE_FRAME_ALLOCATOR`] (i.e. `num_pages` is rounded
+/// up to the next power of two).
+pub fn alloc_coherent(
+    num_pages: usize,
+    token: LevelMapping,
+) -> Result<(DmaBuffer, LevelMapping), (MemoryError, LevelMapping)> {
+    // Step 1: Allocate contiguous page frames
+    if num_pages == 0 {
+        return Err((MemoryError::InvalidAddress, token));
+    }
+    let order = order(num_pages);
+    if order > MAX_ORDER {
+        return Err((MemoryError::OutOfMemory, token));
+    }
+    let adapter = AdapterMappingPaging::new();
+    let (guard, t) = adapter.enter(token);
+    let (phys_addr, token) = match PAGE_FRAME_ALLOCATOR.allocate_order(order, t) {
+        Ok((phys_addr, t)) => (phys_addr, guard.leave(t)),
+        Err((err, t)) => return Err((err, guard.leave(t))),
+    };
+    let num_pages = 1 << order;
+    let size = num_pages * cpu::page_size();
+
+    // Step 2: Translate to bus address
+    let bus_addr = match phys_to_bus(phys_addr.addr(), size) {
+        Some(bus_addr) => bus_addr,
+        None => {
+            let token = free_pages(phys_addr, order, token);
+            return Err((MemoryError::InvalidAddress, token));
+        }
+    };
+
+    // Step 3: Write back zeroed pages and bypass caches (if DMA is not coherent)
+    let mut virt_addr = PageFrameAllocator::phys_to_virt(phys_addr);
+    let mut remapped = false;
+    let mut token = token;
+    if !DMA_CONFIG.as_ref().coherent {
+        maintain(virt_addr.addr(), size, cbo::cbo_flush::<u8>);
+
+        if mapping::paging_extensions().svpbmt {
+            (virt_addr, token) =
+                match KERNEL_VIRTUAL_RANGES.remap(phys_addr, size, MemoryType::NC, token) {
+                    Ok((virt_addr, token)) => (virt_addr, token),
+                    Err((err, token)) => {
+                        let token = free_pages(phys_addr, order, token);
+                        return Err((err, token));
+                    }
+                };
+            remapped = true;
+        }
+    }
+
+    let buffer = DmaBuffer {
+        virt_addr,
+        phys_addr,
+        bus_addr,
+        num_pages,
+        remapped,
+    };
+    Ok((buffer, token))
+}
+
+/// Free `buffer` allocated by [`alloc_coherent`].
+///
+/// The device must not access the buffer anymore.
+pub fn free_coherent(buffer: DmaBuffer, token: LevelMapping) -> LevelMapping {
+    let token = match buffer.remapped {
+        true => KERNEL_VIRTUAL_RANGES
+            .iounmap(buffer.virt_addr, token)
+            .unwrap_or_else(|(err, _)| panic!("Unable to unmap DMA buffer: {}", err)),
+        false => token,
+    };
+
+    free_pages(buffer.phys_addr, order(buffer.num_pages), token)
+}
+
+/// Expose `size` bytes of an existing buffer at `virt_addr` (mapped within the kernel space) to
+/// devices for a transfer in `direction`.
+///
+/// The buffer must be physically contiguous and must not be accessed by the kernel until it is
+/// unmapped again (see [`unmap`]).
+pub fn map(
+    virt_addr: VirtualAddress<c_void>,
+    size: usize,
+    direction: DmaDirection,
+    token: LevelMapping,
+) -> Result<(DmaMapping, LevelMapping), (MemoryError, LevelMapping)> {
+    if size == 0 {
+        return Err((MemoryError::InvalidAddress, token));
+    }
+
+    // Step 1: Resolve physical address (and check for contiguity)
+    let start = virt_addr.addr() - virt_addr.addr() % cpu::page_size();
+    let end = match virt_addr.addr().checked_add(size) {
+        Some(end) => end,
+        None => return Err((MemoryError::InvalidAddress, token)),
+    };
+    let mut phys_start = 0;
+    let mut token = token;
+    for page in (start..end).step_by(cpu::page_size()) {
+        let (phys_page, page_size, t) = match KERNEL_VIRTUAL_MEMORY_SYSTEM
+            .as_ref()
+            .lookup(VirtualAddress::new(page as *mut c_void), token)
+        {
+            Ok((phys_page, _, _, page_size, t)) => (phys_page, page_size, t),
+            Err((err, t)) => return Err((err, t)),
+        };
+        token = t;
+
+        let phys_addr = phys_page.addr() + page % page_size.size();
+        if page == start {
+            phys_start = phys_addr;
+        } else if phys_addr != phys_start + (page - start) {
+            return Err((MemoryError::InvalidAddress, token));
+        }
+    }
+
+    // Step 2: Translate to bus address
+    let offset = virt_addr.addr() - start;
+    let bus_addr = match phys_to_bus(phys_start + offset, size) {
+        Some(bus_addr) => bus_addr,
+        None => return Err((MemoryError::InvalidAddress, token)),
+    };
+
+    // Step 3: Hand over buffer to device
+    sync_for_device(virt_addr.addr(), size, direction);
+
+    let mapping = DmaMapping {
+        virt_addr,
+        bus_addr,
+        size,
+        direction,
+    };
+    Ok((mapping, token))
+}
+
+/// Revoke streaming `mapping` created by [`map`] (after the transfer completed) and hand back
+/// the buffer to the kernel.
+pub fn unmap(mapping: DmaMapping) {
+    sync_for_cpu(mapping.virt_addr.addr(), mapping.size, mapping.direction);
+}
+
+/// Get order of the smallest block containing `num_pages` pages.
+fn order(num_pages: usize) -> usize {
+    num_pages.next_power_of_two().trailing_zeros() as usize
+}
+
+/// Return block of `2^order` pages at `phys_addr` to the [`PAGE_FRAME_ALLOCATOR`].
+fn free_pages(
+    phys_addr: PhysicalAddress<c_void>,
+    order: usize,
+    token: LevelMapping,
+) -> LevelMapping {
+    let adapter = AdapterMappingPaging::new();
+    let (guard, t) = adapter.enter(token);
+    guard.leave(unsafe { PAGE_FRAME_ALLOCATOR.free_order(phys_addr, order, t) })
+}
+
+/// Translate physical range `[phys_addr, phys_addr + size)` to a bus address (`None`, if not
+/// accessible by DMA).
+fn phys_to_bus(phys_addr: usize, size: usize) -> Option<BusAddress> {
+    let mut ranges = DMA_CONFIG.as_ref().ranges.iter().flatten().peekable();
+    if ranges.peek().is_none() {
+        return Some(BusAddress(phys_addr));
+    }
+
+    ranges
+        .find(|range| phys_addr >= range.phys && phys_addr - range.phys + size <= range.size)
+        .map(|range| BusAddress(range.bus + (phys_addr - range.phys)))
+}
+
+/// Perform cache maintenance before a transfer in `direction` of `[virt_addr, virt_addr + size)`.
+fn sync_for_device(virt_addr: usize, size: usize, direction: DmaDirection) {
+    match direction {
+        DmaDirection::ToDevice => maintain(virt_addr, size, cbo::cbo_clean::<u8>),
+        // Write back dirty blocks, so that they cannot be evicted during the transfer
+        DmaDirection::FromDevice | DmaDirection::Bidirectional => {
+            maintain(virt_addr, size, cbo::cbo_flush::<u8>)
+        }
+    }
+}
+
+/// Perform cache maintenance after a transfer in `direction` of `[virt_addr, virt_addr + size)`.
+fn sync_for_cpu(virt_addr: usize, size: usize, direction: DmaDirection) {
+    match direction {
+        DmaDirection::ToDevice => {}
+        // Blocks only partially covered by the buffer may hold data of the CPU (next to it), which
+        // must be written back instead of being discarded
+        DmaDirection::FromDevice | DmaDirection::Bidirectional => {
+            maintain_with_edges(virt_addr, size, cbo::cbo_inval::<u8>, cbo::cbo_flush::<u8>)
+        }
+    }
+}
+
+/// Apply cache-block operation `op` to `[virt_addr, virt_addr + size)` (only if DMA is not
+/// coherent and `Zicbom` is supported).
+fn maintain(virt_addr: usize, size: usize, op: fn(VirtualAddress<u8>)) {
+    maintain_with_edges(virt_addr, size, op, op)
+}
+
+/// Apply cache-block operation `op` to blocks fully inside `[virt_addr, virt_addr + size)` and
+/// `edge` to the blocks only partially covered at its boundaries (only if DMA is not coherent and
+/// `Zicbom` is supported).
+fn maintain_with_edges(
+    virt_addr: usize,
+    size: usize,
+    op: fn(VirtualAddress<u8>),
+    edge: fn(VirtualAddress<u8>),
+) {
+    let config = DMA_CONFIG.as_ref();
+    let block_size = match (config.coherent, config.cbom_block_size) {
+        (false, Some(block_size)) => block_size,
+        _ => return,
+    };
+
+    let start = virt_addr - virt_addr % block_size;
+    let end = virt_addr + size;
+    for block in (start..end).step_by(block_size) {
+        let block_op = match block >= virt_addr && block + block_size <= end {
+            true => op,
+            false => edge,
+        };
+        block_op(VirtualAddress::new(block as *mut u8));
+    }
+
+    // Order cache maintenance with subsequent accesses (of the device)
+    fence(Ordering::SeqCst);
+}
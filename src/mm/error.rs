@@ -15,6 +15,9 @@ pub enum MemoryError {
     /// Invalid address (e.g. Kernel address (upper `4GiB`) with
     /// [`Mode::User`](crate::mm::mapping::Mode::User).
     InvalidAddress,
+    /// Mapping violates the kernel's protection policy (e.g. writable and executable kernel
+    /// mapping or modification of sealed kernel sections).
+    PermissionDenied,
 }
 
 impl Display for MemoryError {
@@ -24,6 +27,7 @@ impl Display for MemoryError {
             MemoryError::AddressAlreadyInUse => write!(f, "Address already in Use"),
             MemoryError::NoSuchAddress => write!(f, "No such Address"),
             MemoryError::InvalidAddress => write!(f, "Invalid Address"),
+            MemoryError::PermissionDenied => write!(f, "Permission denied"),
         }
     }
 }
@@ -41,3 +45,44 @@ impl Error for MemoryError {
         self.source()
     }
 }
+
+/// [`Error`]s associated with resolving page faults.
+#[derive(Debug)]
+pub enum PageFaultError {
+    /// No virtual memory area covers the faulting address.
+    NoSuchArea,
+    /// Access is not permitted by the protection of the virtual memory area.
+    AccessViolation,
+    /// User-mode access to kernel memory.
+    PrivilegeViolation,
+    /// Out-of-Memory.
+    OutOfMemory,
+    /// Backing store failed to read in page.
+    BackingStore,
+}
+
+impl Display for PageFaultError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PageFaultError::NoSuchArea => write!(f, "No virtual memory area"),
+            PageFaultError::AccessViolation => write!(f, "Access violation"),
+            PageFaultError::PrivilegeViolation => write!(f, "Privilege violation"),
+            PageFaultError::OutOfMemory => write!(f, "Out of Memory"),
+            PageFaultError::BackingStore => write!(f, "Backing store failure"),
+        }
+    }
+}
+
+impl Error for PageFaultError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+
+    fn description(&self) -> &str {
+        "description() is deprecated; use Display"
+    }
+
+    fn cause(&self) -> Option<&dyn Error> {
+        self.source()
+    }
+}
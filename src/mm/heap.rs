@@ -0,0 +1,252 @@
+//! Kernel heap based on size classes (slabs) on top of the [`PageFrameAllocator`].
+//!
+//! Small allocations (up to [`MAX_CLASS_SIZE`] bytes) are served from per-size-class free lists,
+//! which are refilled page-wise. Larger allocations are directly served by the
+//! [`PageFrameAllocator`] as `2^order` physically contiguous pages.
+
+use core::alloc::GlobalAlloc;
+use core::alloc::Layout;
+use core::ffi::c_void;
+use core::ptr;
+use core::ptr::NonNull;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering;
+
+use crate::config;
+use crate::kernel::address::Address;
+use crate::kernel::address::VirtualAddress;
+use crate::kernel::cpu;
+use crate::mm::error::MemoryError;
+use crate::mm::page_allocator::PageFrameAllocator;
+use crate::mm::page_allocator::PAGE_FRAME_ALLOCATOR;
+use crate::sync::level::Adapter;
+use crate::sync::level::AdapterGuard;
+use crate::sync::level::AdapterMappingPaging;
+use crate::sync::level::AdapterMemoryPaging;
+use crate::sync::level::Level;
+use crate::sync::level::LevelMemory;
+use crate::sync::ticketlock::TicketlockMemory;
+use crate::trap::handlers::TrapHandlers;
+
+/// Global [`Heap`] instance (used as `#[global_allocator]`).
+#[global_allocator]
+pub static KERNEL_HEAP: Heap = Heap::new();
+
+/// Global allocations are enabled on each hart (see [`scope`]).
+static GLOBAL_ALLOCATIONS: [AtomicBool; config::MAX_CPU_NUM] =
+    [const { AtomicBool::new(false) }; config::MAX_CPU_NUM];
+
+/// Size of the smallest size class.
+const MIN_CLASS_SIZE: usize = 16;
+
+/// Size of the largest size class.
+pub const MAX_CLASS_SIZE: usize = 2048;
+
+/// Number of size classes (`16, 32, ..., 2048`).
+const NUM_CLASSES: usize =
+    (MAX_CLASS_SIZE.trailing_zeros() - MIN_CLASS_SIZE.trailing_zeros() + 1) as usize;
+
+/// Free object within a size class.
+struct FreeObject {
+    next: *mut FreeObject,
+}
+
+/// Free list of a single size class.
+struct SizeClass {
+    head: *mut FreeObject,
+}
+
+unsafe impl Send for SizeClass {}
+
+/// Size-class-based kernel heap.
+///
+/// The heap requires [`LevelMemory`] and may therefore neither be used within a `prologue` nor
+/// while holding locks of a lower level (e.g. [`LevelMapping`](crate::sync::level::LevelMapping)).
+/// As [`GlobalAlloc`] provides no level token, global allocations (e.g. `Box`) are restricted to
+/// a [`scope`] entered on [`LevelMemory`].
+pub struct Heap {
+    classes: [TicketlockMemory<SizeClass>; NUM_CLASSES],
+}
+
+impl Heap {
+    const fn new() -> Self {
+        const EMPTY: TicketlockMemory<SizeClass> = TicketlockMemory::new(SizeClass {
+            head: ptr::null_mut(),
+        });
+
+        Self {
+            classes: [EMPTY; NUM_CLASSES],
+        }
+    }
+
+    /// Get index of size class serving `layout` (if any).
+    fn class(layout: Layout) -> Option<usize> {
+        let size = usize::max(layout.size(), layout.align());
+        let size = usize::max(size, MIN_CLASS_SIZE).next_power_of_two();
+        if size > MAX_CLASS_SIZE {
+            return None;
+        }
+
+        Some((size.trailing_zeros() - MIN_CLASS_SIZE.trailing_zeros()) as usize)
+    }
+
+    /// Get order of pages serving `layout` (for allocations exceeding all size classes).
+    fn order(layout: Layout) -> usize {
+        let size = usize::max(layout.size(), layout.align());
+        let num_pages = (size + cpu::page_size() - 1) / cpu::page_size();
+        num_pages.next_power_of_two().trailing_zeros() as usize
+    }
+
+    /// Try to allocate memory as described by `layout`.
+    pub fn allocate(
+        &self,
+        layout: Layout,
+        token: LevelMemory,
+    ) -> Result<(NonNull<u8>, LevelMemory), (MemoryError, LevelMemory)> {
+        // Step 1: Serve large allocations directly by the page frame allocator
+        let idx = match Self::class(layout) {
+            Some(idx) => idx,
+            None => {
+                let adapter = AdapterMemoryPaging::new();
+                let (guard, token) = adapter.enter(token);
+                let result = PAGE_FRAME_ALLOCATOR.allocate_order(Self::order(layout), token);
+                return match result {
+                    Ok((p_block, token)) => {
+                        let mut v_block = PageFrameAllocator::phys_to_virt(p_block);
+                        let block = NonNull::new(v_block.as_mut_ptr().cast()).unwrap();
+                        Ok((block, guard.leave(token)))
+                    }
+                    Err((err, token)) => Err((err, guard.leave(token))),
+                };
+            }
+        };
+        let class_size = MIN_CLASS_SIZE << idx;
+
+        // Step 2: Lock size class
+        let (mut class, token) = self.classes[idx].lock(token);
+
+        // Step 3: Refill size class (if empty) using a new page
+        let token = if class.head.is_null() {
+            let adapter = AdapterMappingPaging::new();
+            let (guard, token) = adapter.enter(token);
+            let (p_page, token) = match PAGE_FRAME_ALLOCATOR.allocate(token) {
+                Ok(result) => result,
+                Err((err, token)) => {
+                    let token = class.unlock(guard.leave(token));
+                    return Err((err, token));
+                }
+            };
+            let v_page: VirtualAddress<c_void> = PageFrameAllocator::phys_to_virt(p_page);
+
+            for i in (0..cpu::page_size() / class_size).rev() {
+                let mut v_object = unsafe { v_page.byte_add(i * class_size) };
+                let object: *mut FreeObject = v_object.as_mut_ptr().cast();
+                unsafe { object.write(FreeObject { next: class.head }) };
+                class.head = object;
+            }
+
+            guard.leave(token)
+        } else {
+            token
+        };
+
+        // Step 4: Take first free object
+        let object = class.head;
+        class.head = unsafe { (*object).next };
+
+        // Step 5: Unlock size class
+        let token = class.unlock(token);
+        Ok((NonNull::new(object.cast()).unwrap(), token))
+    }
+
+    /// Deallocate memory at `ptr` previously allocated with `layout`.
+    ///
+    /// # Safety
+    /// This function is unsafe because undefined behavior can result if ...
+    /// - `ptr` refers to a block of memory currently allocated via this heap with the same
+    ///   `layout`.
+    /// - the referenced memory is still in use.
+    pub unsafe fn deallocate(
+        &self,
+        ptr: NonNull<u8>,
+        layout: Layout,
+        token: LevelMemory,
+    ) -> LevelMemory {
+        // Step 1: Return large allocations directly to the page frame allocator
+        let idx = match Self::class(layout) {
+            Some(idx) => idx,
+            None => {
+                let v_block = VirtualAddress::new(ptr.as_ptr().cast::<c_void>());
+                let p_block = PageFrameAllocator::virt_to_phys(v_block);
+
+                let adapter = AdapterMemoryPaging::new();
+                let (guard, token) = adapter.enter(token);
+                let token = PAGE_FRAME_ALLOCATOR.free_order(p_block, Self::order(layout), token);
+                return guard.leave(token);
+            }
+        };
+
+        // Step 2: Push object onto free list of size class
+        let (mut class, token) = self.classes[idx].lock(token);
+        let object: *mut FreeObject = ptr.as_ptr().cast();
+        object.write(FreeObject { next: class.head });
+        class.head = object;
+
+        class.unlock(token)
+    }
+}
+
+/// Execute `f` with global allocations (e.g. `Box`) enabled on the current hart.
+///
+/// As `f` is given no level token, it cannot acquire any lock until the consumed [`LevelMemory`]
+/// `token` is produced again afterwards. Thus, the heap is only used on [`LevelMemory`]. Global
+/// allocations have to be dropped within a scope as well (e.g. `scope(token, || drop(value))`).
+pub fn scope<R>(token: LevelMemory, f: impl FnOnce() -> R) -> (R, LevelMemory) {
+    let enabled = &GLOBAL_ALLOCATIONS[cpu::current().raw()];
+    let outer = enabled.swap(true, Ordering::Relaxed);
+    let result = f();
+    enabled.store(outer, Ordering::Relaxed);
+
+    (result, token)
+}
+
+/// Check that the caller of [`GlobalAlloc`] is on [`LevelMemory`] (i.e. within a [`scope`]).
+///
+/// # Panic
+/// If called within a `prologue` or outside of a [`scope`], this function will panic!
+fn assert_level_memory() {
+    assert!(
+        !TrapHandlers::in_prologue(),
+        "Kernel heap must not be used within a prologue"
+    );
+    assert!(
+        GLOBAL_ALLOCATIONS[cpu::current().raw()].load(Ordering::Relaxed),
+        "Kernel heap must not be used outside of heap::scope"
+    );
+}
+
+unsafe impl GlobalAlloc for Heap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // # Safety
+        // `GlobalAlloc` does not provide any level token. Thus, the level is checked at runtime
+        // (see `assert_level_memory`).
+        assert_level_memory();
+        let token = LevelMemory::create();
+
+        // Signal allocation failure (instead of aborting) by returning NULL
+        match self.allocate(layout, token) {
+            Ok((ptr, _)) => ptr.as_ptr(),
+            Err(_) => ptr::null_mut(),
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // # Safety
+        // `GlobalAlloc` does not provide any level token. Thus, the level is checked at runtime
+        // (see `assert_level_memory`).
+        assert_level_memory();
+        let token = LevelMemory::create();
+
+        let _ = self.deallocate(NonNull::new(ptr).unwrap(), layout, token);
+    }
+}
@@ -1,26 +1,206 @@
 //! Kernel APIs to create/update/revoke mappings.
 
 use core::ffi::c_void;
+use core::mem;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, AtomicUsize, Ordering};
 
 use crate::arch::csr::CSR;
-use crate::arch::satp::SATP;
+use crate::arch::satp::{PagingMode, SATP};
+use crate::arch::sfence;
+use crate::boot::device_tree::dt::DeviceTree;
+use crate::config;
 use crate::kernel::address::{Address, PhysicalAddress, VirtualAddress};
 use crate::kernel::compiler;
 use crate::kernel::cpu;
-use crate::mm::error::MemoryError;
+use crate::kernel::cpu_map;
+use crate::kernel::printer::LogLevel;
+use crate::mm::asid;
+use crate::mm::error::{MemoryError, PageFaultError};
 use crate::mm::page_allocator::PageFrameAllocator;
 use crate::mm::page_allocator::PAGE_FRAME_ALLOCATOR;
+use crate::mm::ptdump::{Dumper, Violation, WalkEntry};
 use crate::mm::pte::PageTableEntry;
+use crate::mm::reclaim;
+use crate::mm::tlb;
+use crate::mm::vma::{Access, VirtualMemoryArea, VirtualMemoryAreas};
+use crate::mm::vmap;
+use crate::printk;
 use crate::sync::const_cell::ConstCell;
 use crate::sync::init_cell::InitCell;
-use crate::sync::level::{LevelInitialization, LevelMapping, LevelPaging};
-use crate::sync::ticketlock::TicketlockMapping;
+use crate::sync::level::{Adapter, AdapterGuard, AdapterMappingPaging};
+use crate::sync::level::{LevelInitialization, LevelMapping, LevelMemory, LevelPaging};
+use crate::sync::ticketlock::{TicketlockMapping, TicketlockMemory};
 
 /// Virtual memory system containing only kernel-addresses (upper `4GiB`).
 pub static KERNEL_VIRTUAL_MEMORY_SYSTEM: InitCell<VirtualMemorySystem> = InitCell::new();
 
 static KERNEL_PTS_1: InitCell<TicketlockMapping<PageTableSubspace>> = InitCell::new();
 
+/// Virtual memory system currently loaded per hart (`null` until loaded after CPU map
+/// initialization).
+static ACTIVE_VIRTUAL_MEMORY_SYSTEMS: [AtomicPtr<VirtualMemorySystem>; config::MAX_CPU_NUM] = {
+    const NONE: AtomicPtr<VirtualMemorySystem> = AtomicPtr::new(ptr::null_mut());
+    [NONE; config::MAX_CPU_NUM]
+};
+
+/// Paging mode (as selected during boot).
+static PAGING_MODE: InitCell<PagingMode> = InitCell::new();
+
+/// Optional paging extensions supported by all harts (as detected during boot).
+static PAGING_EXTENSIONS: InitCell<PagingExtensions> = InitCell::new();
+
+/// `.text` and `.rodata` are sealed (see [`seal_kernel_sections`]).
+static KERNEL_SECTIONS_SEALED: AtomicBool = AtomicBool::new(false);
+
+/// Number of entries per page table.
+const NUM_PAGE_TABLE_ENTRIES: usize = 512;
+
+/// First virtual address of user space (lower `4GiB`).
+pub const USER_SPACE_START: usize = 0x0000_0000_0000_0000;
+
+/// First virtual address behind user space.
+pub const USER_SPACE_END: usize = 0x0000_0001_0000_0000;
+
+/// First virtual address of kernel space (upper `4GiB`).
+pub const KERNEL_SPACE_START: usize = 0xffff_ffff_0000_0000;
+
+// Reject unsupported paging modes of `config.yaml` at compile time
+const _: () = assert!(
+    PagingMode::from_virtual_address_bits(config::PAGING_MODE).is_some(),
+    "Unsupported paging mode (use 39, 48 or 57)"
+);
+
+/// Get paging mode (as selected during boot).
+pub fn paging_mode() -> PagingMode {
+    *PAGING_MODE.as_ref()
+}
+
+/// Optional paging extensions.
+///
+/// See `Svpbmt` and `Svnapot` of `Volume II: RISC-V Privileged Architectures`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PagingExtensions {
+    /// Page-based memory types (see [`MemoryType`]).
+    pub svpbmt: bool,
+    /// NAPOT translation contiguity (see [`PageSize::Size64KiB`]).
+    pub svnapot: bool,
+}
+
+/// Detect optional paging extensions supported by all harts (as described by device tree).
+pub fn detect_extensions(token: LevelInitialization) -> LevelInitialization {
+    let (dt, token) = DeviceTree::get_dt(token);
+
+    let mut extensions = PAGING_EXTENSIONS.get_mut(token);
+    *extensions = PagingExtensions {
+        svpbmt: dt.has_isa_extension("svpbmt"),
+        svnapot: dt.has_isa_extension("svnapot"),
+    };
+    let token = extensions.destroy();
+    unsafe { PAGING_EXTENSIONS.finanlize(token) }
+}
+
+/// Get optional paging extensions (none, until detected during boot).
+pub fn paging_extensions() -> PagingExtensions {
+    match PAGING_EXTENSIONS.is_initialized() {
+        true => *PAGING_EXTENSIONS.as_ref(),
+        false => PagingExtensions {
+            svpbmt: false,
+            svnapot: false,
+        },
+    }
+}
+
+/// Seal `.text` and `.rodata` for the rest of runtime.
+///
+/// Afterwards, their mappings can neither be updated nor removed, and their page frames cannot be
+/// mapped writable anymore (e.g. as alias). Sealing cannot be undone.
+pub fn seal_kernel_sections() {
+    KERNEL_SECTIONS_SEALED.store(true, Ordering::Release);
+}
+
+/// Check if `.text` and `.rodata` are sealed (see [`seal_kernel_sections`]).
+pub fn is_sealed() -> bool {
+    KERNEL_SECTIONS_SEALED.load(Ordering::Acquire)
+}
+
+/// Get virtual and physical `[start, end)` ranges of the sealed kernel sections.
+fn sealed_sections() -> [((usize, usize), (usize, usize)); 2] {
+    [
+        (
+            (
+                compiler::text_segment_virt_start().addr(),
+                compiler::text_segment_virt_end().addr(),
+            ),
+            (
+                compiler::text_segment_phys_start().addr(),
+                compiler::text_segment_phys_end().addr(),
+            ),
+        ),
+        (
+            (
+                compiler::rodata_segment_virt_start().addr(),
+                compiler::rodata_segment_virt_end().addr(),
+            ),
+            (
+                compiler::rodata_segment_phys_start().addr(),
+                compiler::rodata_segment_phys_end().addr(),
+            ),
+        ),
+    ]
+}
+
+/// Check if mapping `size` bytes at `virt_addr` (to `phys_addr`, if known) with
+/// `protection`/`mode` complies with the kernel's protection policy.
+///
+/// Writable and executable kernel mappings are rejected (see `CONFIG_STRICT_KERNEL_WX`), as well as
+/// modifications of sealed kernel sections and writable aliases of them (see
+/// [`seal_kernel_sections`]).
+fn check_protection(
+    phys_addr: Option<usize>,
+    virt_addr: usize,
+    size: usize,
+    protection: Protection,
+    mode: Mode,
+) -> Result<(), MemoryError> {
+    // Step 1: Enforce W^X for kernel mappings
+    if config::STRICT_KERNEL_WX
+        && mode == Mode::Kernel
+        && protection.is_writable()
+        && protection.is_executable()
+    {
+        return Err(MemoryError::PermissionDenied);
+    }
+
+    // Step 2: Protect sealed sections
+    check_sealed(virt_addr, size)?;
+
+    // Step 3: Reject writable aliases of sealed sections
+    if let Some(phys_addr) = phys_addr {
+        let alias = sealed_sections()
+            .iter()
+            .any(|(_, (start, end))| phys_addr < *end && *start < phys_addr + size);
+        if is_sealed() && protection.is_writable() && alias {
+            return Err(MemoryError::PermissionDenied);
+        }
+    }
+
+    Ok(())
+}
+
+/// Check if mappings of `size` bytes at `virt_addr` may be modified (i.e. do not belong to sealed
+/// kernel sections, see [`seal_kernel_sections`]).
+fn check_sealed(virt_addr: usize, size: usize) -> Result<(), MemoryError> {
+    let sealed = is_sealed()
+        && sealed_sections()
+            .iter()
+            .any(|((start, end), _)| virt_addr < *end && *start < virt_addr + size);
+    match sealed {
+        true => Err(MemoryError::PermissionDenied),
+        false => Ok(()),
+    }
+}
+
 /// Protection bits.
 ///
 /// See `4.3.1 Addressing and Memory Protection` of `Volume II: RISC-V Privileged Architectures`.
@@ -39,6 +219,19 @@ pub enum Protection {
 }
 
 impl Protection {
+    /// Get [`Protection`] of `readable`/`writable`/`executable` flags (`None`, if the
+    /// combination is reserved or describes no access at all).
+    pub fn from_flags(readable: bool, writable: bool, executable: bool) -> Option<Self> {
+        match (readable, writable, executable) {
+            (true, true, true) => Some(Protection::RWX),
+            (true, true, false) => Some(Protection::RW),
+            (true, false, true) => Some(Protection::RX),
+            (true, false, false) => Some(Protection::R),
+            (false, false, true) => Some(Protection::X),
+            _ => None,
+        }
+    }
+
     /// Check if [`Protection`] is readable.
     pub fn is_readable(self) -> bool {
         match self {
@@ -82,37 +275,254 @@ pub enum Mode {
     User,
 }
 
+/// Memory type of mapping (overriding the physical memory attributes of the platform).
+///
+/// Without `Svpbmt`, every mapping uses [`MemoryType::PMA`]. See `Svpbmt` of `Volume II: RISC-V
+/// Privileged Architectures`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryType {
+    /// Physical memory attributes of the platform.
+    PMA,
+    /// Non-cacheable, idempotent, weakly-ordered main memory.
+    NC,
+    /// Non-cacheable, non-idempotent, strongly-ordered I/O memory.
+    IO,
+}
+
+impl MemoryType {
+    /// Get [`MemoryType`] of `PBMT` bits (`None`, if reserved).
+    pub fn from_encoding(encoding: u64) -> Option<Self> {
+        match encoding {
+            0 => Some(MemoryType::PMA),
+            1 => Some(MemoryType::NC),
+            2 => Some(MemoryType::IO),
+            _ => None,
+        }
+    }
+
+    /// Get `PBMT` bits of [`MemoryType`].
+    pub fn encoding(self) -> u64 {
+        match self {
+            MemoryType::PMA => 0,
+            MemoryType::NC => 1,
+            MemoryType::IO => 2,
+        }
+    }
+}
+
+/// Size of a (leaf) mapping.
+///
+/// See `4.4.1 Addressing and Memory Protection` of `Volume II: RISC-V Privileged Architectures`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageSize {
+    /// `4KiB` page.
+    Size4KiB,
+    /// `64KiB` NAPOT mapping (sixteen contiguous `4KiB` entries, see `Svnapot`).
+    Size64KiB,
+    /// `2MiB` megapage.
+    Size2MiB,
+    /// `1GiB` gigapage.
+    Size1GiB,
+}
+
+impl PageSize {
+    /// Get size of [`PageSize`] in bytes.
+    pub const fn size(self) -> usize {
+        match self {
+            PageSize::Size4KiB => 4 * 1024,
+            PageSize::Size64KiB => 64 * 1024,
+            PageSize::Size2MiB => 2 * 1024 * 1024,
+            PageSize::Size1GiB => 1024 * 1024 * 1024,
+        }
+    }
+
+    /// Check if `addr` is aligned to [`PageSize`].
+    pub const fn is_aligned(self, addr: usize) -> bool {
+        addr % self.size() == 0
+    }
+
+    /// Get level (with `0` being the root) of the page table holding leaf entries of [`PageSize`]
+    /// for the current [`paging_mode`].
+    pub fn level(self) -> usize {
+        let levels = paging_mode().levels();
+        match self {
+            PageSize::Size4KiB | PageSize::Size64KiB => levels - 1,
+            PageSize::Size2MiB => levels - 2,
+            PageSize::Size1GiB => levels - 3,
+        }
+    }
+}
+
 /// Page table entries at level 1 for either kernel space (upper `4GiB`) or user space (lower `4GiB`).
 struct PageTableSubspace([PhysicalAddress<PageTableEntry>; 4]);
 unsafe impl Send for PageTableSubspace {}
 unsafe impl Sync for PageTableSubspace {}
 
-/// 39bit page-based Virtual Memory System.
+/// Page-based Virtual Memory System (using Sv39, Sv48 or Sv57 as selected during boot).
 ///
-/// For more details, see `4.4 Sv39: Page-Based 39-bit Virtual-Memory System` of `Volume II: RISC-V Privileged Architectures`.
+/// Only the lower `4GiB` (user space) and the upper `4GiB` (kernel space) are managed. For Sv48
+/// and Sv57, both are reached via additional upper page tables, while the remaining structure is
+/// identical to Sv39.
+///
+/// For more details, see `4.4 Sv39: Page-Based 39-bit Virtual-Memory System`, `4.5 Sv48:
+/// Page-Based 48-bit Virtual-Memory System` and `4.6 Sv57: Page-Based 57-bit Virtual-Memory
+/// System` of `Volume II: RISC-V Privileged Architectures`.
 pub struct VirtualMemorySystem {
     root: ConstCell<PhysicalAddress<PageTableEntry>>,
     user_pts_1: TicketlockMapping<PageTableSubspace>,
     kernel_pts_1: &'static TicketlockMapping<PageTableSubspace>,
+    areas: TicketlockMemory<VirtualMemoryAreas>,
+    context: AtomicU64,
+    clock_hand: AtomicUsize,
 }
 unsafe impl Send for VirtualMemorySystem {}
 unsafe impl Sync for VirtualMemorySystem {}
 
 impl VirtualMemorySystem {
-    /// Apply current mapping by writing [`SATP`] register.
-    pub fn load(&self) {
+    /// Apply kernel mapping by writing [`SATP`] register (using the reserved kernel ASID).
+    ///
+    /// Only the [`KERNEL_VIRTUAL_MEMORY_SYSTEM`] may be loaded this way (e.g. during
+    /// initialization), any other virtual memory system has to be [`activate`](Self::activate)d.
+    pub fn load(&'static self) {
+        assert!(self.context.load(Ordering::Relaxed) == asid::KERNEL_CONTEXT);
+
+        self.write_satp(asid::KERNEL_ASID);
+        if !asid::is_supported() {
+            sfence::sfence_vma();
+        }
+    }
+
+    /// Apply mapping by writing [`SATP`] register.
+    ///
+    /// An ASID is assigned on demand (see [`asid`]), so that translations of other address spaces
+    /// need not be flushed. Without ASID support, all translations are flushed instead.
+    ///
+    /// As the hart refers to the virtual memory system until another one is loaded (see
+    /// [`active`](Self::active)), it has to live (and stay in place) for the remaining runtime.
+    pub fn activate(&'static self, token: LevelMapping) -> LevelMapping {
+        // Step 1: Assign ASID
+        let (asid, flush, token) = match asid::is_supported() {
+            true => asid::activate(&self.context, token),
+            false => (asid::KERNEL_ASID, true, token),
+        };
+
+        // Step 2: Load mapping (flushing stale translations, if required)
+        self.write_satp(asid);
+        if flush {
+            sfence::sfence_vma();
+        }
+
+        token
+    }
+
+    /// Write [`SATP`] register using `asid`.
+    fn write_satp(&'static self, asid: usize) {
         let mut satp = SATP::new(0);
         satp.read();
+        satp.set_mode(paging_mode());
+        satp.set_asid(asid);
         satp.set_root_page_table(*self.root);
         satp.write();
+
+        // Remember loaded virtual memory system (required to resolve page faults)
+        if cpu_map::is_initialized() {
+            let vms = self as *const Self as *mut Self;
+            ACTIVE_VIRTUAL_MEMORY_SYSTEMS[cpu::current().raw()].store(vms, Ordering::Relaxed);
+        }
+    }
+
+    /// Get ASID assigned to [`VirtualMemorySystem`] (`None`, if ASIDs are unsupported or no ASID
+    /// was assigned yet).
+    fn asid(&self) -> Option<usize> {
+        match asid::is_supported() {
+            true => asid::asid(self.context.load(Ordering::Relaxed)),
+            false => None,
+        }
+    }
+
+    /// Invalidate translation of `virt_addr` on the current hart (restricted to the ASID of this
+    /// virtual memory system for user space).
+    fn invalidate_page(&self, virt_addr: VirtualAddress<c_void>) {
+        match (virt_addr.addr() < KERNEL_SPACE_START, self.asid()) {
+            (true, Some(asid)) => sfence::sfence_vma_addr_asid(virt_addr, asid),
+            _ => sfence::sfence_vma_addr(virt_addr),
+        }
+    }
+
+    /// Invalidate translation of `virt_addr` on all harts (restricted to the ASID of this virtual
+    /// memory system for user space).
+    fn shootdown_page(&self, virt_addr: VirtualAddress<c_void>) {
+        match (virt_addr.addr() < KERNEL_SPACE_START, self.asid()) {
+            (true, Some(asid)) => tlb::shootdown_page_asid(virt_addr, asid),
+            _ => tlb::shootdown_page(virt_addr),
+        }
+    }
+
+    /// Invalidate all translations of the (user or kernel) space containing `virt_addr` on all
+    /// harts (restricted to the ASID of this virtual memory system for user space).
+    fn shootdown_space(&self, virt_addr: VirtualAddress<c_void>) {
+        match (virt_addr.addr() < KERNEL_SPACE_START, self.asid()) {
+            (true, Some(asid)) => tlb::shootdown_asid(asid),
+            _ => tlb::shootdown_all(),
+        }
+    }
+
+    /// Get [`VirtualMemorySystem`] currently loaded on this hart.
+    ///
+    /// Before any virtual memory system was loaded (after CPU map initialization),
+    /// [`KERNEL_VIRTUAL_MEMORY_SYSTEM`] is returned.
+    pub fn active() -> &'static VirtualMemorySystem {
+        let vms = ACTIVE_VIRTUAL_MEMORY_SYSTEMS[cpu::current().raw()].load(Ordering::Relaxed);
+        match unsafe { vms.as_ref() } {
+            Some(vms) => vms,
+            None => KERNEL_VIRTUAL_MEMORY_SYSTEM.as_ref(),
+        }
     }
 
     /// Create initial [`VirtualMemorySystem`] for kernel-space only.
     pub fn initalize(token: LevelInitialization) -> LevelInitialization {
-        // Initialize kernel mapping (PT 0 & PT 1s)
-        let (p_pt_0, token) = PAGE_FRAME_ALLOCATOR.early_allocate(token).unwrap();
-        let p_pt_0: PhysicalAddress<PageTableEntry> = unsafe { p_pt_0.cast() };
+        // Determine paging mode (as selected during boot)
+        let mut satp = SATP::new(0);
+        satp.read();
+        let mut mode = PAGING_MODE.get_mut(token);
+        *mode = satp.get_mode();
+        let token = mode.destroy();
+        let token = unsafe { PAGING_MODE.finanlize(token) };
+        assert!(paging_mode() != PagingMode::Bare);
+
+        // Detect supported ASIDs
+        let token = asid::initialize(token);
+
+        // Initialize kernel mapping (root page table)
+        let (p_root, token) = PAGE_FRAME_ALLOCATOR.early_allocate(token).unwrap();
+        let p_root: PhysicalAddress<PageTableEntry> = unsafe { p_root.cast() };
+
+        // Initialize upper page tables for user and kernel space (only required for Sv48 and Sv57)
+        let mut token = token;
+        let mut p_pts_0 = [p_root; 2];
+        for (i, space) in [USER_SPACE_START, KERNEL_SPACE_START].iter().enumerate() {
+            let virt_addr: VirtualAddress<c_void> = VirtualAddress::new(*space as *mut c_void);
+            for level in 0..PageSize::Size1GiB.level() {
+                let (p_pt, t) = PAGE_FRAME_ALLOCATOR.early_allocate(token).unwrap();
+                let p_pt: PhysicalAddress<PageTableEntry> = unsafe { p_pt.cast() };
+
+                let v_pt = PageFrameAllocator::phys_to_virt(p_pts_0[i]);
+                let v_pte = unsafe {
+                    v_pt.add(Self::offset(virt_addr, level))
+                        .as_mut_ptr()
+                        .as_mut()
+                        .unwrap()
+                };
+                v_pte.set_physical_page(p_pt);
+                v_pte.mark_as_valid(true);
+
+                p_pts_0[i] = p_pt;
+                token = t;
+            }
+        }
+        let p_pt_0 = p_pts_0[1];
 
+        // Allocate kernel page tables for level 1 (shared by all virtual memory systems)
         let (p_pt_1_511, token) = PAGE_FRAME_ALLOCATOR.early_allocate(token).unwrap();
         let p_pt_1_511: PhysicalAddress<PageTableEntry> = unsafe { p_pt_1_511.cast() };
 
@@ -154,13 +564,18 @@ impl VirtualMemorySystem {
 
         // Initialize kernel-only
         let vms = Self {
-            root: ConstCell::new(p_pt_0),
+            root: ConstCell::new(p_root),
             user_pts_1: TicketlockMapping::new(PageTableSubspace([PhysicalAddress::null(); 4])),
             kernel_pts_1: KERNEL_PTS_1.as_ref(),
+            areas: TicketlockMemory::new(VirtualMemoryAreas::new()),
+            context: AtomicU64::new(asid::KERNEL_CONTEXT),
+            clock_hand: AtomicUsize::new(0),
         };
 
+        // # Safety
+        // The previous value is uninitialized and must not be dropped.
         let mut kernel_virtual_memory_system = KERNEL_VIRTUAL_MEMORY_SYSTEM.get_mut(token);
-        *kernel_virtual_memory_system.as_mut() = vms;
+        unsafe { ptr::write(kernel_virtual_memory_system.as_mut(), vms) };
         let token = kernel_virtual_memory_system.destroy();
         let token = unsafe { KERNEL_VIRTUAL_MEMORY_SYSTEM.finanlize(token) };
 
@@ -262,93 +677,472 @@ impl VirtualMemorySystem {
             );
         }
 
-        // XXX: Map Page Tables as 128 2MiB Huge-Page
-        const HUGE_PAGE_SIZE: usize = 2 * 1024 * 1024;
-        assert!(compiler::pages_mem_size() % HUGE_PAGE_SIZE == 0);
-        assert!(compiler::pages_mem_phys_start().addr() % HUGE_PAGE_SIZE == 0);
-        assert!(compiler::pages_mem_phys_end().addr() % HUGE_PAGE_SIZE == 0);
-        assert!(compiler::pages_mem_virt_start().addr() % HUGE_PAGE_SIZE == 0);
-        assert!(compiler::pages_mem_virt_end().addr() % HUGE_PAGE_SIZE == 0);
+        // Map page pool using 2MiB mappings
+        let page_size = PageSize::Size2MiB;
+        assert!(page_size.is_aligned(compiler::pages_mem_size()));
+        assert!(page_size.is_aligned(compiler::pages_mem_phys_start().addr()));
+        assert!(page_size.is_aligned(compiler::pages_mem_phys_end().addr()));
+        assert!(page_size.is_aligned(compiler::pages_mem_virt_start().addr()));
+        assert!(page_size.is_aligned(compiler::pages_mem_virt_end().addr()));
 
-        // Get first (root) page table
-        let p_pt_0 = KERNEL_VIRTUAL_MEMORY_SYSTEM.as_ref().root.as_ref();
-        let v_pt_0 = PageFrameAllocator::phys_to_virt(*p_pt_0);
-
-        for i in 0..compiler::pages_mem_size() / HUGE_PAGE_SIZE {
+        for i in 0..compiler::pages_mem_size() / page_size.size() {
             let virt_addr =
-                unsafe { compiler::pages_mem_virt_start().byte_add(i * HUGE_PAGE_SIZE) };
+                unsafe { compiler::pages_mem_virt_start().byte_add(i * page_size.size()) };
             let phys_addr =
-                unsafe { compiler::pages_mem_phys_start().byte_add(i * HUGE_PAGE_SIZE) };
+                unsafe { compiler::pages_mem_phys_start().byte_add(i * page_size.size()) };
 
-            // Check first page table
-            let vpn_0 = Self::offset(virt_addr, 0);
-            let pte_0 = unsafe { v_pt_0.add(vpn_0).as_mut_ptr().as_mut().unwrap() };
-            if !pte_0.is_valid() {
-                panic!("");
+            token = Some(
+                KERNEL_VIRTUAL_MEMORY_SYSTEM
+                    .as_ref()
+                    .early_create_huge(
+                        phys_addr,
+                        virt_addr,
+                        page_size,
+                        Protection::RW,
+                        token.unwrap(),
+                    )
+                    .unwrap(),
+            );
+        }
+
+        let token = token.unwrap();
+        token
+    }
+
+    /// Tear down the boot page tables (see `head.S`), which map the lower `4GiB` both
+    /// identically and to the kernel space using readable/writable/executable gigapages.
+    ///
+    /// Afterwards, no further harts can be booted.
+    ///
+    /// # Safety
+    /// All harts must have loaded the fine-grained [`KERNEL_VIRTUAL_MEMORY_SYSTEM`] (see
+    /// [`load`](Self::load)) before.
+    pub unsafe fn teardown_boot_mapping() {
+        // Step 1: Clear all entries of the boot page tables
+        let mut start = compiler::boot_page_tables_virt_start();
+        let size = compiler::boot_page_tables_virt_end().addr() - start.addr();
+        unsafe { start.as_mut_ptr().cast::<u8>().write_bytes(0, size) };
+
+        // Step 2: Invalidate stale translations (sharing the kernel's ASID) on all harts
+        tlb::shootdown_all();
+    }
+
+    /// Create a new [`VirtualMemorySystem`].
+    ///
+    /// The kernel-space (upper `4GiB`) is shared with [`KERNEL_VIRTUAL_MEMORY_SYSTEM`], while the
+    /// user-space (lower `4GiB`) is initially empty.
+    pub fn new(token: LevelMapping) -> Result<(Self, LevelMapping), (MemoryError, LevelMapping)> {
+        // Lock kernel page tables
+        let kernel_pts_1 = KERNEL_PTS_1.as_ref();
+        let (kernel_page_tables, token) = kernel_pts_1.lock(token);
+
+        // Allocate first (root) page table
+        let (p_root, token): (PhysicalAddress<PageTableEntry>, _) =
+            match PAGE_FRAME_ALLOCATOR.allocate(token) {
+                Ok((p_root, token)) => unsafe { (p_root.cast(), token) },
+                Err((err, token)) => {
+                    return Err((err, kernel_page_tables.unlock(token)));
+                }
+            };
+        let v_root = PageFrameAllocator::phys_to_virt(p_root);
+
+        // Share kernel page tables (i.e. kernel page tables for level 1 for Sv39, or upper kernel
+        // page tables for Sv48 and Sv57)
+        let kernel_space: VirtualAddress<c_void> =
+            VirtualAddress::new(KERNEL_SPACE_START as *mut c_void);
+        let v_kernel_root =
+            PageFrameAllocator::phys_to_virt(*KERNEL_VIRTUAL_MEMORY_SYSTEM.as_ref().root);
+        for vpn in Self::offset(kernel_space, 0)..NUM_PAGE_TABLE_ENTRIES {
+            let kernel_pte = unsafe { v_kernel_root.add(vpn).as_ptr().as_ref().unwrap() };
+            let pte = unsafe { v_root.add(vpn).as_mut_ptr().as_mut().unwrap() };
+            pte.set_physical_page(kernel_pte.get_physical_page::<PageTableEntry>());
+            pte.mark_as_valid(kernel_pte.is_valid());
+        }
+
+        // Allocate upper page tables for user space (only required for Sv48 and Sv57)
+        let user_space: VirtualAddress<c_void> =
+            VirtualAddress::new(USER_SPACE_START as *mut c_void);
+        let mut token = token;
+        let mut p_pt = p_root;
+        for level in 0..PageSize::Size1GiB.level() {
+            let (p_pt_next, t): (PhysicalAddress<PageTableEntry>, _) =
+                match PAGE_FRAME_ALLOCATOR.allocate(token) {
+                    Ok((p_pt_next, t)) => unsafe { (p_pt_next.cast(), t) },
+                    Err((err, t)) => {
+                        let t = Self::free_user_upper_page_tables(p_root, t);
+                        let t = unsafe { PAGE_FRAME_ALLOCATOR.free(p_root.cast(), t) };
+                        return Err((err, kernel_page_tables.unlock(t)));
+                    }
+                };
+
+            let v_pt = PageFrameAllocator::phys_to_virt(p_pt);
+            let pte = unsafe {
+                v_pt.add(Self::offset(user_space, level))
+                    .as_mut_ptr()
+                    .as_mut()
+                    .unwrap()
+            };
+            pte.set_physical_page(p_pt_next);
+            pte.mark_as_valid(true);
+
+            p_pt = p_pt_next;
+            token = t;
+        }
+
+        // Unlock kernel page tables
+        let token = kernel_page_tables.unlock(token);
+
+        let vms = Self {
+            root: ConstCell::new(p_root),
+            user_pts_1: TicketlockMapping::new(PageTableSubspace([PhysicalAddress::null(); 4])),
+            kernel_pts_1,
+            areas: TicketlockMemory::new(VirtualMemoryAreas::new()),
+            context: AtomicU64::new(asid::NO_CONTEXT),
+            clock_hand: AtomicUsize::new(0),
+        };
+
+        Ok((vms, token))
+    }
+
+    /// Duplicate [`VirtualMemorySystem`] (e.g. for process duplication).
+    ///
+    /// The kernel-space is shared as for [`new`](Self::new), while the user-space is duplicated
+    /// without copying: Pages of virtual memory areas are shared, whereby writable pages become
+    /// read-only in both virtual memory systems and are copied on first write (see
+    /// [`handle_page_fault`](Self::handle_page_fault)). Any other read-only user mappings
+    /// (including `2MiB` and `1GiB` mappings) are shared as is.
+    ///
+    /// Writable user mappings outside of virtual memory areas (or larger than `4KiB`) cannot be
+    /// shared copy-on-write. If any exists, [`MemoryError::PermissionDenied`] is returned.
+    pub fn duplicate(
+        &self,
+        token: LevelMemory,
+    ) -> Result<(Self, LevelMemory), (MemoryError, LevelMemory)> {
+        // Step 1: Lock areas
+        let (areas, token) = self.areas.lock(token);
+
+        // Step 2: Create new virtual memory system with same areas
+        let (mut vms, token) = match Self::new(token) {
+            Ok(result) => result,
+            Err((err, token)) => return Err((err, areas.unlock(token))),
+        };
+        *vms.areas.get_mut() = areas.clone();
+
+        // Step 3: Duplicate user page tables (if all writable mappings can be shared copy-on-write)
+        let (user_page_tables, token) = self.user_pts_1.lock(token);
+        let result = match self.check_copy_on_write(&user_page_tables, &areas) {
+            Ok(()) => self.duplicate_user_page_tables(&user_page_tables, &areas, &mut vms, token),
+            Err(err) => Err((err, token)),
+        };
+
+        // Step 4: Mark writable pages as copy-on-write (only once duplication cannot fail anymore)
+        if result.is_ok() {
+            self.mark_copy_on_write(&user_page_tables, &areas);
+        }
+
+        // Step 5: Invalidate stale (writable) translations on all harts
+        self.shootdown_space(VirtualAddress::new(USER_SPACE_START as *mut c_void));
+
+        // Unlock areas and page tables (destroying the new virtual memory system on failure)
+        match result {
+            Ok(token) => {
+                let token = areas.unlock(user_page_tables.unlock(token));
+                Ok((vms, token))
+            }
+            Err((err, token)) => {
+                let token = vms.destroy(user_page_tables.unlock(token));
+                Err((err, areas.unlock(token)))
             }
+        }
+    }
 
-            // Check second page table
-            let (p_pts_1, p_pt_1) = match vpn_0 {
-                508 | 509 | 510 | 511 => {
-                    let kernel_page_tables = KERNEL_VIRTUAL_MEMORY_SYSTEM
-                        .as_ref()
-                        .kernel_pts_1
-                        .init_lock(token.unwrap());
-                    let p_pt_1 = kernel_page_tables.0[vpn_0 - 508];
-                    (kernel_page_tables, p_pt_1)
+    /// Check that all writable user mappings (see `user_pts_1`) are `4KiB` pages within `areas`
+    /// and that no mapping within `areas` is part of a NAPOT mapping (i.e. all can be shared
+    /// copy-on-write).
+    fn check_copy_on_write(
+        &self,
+        user_pts_1: &PageTableSubspace,
+        areas: &VirtualMemoryAreas,
+    ) -> Result<(), MemoryError> {
+        let user_space: VirtualAddress<c_void> =
+            VirtualAddress::new(USER_SPACE_START as *mut c_void);
+        let v_pt_0 = self.gigapage_table(user_space).unwrap();
+
+        for (vpn_0, p_pt_1) in user_pts_1.0.iter().enumerate() {
+            // Check `1GiB` mapping (if any)
+            if p_pt_1.is_null() {
+                let pte_0 = unsafe { v_pt_0.add(vpn_0).as_ptr().as_ref().unwrap() };
+                if pte_0.is_valid() && pte_0.is_writable() {
+                    return Err(MemoryError::PermissionDenied);
                 }
-                _ => {
-                    panic!();
+                continue;
+            }
+
+            let v_pt_1 = PageFrameAllocator::phys_to_virt(*p_pt_1);
+            for vpn_1 in 0..NUM_PAGE_TABLE_ENTRIES {
+                let pte_1 = unsafe { v_pt_1.add(vpn_1).as_ptr().as_ref().unwrap() };
+                if !pte_1.is_valid() {
+                    continue;
                 }
-            };
-            let v_pt_1 = PageFrameAllocator::phys_to_virt(p_pt_1);
-            let vpn_1 = Self::offset(virt_addr, 1);
-            let pte_1 = unsafe { v_pt_1.add(vpn_1).as_mut_ptr().as_mut().unwrap() };
-            if pte_1.is_valid() {
-                panic!();
+
+                // Check `2MiB` mapping
+                if !pte_1.is_inner_page_table() {
+                    if pte_1.is_writable() {
+                        return Err(MemoryError::PermissionDenied);
+                    }
+                    continue;
+                }
+
+                // Check `4KiB` mappings outside of areas and NAPOT mappings within areas
+                let v_pt_2: VirtualAddress<PageTableEntry> =
+                    PageFrameAllocator::phys_to_virt(pte_1.get_physical_page());
+                for vpn_2 in 0..NUM_PAGE_TABLE_ENTRIES {
+                    let pte_2 = unsafe { v_pt_2.add(vpn_2).as_ptr().as_ref().unwrap() };
+                    if !pte_2.is_valid() {
+                        continue;
+                    }
+
+                    let virt_addr: VirtualAddress<c_void> = VirtualAddress::new(
+                        (USER_SPACE_START + (vpn_0 << 30) + (vpn_1 << 21) + (vpn_2 << 12))
+                            as *mut c_void,
+                    );
+                    match areas.find(virt_addr) {
+                        Some(_) if pte_2.is_napot() => return Err(MemoryError::PermissionDenied),
+                        None if pte_2.is_writable() => return Err(MemoryError::PermissionDenied),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Duplicate user page tables (see `user_pts_1`) into the fresh `vms`.
+    ///
+    /// Pages within `areas` are shared copy-on-write, while any other (read-only) mapping is shared
+    /// as is (see [`check_copy_on_write`](Self::check_copy_on_write)). The page tables of `self`
+    /// are left untouched, so that a failure can be dropped with `vms` (see
+    /// [`mark_copy_on_write`](Self::mark_copy_on_write) for marking them afterwards).
+    fn duplicate_user_page_tables(
+        &self,
+        user_pts_1: &PageTableSubspace,
+        areas: &VirtualMemoryAreas,
+        vms: &mut Self,
+        token: LevelPaging,
+    ) -> Result<LevelPaging, (MemoryError, LevelPaging)> {
+        let user_space: VirtualAddress<c_void> =
+            VirtualAddress::new(USER_SPACE_START as *mut c_void);
+        let v_pt_0 = self.gigapage_table(user_space).unwrap();
+        let v_vms_pt_0 = vms.gigapage_table(user_space).unwrap();
+
+        let mut token = token;
+        for (vpn_0, p_pt_1) in user_pts_1.0.iter().enumerate() {
+            let pte_0 = unsafe { v_pt_0.add(vpn_0).as_ptr().as_ref().unwrap() };
+            let vms_pte_0 = unsafe { v_vms_pt_0.add(vpn_0).as_mut_ptr().as_mut().unwrap() };
+
+            // Share `1GiB` mapping (if any) as is
+            if p_pt_1.is_null() {
+                *vms_pte_0 = pte_0.clone();
+                continue;
             }
-            pte_1.set_physical_page(phys_addr);
-            pte_1.mark_as_readable(true);
-            pte_1.mark_as_writable(true);
-            pte_1.mark_as_executable(false);
-            pte_1.mark_as_user_accessible(false);
-            pte_1.mark_as_valid(true);
 
-            token = Some(p_pts_1.init_unlock());
+            // Allocate second page table
+            let (p_vms_pt_1, t) = PAGE_FRAME_ALLOCATOR.allocate(token)?;
+            let p_vms_pt_1: PhysicalAddress<PageTableEntry> = unsafe { p_vms_pt_1.cast() };
+            vms_pte_0.set_physical_page(p_vms_pt_1);
+            vms_pte_0.mark_as_valid(true);
+            vms.user_pts_1.get_mut().0[vpn_0] = p_vms_pt_1;
+            token = t;
+
+            let v_pt_1 = PageFrameAllocator::phys_to_virt(*p_pt_1);
+            let v_vms_pt_1 = PageFrameAllocator::phys_to_virt(p_vms_pt_1);
+            for vpn_1 in 0..NUM_PAGE_TABLE_ENTRIES {
+                let pte_1 = unsafe { v_pt_1.add(vpn_1).as_ptr().as_ref().unwrap() };
+                let vms_pte_1 = unsafe { v_vms_pt_1.add(vpn_1).as_mut_ptr().as_mut().unwrap() };
+                if !pte_1.is_valid() {
+                    continue;
+                }
+
+                // Share `2MiB` mapping as is
+                if !pte_1.is_inner_page_table() {
+                    *vms_pte_1 = pte_1.clone();
+                    continue;
+                }
+
+                // Allocate third page table
+                let (p_vms_pt_2, t) = PAGE_FRAME_ALLOCATOR.allocate(token)?;
+                let p_vms_pt_2: PhysicalAddress<PageTableEntry> = unsafe { p_vms_pt_2.cast() };
+                vms_pte_1.set_physical_page(p_vms_pt_2);
+                vms_pte_1.mark_as_valid(true);
+                token = t;
+
+                let v_pt_2: VirtualAddress<PageTableEntry> =
+                    PageFrameAllocator::phys_to_virt(pte_1.get_physical_page());
+                let v_vms_pt_2 = PageFrameAllocator::phys_to_virt(p_vms_pt_2);
+                for vpn_2 in 0..NUM_PAGE_TABLE_ENTRIES {
+                    let pte_2 = unsafe { v_pt_2.add(vpn_2).as_ptr().as_ref().unwrap() };
+                    let vms_pte_2 = unsafe { v_vms_pt_2.add(vpn_2).as_mut_ptr().as_mut().unwrap() };
+
+                    // Share pages written out to the backing store
+                    if pte_2.is_swapped() {
+                        let store = reclaim::backing_store().unwrap();
+                        token = store.share(pte_2.get_swap_slot(), token)?;
+                        *vms_pte_2 = pte_2.clone();
+                        continue;
+                    }
+                    if !pte_2.is_valid() {
+                        continue;
+                    }
+
+                    // Share pages of virtual memory areas copy-on-write
+                    let virt_addr: VirtualAddress<c_void> = VirtualAddress::new(
+                        (USER_SPACE_START + (vpn_0 << 30) + (vpn_1 << 21) + (vpn_2 << 12))
+                            as *mut c_void,
+                    );
+                    let mut entry = pte_2.clone();
+                    if areas.find(virt_addr).is_some() {
+                        token = PAGE_FRAME_ALLOCATOR.share(pte_2.get_physical_page(), token)?;
+                        if entry.is_writable() {
+                            entry.mark_as_writable(false);
+                            entry.mark_as_copy_on_write(true);
+                        }
+                    }
+                    *vms_pte_2 = entry;
+                }
+            }
         }
 
-        let token = token.unwrap();
-        token
+        Ok(token)
     }
 
-    /// Create a new [`VirtualMemorySystem`].
-    pub fn new(token: LevelMapping) -> Result<(Self, LevelMapping), (MemoryError, LevelMapping)> {
-        let _ = token;
-        todo!();
+    /// Mark all writable pages within `areas` of user page tables (see `user_pts_1`) as
+    /// copy-on-write after they were duplicated (see
+    /// [`duplicate_user_page_tables`](Self::duplicate_user_page_tables)).
+    fn mark_copy_on_write(&self, user_pts_1: &PageTableSubspace, areas: &VirtualMemoryAreas) {
+        for (vpn_0, p_pt_1) in user_pts_1.0.iter().enumerate() {
+            if p_pt_1.is_null() {
+                continue;
+            }
+
+            let v_pt_1 = PageFrameAllocator::phys_to_virt(*p_pt_1);
+            for vpn_1 in 0..NUM_PAGE_TABLE_ENTRIES {
+                let pte_1 = unsafe { v_pt_1.add(vpn_1).as_ptr().as_ref().unwrap() };
+                if !pte_1.is_valid() || !pte_1.is_inner_page_table() {
+                    continue;
+                }
+
+                let v_pt_2: VirtualAddress<PageTableEntry> =
+                    PageFrameAllocator::phys_to_virt(pte_1.get_physical_page());
+                for vpn_2 in 0..NUM_PAGE_TABLE_ENTRIES {
+                    let pte_2 = unsafe { v_pt_2.add(vpn_2).as_mut_ptr().as_mut().unwrap() };
+                    if !pte_2.is_valid() || !pte_2.is_writable() {
+                        continue;
+                    }
+
+                    let virt_addr: VirtualAddress<c_void> = VirtualAddress::new(
+                        (USER_SPACE_START + (vpn_0 << 30) + (vpn_1 << 21) + (vpn_2 << 12))
+                            as *mut c_void,
+                    );
+                    if areas.find(virt_addr).is_some() {
+                        pte_2.mark_as_writable(false);
+                        pte_2.mark_as_copy_on_write(true);
+                    }
+                }
+            }
+        }
     }
 
-    /// Create a new mapping from `virt_addr` to `phys_addr` with specified `protection`/`mode`.
+    /// Create a new mapping from `virt_addr` to `phys_addr` with specified
+    /// `protection`/`mode`/`memory_type`.
+    ///
+    /// The page is mapped as dirty, so that its content is preserved on eviction (see
+    /// [`reclaim`](Self::reclaim)).
     pub fn create(
         &self,
         phys_addr: PhysicalAddress<c_void>,
         virt_addr: VirtualAddress<c_void>,
         protection: Protection,
         mode: Mode,
+        memory_type: MemoryType,
+        token: LevelMapping,
+    ) -> Result<LevelMapping, (MemoryError, LevelMapping)> {
+        self.create_page(
+            phys_addr,
+            virt_addr,
+            protection,
+            mode,
+            memory_type,
+            false,
+            token,
+        )
+    }
+
+    /// Create a new mapping from `virt_addr` to `phys_addr` (see [`create`](Self::create)).
+    ///
+    /// Only `zero_filled` pages are mapped as clean, as they are dropped on eviction unless
+    /// written (see [`evict`](Self::evict)).
+    fn create_page(
+        &self,
+        phys_addr: PhysicalAddress<c_void>,
+        virt_addr: VirtualAddress<c_void>,
+        protection: Protection,
+        mode: Mode,
+        memory_type: MemoryType,
+        zero_filled: bool,
+        token: LevelMapping,
+    ) -> Result<LevelMapping, (MemoryError, LevelMapping)> {
+        let page_size = PageSize::Size4KiB;
+        if let Err(err) = check_protection(
+            Some(phys_addr.addr()),
+            virt_addr.addr(),
+            page_size.size(),
+            protection,
+            mode,
+        ) {
+            return Err((err, token));
+        }
+
+        self.create_pages(
+            phys_addr,
+            virt_addr,
+            page_size,
+            protection,
+            mode,
+            memory_type,
+            zero_filled,
+            token,
+        )
+    }
+
+    /// Create a new mapping of `page_size` (either `4KiB` or `64KiB`) within a third page table.
+    ///
+    /// `64KiB` mappings consist of sixteen contiguous entries, which are marked as NAPOT if
+    /// `Svnapot` is supported. Unless `zero_filled`, the entries are marked as dirty (see
+    /// [`create_page`](Self::create_page)).
+    fn create_pages(
+        &self,
+        phys_addr: PhysicalAddress<c_void>,
+        virt_addr: VirtualAddress<c_void>,
+        page_size: PageSize,
+        protection: Protection,
+        mode: Mode,
+        memory_type: MemoryType,
+        zero_filled: bool,
         token: LevelMapping,
     ) -> Result<LevelMapping, (MemoryError, LevelMapping)> {
-        // Get first (root) page table
-        let p_pt_0 = self.root.as_ref();
-        let v_pt_0 = PageFrameAllocator::phys_to_virt(*p_pt_0);
+        // Get page table mapping `1GiB` per entry (i.e. root page table for Sv39)
+        let v_pt_0 = match self.gigapage_table(virt_addr) {
+            Some(v_pt_0) => v_pt_0,
+            None => return Err((MemoryError::InvalidAddress, token)),
+        };
 
         // Check first page table
-        let vpn_0 = Self::offset(virt_addr, 0);
+        let vpn_0 = Self::offset(virt_addr, PageSize::Size1GiB.level());
         let pte_0 = unsafe { v_pt_0.add(vpn_0).as_mut_ptr().as_mut().unwrap() };
-        if !pte_0.is_valid() {
-            return Err((MemoryError::InvalidAddress, token));
-        }
 
         // Check second page table
-        let (p_pts_1, p_pt_1, token) = match vpn_0 {
+        let (mut p_pts_1, p_pt_1, token) = match vpn_0 {
             0 | 1 | 2 | 3 => {
                 if mode != Mode::User {
                     return Err((MemoryError::InvalidAddress, token));
@@ -362,6 +1156,9 @@ impl VirtualMemorySystem {
                 if mode != Mode::Kernel {
                     return Err((MemoryError::InvalidAddress, token));
                 }
+                if !pte_0.is_valid() {
+                    return Err((MemoryError::InvalidAddress, token));
+                }
 
                 let (kernel_page_tables, token) = self.kernel_pts_1.lock(token);
                 let p_pt_1 = kernel_page_tables.0[vpn_0 - 508];
@@ -371,15 +1168,44 @@ impl VirtualMemorySystem {
                 return Err((MemoryError::InvalidAddress, token));
             }
         };
+
+        // Allocate user page tables for level 1 lazily
+        let (p_pt_1, token) = match p_pt_1.is_null() {
+            false => (p_pt_1, token),
+            true => {
+                // Check for existing `1GiB` mapping
+                if pte_0.is_valid() {
+                    return Err((MemoryError::AddressAlreadyInUse, p_pts_1.unlock(token)));
+                }
+
+                // Allocate a fresh page table entry
+                let (p_pt_1, token): (PhysicalAddress<PageTableEntry>, _) =
+                    match PAGE_FRAME_ALLOCATOR.allocate(token) {
+                        Ok((p_pt_1, token)) => unsafe { (p_pt_1.cast(), token) },
+                        Err((err, token)) => {
+                            return Err((err, p_pts_1.unlock(token)));
+                        }
+                    };
+
+                // Update first page table
+                pte_0.set_physical_page(p_pt_1);
+                pte_0.mark_as_valid(true);
+                p_pts_1.0[vpn_0] = p_pt_1;
+
+                (p_pt_1, token)
+            }
+        };
         let v_pt_1 = PageFrameAllocator::phys_to_virt(p_pt_1);
-        let vpn_1 = Self::offset(virt_addr, 1);
+        let vpn_1 = Self::offset(virt_addr, PageSize::Size2MiB.level());
         let pte_1 = unsafe { v_pt_1.add(vpn_1).as_mut_ptr().as_mut().unwrap() };
 
         // Check third page table
         let (p_pt_2, token) = match pte_1.is_valid() {
             true => {
-                // Check entry
-                assert!(pte_1.is_inner_page_table());
+                // Check for existing `2MiB` mapping
+                if !pte_1.is_inner_page_table() {
+                    return Err((MemoryError::AddressAlreadyInUse, p_pts_1.unlock(token)));
+                }
                 assert!(pte_1.is_user_accessible() == false);
 
                 (pte_1.get_physical_page(), token)
@@ -402,23 +1228,24 @@ impl VirtualMemorySystem {
             }
         };
         let v_pt_2 = PageFrameAllocator::phys_to_virt(p_pt_2);
-        let vpn_2 = Self::offset(virt_addr, 2);
-        let pte_2 = unsafe { v_pt_2.add(vpn_2).as_mut_ptr().as_mut().unwrap() };
+        let vpn_2 = Self::offset(virt_addr, PageSize::Size4KiB.level());
+        let num_entries = page_size.size() / cpu::page_size();
+        let ptes =
+            unsafe { core::slice::from_raw_parts_mut(v_pt_2.add(vpn_2).as_mut_ptr(), num_entries) };
 
         // Try to create mapping
-        match pte_2.is_valid() {
-            true => {
-                // Mapping for given virtual address already exists
-                return Err((MemoryError::AddressAlreadyInUse, p_pts_1.unlock(token)));
-            }
-            false => {
-                // Update mapping
-                pte_2.set_physical_page(phys_addr);
-                pte_2.mark_as_readable(protection.is_readable());
-                pte_2.mark_as_writable(protection.is_writable());
-                pte_2.mark_as_executable(protection.is_executable());
-                pte_2.mark_as_user_accessible(mode == Mode::User);
-                pte_2.mark_as_valid(true);
+        if ptes.iter().any(|pte| pte.is_valid() || pte.is_swapped()) {
+            // Mapping for given virtual address already exists (or was written out)
+            return Err((MemoryError::AddressAlreadyInUse, p_pts_1.unlock(token)));
+        }
+        let napot = page_size == PageSize::Size64KiB && paging_extensions().svnapot;
+        for (i, pte) in ptes.iter_mut().enumerate() {
+            let phys_addr = unsafe { phys_addr.byte_add(i * cpu::page_size()) };
+            Self::set_leaf(pte, phys_addr, protection, mode, memory_type);
+            pte.mark_as_napot(napot);
+            if !zero_filled {
+                pte.set_access_flag();
+                pte.set_dirty_flag();
             }
         }
 
@@ -427,30 +1254,217 @@ impl VirtualMemorySystem {
         Ok(token)
     }
 
-    /// Create a new mapping from `virt_addr` to `phys_addr` with specified `protection`/`mode`
-    /// during initialization.
-    pub fn early_create(
+    /// Create a new mapping of `page_size` from `virt_addr` to `phys_addr` with specified
+    /// `protection`/`mode`/`memory_type`.
+    ///
+    /// Both `virt_addr` and `phys_addr` must be aligned to `page_size`. Without `Svnapot`, `64KiB`
+    /// mappings consist of sixteen regular `4KiB` mappings.
+    ///
+    /// Kernel space is shared by all virtual memory systems via the page tables for level 1 (the
+    /// first page table itself is copied for Sv39). Thus, `1GiB` kernel mappings (e.g. of the
+    /// direct map or MMIO ranges) consist of 512 `2MiB` mappings filling the corresponding (empty)
+    /// shared page table, which are also reported and removed as such.
+    pub fn create_huge(
         &self,
         phys_addr: PhysicalAddress<c_void>,
         virt_addr: VirtualAddress<c_void>,
+        page_size: PageSize,
         protection: Protection,
         mode: Mode,
-        token: LevelInitialization,
-    ) -> Result<LevelInitialization, (MemoryError, LevelInitialization)> {
-        let (mut page, token): (Option<PhysicalAddress<PageTableEntry>>, _) =
-            match PAGE_FRAME_ALLOCATOR.early_allocate(token) {
-                Ok((page, token)) => (Some(unsafe { page.cast() }), token),
-                Err((err, token)) => return Err((err, token)),
-            };
-
-        // Get first (root) page table
-        let p_pt_0 = self.root.as_ref();
-        let v_pt_0 = PageFrameAllocator::phys_to_virt(*p_pt_0);
-
-        // Check first page table
-        let vpn_0 = Self::offset(virt_addr, 0);
-        let pte_0 = unsafe { v_pt_0.add(vpn_0).as_mut_ptr().as_mut().unwrap() };
+        memory_type: MemoryType,
+        token: LevelMapping,
+    ) -> Result<LevelMapping, (MemoryError, LevelMapping)> {
+        // Check alignment and protection
+        if !page_size.is_aligned(phys_addr.addr()) || !page_size.is_aligned(virt_addr.addr()) {
+            return Err((MemoryError::InvalidAddress, token));
+        }
+        if let Err(err) = check_protection(
+            Some(phys_addr.addr()),
+            virt_addr.addr(),
+            page_size.size(),
+            protection,
+            mode,
+        ) {
+            return Err((err, token));
+        }
+        if let PageSize::Size4KiB | PageSize::Size64KiB = page_size {
+            return self.create_pages(
+                phys_addr,
+                virt_addr,
+                page_size,
+                protection,
+                mode,
+                memory_type,
+                false,
+                token,
+            );
+        }
+
+        // Get page table mapping `1GiB` per entry (i.e. root page table for Sv39)
+        let v_pt_0 = match self.gigapage_table(virt_addr) {
+            Some(v_pt_0) => v_pt_0,
+            None => return Err((MemoryError::InvalidAddress, token)),
+        };
+
+        // Check first page table
+        let vpn_0 = Self::offset(virt_addr, PageSize::Size1GiB.level());
+        let pte_0 = unsafe { v_pt_0.add(vpn_0).as_mut_ptr().as_mut().unwrap() };
+
+        // Check second page table
+        let (mut p_pts_1, p_pt_1, token) = match vpn_0 {
+            0 | 1 | 2 | 3 => {
+                if mode != Mode::User {
+                    return Err((MemoryError::InvalidAddress, token));
+                }
+
+                let (user_page_tables, token) = self.user_pts_1.lock(token);
+                let p_pt_1 = user_page_tables.0[vpn_0];
+                (user_page_tables, p_pt_1, token)
+            }
+            508 | 509 | 510 | 511 => {
+                if mode != Mode::Kernel {
+                    return Err((MemoryError::InvalidAddress, token));
+                }
+                if !pte_0.is_valid() {
+                    return Err((MemoryError::InvalidAddress, token));
+                }
+
+                let (kernel_page_tables, token) = self.kernel_pts_1.lock(token);
+                let p_pt_1 = kernel_page_tables.0[vpn_0 - 508];
+                (kernel_page_tables, p_pt_1, token)
+            }
+            _ => {
+                return Err((MemoryError::InvalidAddress, token));
+            }
+        };
+
+        // Try to create `1GiB` mapping
+        if page_size == PageSize::Size1GiB {
+            match mode {
+                Mode::User => {
+                    if !p_pt_1.is_null() || pte_0.is_valid() {
+                        return Err((MemoryError::AddressAlreadyInUse, p_pts_1.unlock(token)));
+                    }
+                    Self::set_leaf(pte_0, phys_addr, protection, mode, memory_type);
+                }
+                Mode::Kernel => {
+                    // Fill shared second page table with `2MiB` mappings
+                    assert!(!p_pt_1.is_null());
+                    let mut v_pt_1 = PageFrameAllocator::phys_to_virt(p_pt_1);
+                    let ptes = unsafe {
+                        core::slice::from_raw_parts_mut(v_pt_1.as_mut_ptr(), NUM_PAGE_TABLE_ENTRIES)
+                    };
+                    if ptes.iter().any(|pte| pte.is_valid()) {
+                        return Err((MemoryError::AddressAlreadyInUse, p_pts_1.unlock(token)));
+                    }
+                    for (i, pte) in ptes.iter_mut().enumerate() {
+                        let phys_addr =
+                            unsafe { phys_addr.byte_add(i * PageSize::Size2MiB.size()) };
+                        Self::set_leaf(pte, phys_addr, protection, mode, memory_type);
+                    }
+                }
+            }
+
+            let token = p_pts_1.unlock(token);
+            return Ok(token);
+        }
+
+        // Allocate user page tables for level 1 lazily
+        let (p_pt_1, token) = match p_pt_1.is_null() {
+            false => (p_pt_1, token),
+            true => {
+                // Check for existing `1GiB` mapping
+                if pte_0.is_valid() {
+                    return Err((MemoryError::AddressAlreadyInUse, p_pts_1.unlock(token)));
+                }
+
+                // Allocate a fresh page table entry
+                let (p_pt_1, token): (PhysicalAddress<PageTableEntry>, _) =
+                    match PAGE_FRAME_ALLOCATOR.allocate(token) {
+                        Ok((p_pt_1, token)) => unsafe { (p_pt_1.cast(), token) },
+                        Err((err, token)) => {
+                            return Err((err, p_pts_1.unlock(token)));
+                        }
+                    };
+
+                // Update first page table
+                pte_0.set_physical_page(p_pt_1);
+                pte_0.mark_as_valid(true);
+                p_pts_1.0[vpn_0] = p_pt_1;
+
+                (p_pt_1, token)
+            }
+        };
+        let v_pt_1 = PageFrameAllocator::phys_to_virt(p_pt_1);
+        let vpn_1 = Self::offset(virt_addr, PageSize::Size2MiB.level());
+        let pte_1 = unsafe { v_pt_1.add(vpn_1).as_mut_ptr().as_mut().unwrap() };
+
+        // Try to create `2MiB` mapping
+        if pte_1.is_valid() {
+            return Err((MemoryError::AddressAlreadyInUse, p_pts_1.unlock(token)));
+        }
+        Self::set_leaf(pte_1, phys_addr, protection, mode, memory_type);
+
+        // Unlock mapping
+        let token = p_pts_1.unlock(token);
+        Ok(token)
+    }
+
+    /// Create a new mapping from `virt_addr` to `phys_addr` with specified `protection`/`mode`
+    /// during initialization.
+    pub fn early_create(
+        &self,
+        phys_addr: PhysicalAddress<c_void>,
+        virt_addr: VirtualAddress<c_void>,
+        protection: Protection,
+        mode: Mode,
+        token: LevelInitialization,
+    ) -> Result<LevelInitialization, (MemoryError, LevelInitialization)> {
+        let memory_type = MemoryType::PMA;
+        self.early_create_typed(phys_addr, virt_addr, protection, mode, memory_type, token)
+    }
+
+    /// Create a new mapping from `virt_addr` to `phys_addr` with specified
+    /// `protection`/`mode`/`memory_type` during initialization.
+    fn early_create_typed(
+        &self,
+        phys_addr: PhysicalAddress<c_void>,
+        virt_addr: VirtualAddress<c_void>,
+        protection: Protection,
+        mode: Mode,
+        memory_type: MemoryType,
+        token: LevelInitialization,
+    ) -> Result<LevelInitialization, (MemoryError, LevelInitialization)> {
+        if let Err(err) = check_protection(
+            Some(phys_addr.addr()),
+            virt_addr.addr(),
+            cpu::page_size(),
+            protection,
+            mode,
+        ) {
+            return Err((err, token));
+        }
+
+        let (mut page, token): (Option<PhysicalAddress<PageTableEntry>>, _) =
+            match PAGE_FRAME_ALLOCATOR.early_allocate(token) {
+                Ok((page, token)) => (Some(unsafe { page.cast() }), token),
+                Err((err, token)) => return Err((err, token)),
+            };
+
+        // Get page table mapping `1GiB` per entry (i.e. root page table for Sv39)
+        let v_pt_0 = match self.gigapage_table(virt_addr) {
+            Some(v_pt_0) => v_pt_0,
+            None => {
+                let token = Self::early_release(page, token);
+                return Err((MemoryError::InvalidAddress, token));
+            }
+        };
+
+        // Check first page table
+        let vpn_0 = Self::offset(virt_addr, PageSize::Size1GiB.level());
+        let pte_0 = unsafe { v_pt_0.add(vpn_0).as_mut_ptr().as_mut().unwrap() };
         if !pte_0.is_valid() {
+            let token = Self::early_release(page, token);
             return Err((MemoryError::InvalidAddress, token));
         }
 
@@ -458,6 +1472,7 @@ impl VirtualMemorySystem {
         let (p_pts_1, p_pt_1) = match vpn_0 {
             0 | 1 | 2 | 3 => {
                 if mode != Mode::User {
+                    let token = Self::early_release(page, token);
                     return Err((MemoryError::InvalidAddress, token));
                 }
 
@@ -467,6 +1482,7 @@ impl VirtualMemorySystem {
             }
             508 | 509 | 510 | 511 => {
                 if mode != Mode::Kernel {
+                    let token = Self::early_release(page, token);
                     return Err((MemoryError::InvalidAddress, token));
                 }
 
@@ -475,18 +1491,22 @@ impl VirtualMemorySystem {
                 (kernel_page_tables, p_pt_1)
             }
             _ => {
+                let token = Self::early_release(page, token);
                 return Err((MemoryError::InvalidAddress, token));
             }
         };
         let v_pt_1 = PageFrameAllocator::phys_to_virt(p_pt_1);
-        let vpn_1 = Self::offset(virt_addr, 1);
+        let vpn_1 = Self::offset(virt_addr, PageSize::Size2MiB.level());
         let pte_1 = unsafe { v_pt_1.add(vpn_1).as_mut_ptr().as_mut().unwrap() };
 
         // Check third page table
         let p_pt_2 = match pte_1.is_valid() {
             true => {
-                // Check entry
-                assert!(pte_1.is_inner_page_table());
+                // Check for existing `2MiB` mapping
+                if !pte_1.is_inner_page_table() {
+                    let token = Self::early_release(page, p_pts_1.init_unlock());
+                    return Err((MemoryError::AddressAlreadyInUse, token));
+                }
                 assert!(pte_1.is_user_accessible() == false);
 
                 pte_1.get_physical_page()
@@ -501,32 +1521,48 @@ impl VirtualMemorySystem {
             }
         };
         let v_pt_2 = PageFrameAllocator::phys_to_virt(p_pt_2);
-        let vpn_2 = Self::offset(virt_addr, 2);
+        let vpn_2 = Self::offset(virt_addr, PageSize::Size4KiB.level());
         let pte_2 = unsafe { v_pt_2.add(vpn_2).as_mut_ptr().as_mut().unwrap() };
 
         // Try to create mapping
         match pte_2.is_valid() {
             true => {
                 // Mapping for given virtual address already exists
-                return Err((MemoryError::AddressAlreadyInUse, p_pts_1.init_unlock()));
+                let token = Self::early_release(page, p_pts_1.init_unlock());
+                return Err((MemoryError::AddressAlreadyInUse, token));
             }
             false => {
                 // Update mapping
-                pte_2.set_physical_page(phys_addr);
-                pte_2.mark_as_readable(protection.is_readable());
-                pte_2.mark_as_writable(protection.is_writable());
-                pte_2.mark_as_executable(protection.is_executable());
-                pte_2.mark_as_user_accessible(mode == Mode::User);
-                pte_2.mark_as_valid(true);
+                Self::set_leaf(pte_2, phys_addr, protection, mode, memory_type);
             }
         }
 
         // Unlock mapping
-        let token = p_pts_1.init_unlock();
+        let token = Self::early_release(page, p_pts_1.init_unlock());
         Ok(token)
     }
 
+    /// Release pre-allocated (but unused) page table `page` during initialization.
+    fn early_release(
+        page: Option<PhysicalAddress<PageTableEntry>>,
+        token: LevelInitialization,
+    ) -> LevelInitialization {
+        match page {
+            // # Safety
+            // The page was allocated by `early_create` and never used as page table.
+            Some(page) => unsafe { PAGE_FRAME_ALLOCATOR.early_free(page.cast(), token) },
+            None => token,
+        }
+    }
+
     /// Create a new (readable/writable for kernel) mapping for `phys_addr` associated driver memory-mapped IO space.
+    ///
+    /// Once `Svpbmt` was detected (see [`detect_extensions`]), the space is mapped as
+    /// [`MemoryType::IO`].
+    ///
+    /// # Panic
+    /// If the space is mapped within the window for dynamic mappings (see [`vmap::VMAP_START`]),
+    /// this function will panic!
     pub fn early_create_dev(
         &self,
         phys_addr: PhysicalAddress<c_void>,
@@ -546,13 +1582,23 @@ impl VirtualMemorySystem {
         let mut virt_drag_addr = VirtualAddress::new((phys_raw_addr + offset) as *mut c_void);
         let mut phys_drag_addr = PhysicalAddress::new(phys_raw_addr as *mut c_void);
 
+        // Reject devices shadowed by the window for dynamic mappings (use `ioremap` instead)
+        let virt_start = virt_drag_addr.addr();
+        assert!(
+            virt_start + size <= vmap::VMAP_START
+                || vmap::VMAP_START + vmap::VMAP_SIZE <= virt_start,
+            "Device at {:#x} intersects the window for dynamic mappings",
+            phys_addr.addr()
+        );
+
         let mut token = Some(token);
         for _ in 0..size / cpu::page_size() {
-            match self.early_create(
+            match self.early_create_typed(
                 phys_drag_addr,
                 virt_drag_addr,
                 Protection::RW,
                 Mode::Kernel,
+                MemoryType::IO,
                 token.unwrap(),
             ) {
                 Ok(t) => {
@@ -574,60 +1620,190 @@ impl VirtualMemorySystem {
         Ok((virt_addr, token.unwrap()))
     }
 
-    /// Update `protection`/`mode` of a given `virt_addr`.
-    pub fn update(
+    /// Create a new (kernel-only) mapping of `page_size` from `virt_addr` to `phys_addr` with
+    /// specified `protection` during initialization.
+    ///
+    /// Both `virt_addr` and `phys_addr` must be aligned to `page_size`. As the kernel page tables
+    /// for level 1 are shared, `1GiB` mappings are not supported (neither are `64KiB` mappings).
+    pub fn early_create_huge(
         &self,
+        phys_addr: PhysicalAddress<c_void>,
         virt_addr: VirtualAddress<c_void>,
+        page_size: PageSize,
         protection: Protection,
-        mode: Mode,
-        token: LevelMapping,
-    ) -> Result<LevelMapping, (MemoryError, LevelMapping)> {
-        let _ = virt_addr;
-        let _ = protection;
-        let _ = mode;
-        let _ = token;
-        todo!();
+        token: LevelInitialization,
+    ) -> Result<LevelInitialization, (MemoryError, LevelInitialization)> {
+        // Check alignment and protection
+        if !page_size.is_aligned(phys_addr.addr()) || !page_size.is_aligned(virt_addr.addr()) {
+            return Err((MemoryError::InvalidAddress, token));
+        }
+        if let Err(err) = check_protection(
+            Some(phys_addr.addr()),
+            virt_addr.addr(),
+            page_size.size(),
+            protection,
+            Mode::Kernel,
+        ) {
+            return Err((err, token));
+        }
+        match page_size {
+            PageSize::Size4KiB => {
+                return self.early_create(phys_addr, virt_addr, protection, Mode::Kernel, token);
+            }
+            PageSize::Size2MiB => {}
+            PageSize::Size64KiB | PageSize::Size1GiB => {
+                return Err((MemoryError::InvalidAddress, token));
+            }
+        }
+
+        // Get page table mapping `1GiB` per entry (i.e. root page table for Sv39)
+        let v_pt_0 = match self.gigapage_table(virt_addr) {
+            Some(v_pt_0) => v_pt_0,
+            None => return Err((MemoryError::InvalidAddress, token)),
+        };
+
+        // Check first page table
+        let vpn_0 = Self::offset(virt_addr, PageSize::Size1GiB.level());
+        let pte_0 = unsafe { v_pt_0.add(vpn_0).as_mut_ptr().as_mut().unwrap() };
+        if !pte_0.is_valid() {
+            return Err((MemoryError::InvalidAddress, token));
+        }
+
+        // Check second page table
+        let (p_pts_1, p_pt_1) = match vpn_0 {
+            508 | 509 | 510 | 511 => {
+                let kernel_page_tables = self.kernel_pts_1.init_lock(token);
+                let p_pt_1 = kernel_page_tables.0[vpn_0 - 508];
+                (kernel_page_tables, p_pt_1)
+            }
+            _ => {
+                return Err((MemoryError::InvalidAddress, token));
+            }
+        };
+        let v_pt_1 = PageFrameAllocator::phys_to_virt(p_pt_1);
+        let vpn_1 = Self::offset(virt_addr, PageSize::Size2MiB.level());
+        let pte_1 = unsafe { v_pt_1.add(vpn_1).as_mut_ptr().as_mut().unwrap() };
+
+        // Try to create mapping
+        if pte_1.is_valid() {
+            return Err((MemoryError::AddressAlreadyInUse, p_pts_1.init_unlock()));
+        }
+        Self::set_leaf(pte_1, phys_addr, protection, Mode::Kernel, MemoryType::PMA);
+
+        // Unlock mapping
+        let token = p_pts_1.init_unlock();
+        Ok(token)
     }
 
-    /// Revoke a new mapping targeting `virt_addr`.
-    pub fn remove(
+    /// Map physical memory `[phys_addr, phys_addr + size)` (readable/writable for kernel) into the
+    /// kernel's direct map during initialization.
+    ///
+    /// Whenever possible `2MiB` mappings are used, otherwise the memory is mapped page-wise.
+    pub fn early_create_direct(
         &self,
-        virt_addr: VirtualAddress<c_void>,
-        token: LevelMapping,
-    ) -> Result<LevelMapping, (MemoryError, LevelMapping)> {
-        let _ = virt_addr;
-        let _ = token;
-        todo!();
+        phys_addr: PhysicalAddress<c_void>,
+        size: usize,
+        token: LevelInitialization,
+    ) -> Result<LevelInitialization, (MemoryError, LevelInitialization)> {
+        let page_size = PageSize::Size2MiB;
+
+        // Calculate address shift
+        let offset =
+            compiler::data_segment_virt_start().addr() - compiler::data_segment_phys_start().addr();
+
+        assert!(phys_addr.addr() % cpu::page_size() == 0);
+        assert!(size % cpu::page_size() == 0);
+
+        let mut token = token;
+        let mut phys_drag_addr = phys_addr;
+        let end = phys_addr.addr() + size;
+        while phys_drag_addr.addr() < end {
+            let virt_drag_addr =
+                VirtualAddress::new((phys_drag_addr.addr() + offset) as *mut c_void);
+
+            // Try to use a `2MiB` mapping
+            if page_size.is_aligned(phys_drag_addr.addr())
+                && phys_drag_addr.addr() + page_size.size() <= end
+            {
+                match self.early_create_huge(
+                    phys_drag_addr,
+                    virt_drag_addr,
+                    page_size,
+                    Protection::RW,
+                    token,
+                ) {
+                    Ok(t) => {
+                        token = t;
+                        phys_drag_addr = unsafe { phys_drag_addr.byte_add(page_size.size()) };
+                        continue;
+                    }
+                    Err((MemoryError::AddressAlreadyInUse, t)) => {
+                        // Fall back to page-wise mapping
+                        token = t;
+                    }
+                    Err((err, t)) => {
+                        return Err((err, t));
+                    }
+                }
+            }
+
+            token = self.early_create(
+                phys_drag_addr,
+                virt_drag_addr,
+                Protection::RW,
+                Mode::Kernel,
+                token,
+            )?;
+            phys_drag_addr = unsafe { phys_drag_addr.byte_add(cpu::page_size()) };
+        }
+
+        Ok(token)
     }
 
-    /// Perform a software-based page table lookup.
-    pub fn lookup(
+    /// Update `protection`/`mode` of a given `virt_addr`.
+    pub fn update(
         &self,
         virt_addr: VirtualAddress<c_void>,
+        protection: Protection,
+        mode: Mode,
         token: LevelMapping,
-    ) -> Result<
-        (PhysicalAddress<c_void>, Protection, Mode, LevelMapping),
-        (MemoryError, LevelMapping),
-    > {
-        // Get first (root) page table
-        let p_pt_0 = self.root.as_ref();
-        let v_pt_0 = PageFrameAllocator::phys_to_virt(*p_pt_0);
+    ) -> Result<LevelMapping, (MemoryError, LevelMapping)> {
+        // Check protection
+        if let Err(err) =
+            check_protection(None, virt_addr.addr(), cpu::page_size(), protection, mode)
+        {
+            return Err((err, token));
+        }
+
+        // Get page table mapping `1GiB` per entry (i.e. root page table for Sv39)
+        let v_pt_0 = match self.gigapage_table(virt_addr) {
+            Some(v_pt_0) => v_pt_0,
+            None => return Err((MemoryError::InvalidAddress, token)),
+        };
 
         // Check first page table
-        let vpn_0 = Self::offset(virt_addr, 0);
+        let vpn_0 = Self::offset(virt_addr, PageSize::Size1GiB.level());
         let pte_0 = unsafe { v_pt_0.add(vpn_0).as_mut_ptr().as_mut().unwrap() };
-        if !pte_0.is_valid() {
-            return Err((MemoryError::InvalidAddress, token));
-        }
 
         // Check second page table
         let (p_pts_1, p_pt_1, token) = match vpn_0 {
             0 | 1 | 2 | 3 => {
+                if mode != Mode::User {
+                    return Err((MemoryError::InvalidAddress, token));
+                }
+
                 let (user_page_tables, token) = self.user_pts_1.lock(token);
                 let p_pt_1 = user_page_tables.0[vpn_0];
                 (user_page_tables, p_pt_1, token)
             }
             508 | 509 | 510 | 511 => {
+                if mode != Mode::Kernel {
+                    return Err((MemoryError::InvalidAddress, token));
+                }
+                if !pte_0.is_valid() {
+                    return Err((MemoryError::InvalidAddress, token));
+                }
+
                 let (kernel_page_tables, token) = self.kernel_pts_1.lock(token);
                 let p_pt_1 = kernel_page_tables.0[vpn_0 - 508];
                 (kernel_page_tables, p_pt_1, token)
@@ -636,86 +1812,332 @@ impl VirtualMemorySystem {
                 return Err((MemoryError::InvalidAddress, token));
             }
         };
-        let v_pt_1 = PageFrameAllocator::phys_to_virt(p_pt_1);
-        let vpn_1 = Self::offset(virt_addr, 1);
-        let pte_1 = unsafe { v_pt_1.add(vpn_1).as_mut_ptr().as_mut().unwrap() };
-
-        // Check third page table
-        let (p_pt_2, token): (PhysicalAddress<PageTableEntry>, LevelPaging) = match pte_1.is_valid()
-        {
+        let pte = match p_pt_1.is_null() {
+            // Check for `1GiB` mapping (only possible for user space)
             true => {
-                // Check entry
-                assert!(pte_1.is_inner_page_table());
-                assert!(pte_1.is_user_accessible() == false);
+                if !pte_0.is_valid() {
+                    let token = p_pts_1.unlock(token);
+                    return Err((MemoryError::NoSuchAddress, token));
+                }
 
-                (pte_1.get_physical_page(), token)
+                pte_0
             }
             false => {
-                let token = p_pts_1.unlock(token);
-                return Err((MemoryError::NoSuchAddress, token));
+                let v_pt_1 = PageFrameAllocator::phys_to_virt(p_pt_1);
+                let vpn_1 = Self::offset(virt_addr, PageSize::Size2MiB.level());
+                let pte_1 = unsafe { v_pt_1.add(vpn_1).as_mut_ptr().as_mut().unwrap() };
+
+                // Check third page table (or `2MiB` mapping)
+                if !pte_1.is_valid() {
+                    let token = p_pts_1.unlock(token);
+                    return Err((MemoryError::NoSuchAddress, token));
+                }
+                match pte_1.is_inner_page_table() {
+                    false => pte_1,
+                    true => {
+                        let p_pt_2: PhysicalAddress<PageTableEntry> = pte_1.get_physical_page();
+                        let v_pt_2 = PageFrameAllocator::phys_to_virt(p_pt_2);
+                        let vpn_2 = Self::offset(virt_addr, PageSize::Size4KiB.level());
+
+                        // Split `64KiB` mapping (as only a single page is updated)
+                        Self::split_napot(v_pt_2, vpn_2);
+                        unsafe { v_pt_2.add(vpn_2).as_mut_ptr().as_mut().unwrap() }
+                    }
+                }
             }
         };
-        let v_pt_2 = PageFrameAllocator::phys_to_virt(p_pt_2);
-        let vpn_2 = Self::offset(virt_addr, 2);
-        let pte_2 = unsafe { v_pt_2.add(vpn_2).as_mut_ptr().as_mut().unwrap() };
 
-        // Try to create mapping
-        if !pte_2.is_valid() {
+        // Try to update mapping
+        if !pte.is_valid() {
             let token = p_pts_1.unlock(token);
             return Err((MemoryError::NoSuchAddress, token));
         }
-
-        let phys_addr = pte_2.get_physical_page();
-        let protection = match (
-            pte_2.is_readable(),
-            pte_2.is_writable(),
-            pte_2.is_executable(),
-        ) {
-            (true, true, true) => Protection::RWX,
-            (true, true, false) => Protection::RW,
-            (true, false, true) => Protection::RX,
-            (true, false, false) => Protection::R,
-            (false, false, true) => Protection::X,
-            (readable, writable, executable) => panic!(
-                "Invalid memory protection: Readable? {} Writable? {} Executable? {}",
-                readable, writable, executable
-            ),
-        };
-        let mode = match pte_2.is_user_accessible() {
-            true => Mode::User,
-            false => Mode::Kernel,
-        };
+        let downgrade = (pte.is_readable() && !protection.is_readable())
+            || (pte.is_writable() && !protection.is_writable())
+            || (pte.is_executable() && !protection.is_executable());
+        pte.mark_as_readable(protection.is_readable());
+        pte.mark_as_writable(protection.is_writable() && !pte.is_copy_on_write());
+        pte.mark_as_executable(protection.is_executable());
+        pte.mark_as_user_accessible(mode == Mode::User);
+
+        // Invalidate stale translation (on all harts, if permissions were revoked)
+        if downgrade {
+            self.shootdown_page(virt_addr);
+        } else {
+            self.invalidate_page(virt_addr);
+        }
 
         // Unlock mapping
         let token = p_pts_1.unlock(token);
-        Ok((phys_addr, protection, mode, token))
+        Ok(token)
     }
 
-    /// Check if `virt_addr` is readable for kernel-space.
-    pub fn is_kernel_readable(
+    /// Revoke a new mapping targeting `virt_addr`.
+    ///
+    /// Page tables (of level 2 and user-space page tables of level 1) which become empty are freed.
+    pub fn remove(
         &self,
         virt_addr: VirtualAddress<c_void>,
         token: LevelMapping,
-    ) -> (bool, LevelMapping) {
-        match self.lookup(virt_addr, token) {
-            Ok((_, protection, mode, token)) => {
-                return (mode == Mode::Kernel && protection.is_readable(), token);
-            }
-            Err((_, token)) => {
-                return (false, token);
-            }
+    ) -> Result<LevelMapping, (MemoryError, LevelMapping)> {
+        // Sealed sections must not be unmapped
+        if let Err(err) = check_sealed(virt_addr.addr(), cpu::page_size()) {
+            return Err((err, token));
         }
-    }
 
-    /// Check if `virt_addr` is writable for kernel-space.
-    pub fn is_kernel_writable(
-        &self,
-        virt_addr: VirtualAddress<c_void>,
-        token: LevelMapping,
-    ) -> (bool, LevelMapping) {
-        match self.lookup(virt_addr, token) {
-            Ok((_, protection, mode, token)) => {
-                return (mode == Mode::Kernel && protection.is_writable(), token);
+        // Get page table mapping `1GiB` per entry (i.e. root page table for Sv39)
+        let v_pt_0 = match self.gigapage_table(virt_addr) {
+            Some(v_pt_0) => v_pt_0,
+            None => return Err((MemoryError::InvalidAddress, token)),
+        };
+
+        // Check first page table
+        let vpn_0 = Self::offset(virt_addr, PageSize::Size1GiB.level());
+        let pte_0 = unsafe { v_pt_0.add(vpn_0).as_mut_ptr().as_mut().unwrap() };
+
+        // Check second page table
+        let (mut p_pts_1, p_pt_1, token) = match vpn_0 {
+            0 | 1 | 2 | 3 => {
+                let (user_page_tables, token) = self.user_pts_1.lock(token);
+                let p_pt_1 = user_page_tables.0[vpn_0];
+                (user_page_tables, p_pt_1, token)
+            }
+            508 | 509 | 510 | 511 => {
+                if !pte_0.is_valid() {
+                    return Err((MemoryError::InvalidAddress, token));
+                }
+
+                let (kernel_page_tables, token) = self.kernel_pts_1.lock(token);
+                let p_pt_1 = kernel_page_tables.0[vpn_0 - 508];
+                (kernel_page_tables, p_pt_1, token)
+            }
+            _ => {
+                return Err((MemoryError::InvalidAddress, token));
+            }
+        };
+        if p_pt_1.is_null() {
+            // Try to remove `1GiB` mapping (only possible for user space)
+            if !pte_0.is_valid() {
+                let token = p_pts_1.unlock(token);
+                return Err((MemoryError::NoSuchAddress, token));
+            }
+            pte_0.clear();
+
+            // Invalidate stale translation on all harts
+            self.shootdown_page(virt_addr);
+
+            let token = p_pts_1.unlock(token);
+            return Ok(token);
+        }
+        let v_pt_1 = PageFrameAllocator::phys_to_virt(p_pt_1);
+        let vpn_1 = Self::offset(virt_addr, PageSize::Size2MiB.level());
+        let pte_1 = unsafe { v_pt_1.add(vpn_1).as_mut_ptr().as_mut().unwrap() };
+
+        // Check third page table
+        if !pte_1.is_valid() {
+            let token = p_pts_1.unlock(token);
+            return Err((MemoryError::NoSuchAddress, token));
+        }
+        let p_pt_2 = match pte_1.is_inner_page_table() {
+            false => {
+                // Remove `2MiB` mapping
+                pte_1.clear();
+                None
+            }
+            true => {
+                let p_pt_2: PhysicalAddress<PageTableEntry> = pte_1.get_physical_page();
+                let v_pt_2 = PageFrameAllocator::phys_to_virt(p_pt_2);
+                let vpn_2 = Self::offset(virt_addr, PageSize::Size4KiB.level());
+
+                // Split `64KiB` mapping (as only a single page is removed)
+                Self::split_napot(v_pt_2, vpn_2);
+                let pte_2 = unsafe { v_pt_2.add(vpn_2).as_mut_ptr().as_mut().unwrap() };
+
+                // Try to remove mapping
+                if !pte_2.is_valid() {
+                    let token = p_pts_1.unlock(token);
+                    return Err((MemoryError::NoSuchAddress, token));
+                }
+                pte_2.clear();
+
+                // Free third page table if empty
+                if !Self::is_page_table_empty(v_pt_2) {
+                    // Invalidate stale translation on all harts
+                    self.shootdown_page(virt_addr);
+
+                    let token = p_pts_1.unlock(token);
+                    return Ok(token);
+                }
+                pte_1.clear();
+                Some(p_pt_2)
+            }
+        };
+
+        // Detach second page table if empty (only possible for user space)
+        let p_pt_1 = match vpn_0 {
+            0 | 1 | 2 | 3 if Self::is_page_table_empty(v_pt_1) => {
+                pte_0.clear();
+                p_pts_1.0[vpn_0] = PhysicalAddress::null();
+                Some(p_pt_1)
+            }
+            _ => None,
+        };
+
+        // Invalidate stale (leaf and non-leaf) translations on all harts
+        self.shootdown_space(virt_addr);
+
+        // Free detached page tables (no hart may walk them after the shootdown)
+        let token = match p_pt_2 {
+            Some(p_pt_2) => unsafe { PAGE_FRAME_ALLOCATOR.free(p_pt_2.cast(), token) },
+            None => token,
+        };
+        let token = match p_pt_1 {
+            Some(p_pt_1) => unsafe { PAGE_FRAME_ALLOCATOR.free(p_pt_1.cast(), token) },
+            None => token,
+        };
+
+        // Unlock mapping
+        let token = p_pts_1.unlock(token);
+        Ok(token)
+    }
+
+    /// Perform a software-based page table lookup.
+    ///
+    /// On success, the physical address of the (possibly huge) page containing `virt_addr` is
+    /// returned together with its [`PageSize`].
+    pub fn lookup(
+        &self,
+        virt_addr: VirtualAddress<c_void>,
+        token: LevelMapping,
+    ) -> Result<
+        (
+            PhysicalAddress<c_void>,
+            Protection,
+            Mode,
+            PageSize,
+            LevelMapping,
+        ),
+        (MemoryError, LevelMapping),
+    > {
+        // Get page table mapping `1GiB` per entry (i.e. root page table for Sv39)
+        let v_pt_0 = match self.gigapage_table(virt_addr) {
+            Some(v_pt_0) => v_pt_0,
+            None => return Err((MemoryError::InvalidAddress, token)),
+        };
+
+        // Check first page table
+        let vpn_0 = Self::offset(virt_addr, PageSize::Size1GiB.level());
+        let pte_0 = unsafe { v_pt_0.add(vpn_0).as_mut_ptr().as_mut().unwrap() };
+
+        // Check second page table
+        let (p_pts_1, p_pt_1, token) = match vpn_0 {
+            0 | 1 | 2 | 3 => {
+                let (user_page_tables, token) = self.user_pts_1.lock(token);
+                let p_pt_1 = user_page_tables.0[vpn_0];
+                (user_page_tables, p_pt_1, token)
+            }
+            508 | 509 | 510 | 511 => {
+                if !pte_0.is_valid() {
+                    return Err((MemoryError::InvalidAddress, token));
+                }
+
+                let (kernel_page_tables, token) = self.kernel_pts_1.lock(token);
+                let p_pt_1 = kernel_page_tables.0[vpn_0 - 508];
+                (kernel_page_tables, p_pt_1, token)
+            }
+            _ => {
+                return Err((MemoryError::InvalidAddress, token));
+            }
+        };
+        let (pte, page_size) = match p_pt_1.is_null() {
+            // Check for `1GiB` mapping (only possible for user space)
+            true => {
+                if !pte_0.is_valid() {
+                    let token = p_pts_1.unlock(token);
+                    return Err((MemoryError::NoSuchAddress, token));
+                }
+
+                (pte_0, PageSize::Size1GiB)
+            }
+            false => {
+                let v_pt_1 = PageFrameAllocator::phys_to_virt(p_pt_1);
+                let vpn_1 = Self::offset(virt_addr, PageSize::Size2MiB.level());
+                let pte_1 = unsafe { v_pt_1.add(vpn_1).as_mut_ptr().as_mut().unwrap() };
+
+                // Check third page table (or `2MiB` mapping)
+                if !pte_1.is_valid() {
+                    let token = p_pts_1.unlock(token);
+                    return Err((MemoryError::NoSuchAddress, token));
+                }
+                match pte_1.is_inner_page_table() {
+                    false => (pte_1, PageSize::Size2MiB),
+                    true => {
+                        assert!(pte_1.is_user_accessible() == false);
+
+                        let p_pt_2: PhysicalAddress<PageTableEntry> = pte_1.get_physical_page();
+                        let v_pt_2 = PageFrameAllocator::phys_to_virt(p_pt_2);
+                        let vpn_2 = Self::offset(virt_addr, PageSize::Size4KiB.level());
+                        let pte_2 = unsafe { v_pt_2.add(vpn_2).as_mut_ptr().as_mut().unwrap() };
+                        match pte_2.is_napot() {
+                            true => (pte_2, PageSize::Size64KiB),
+                            false => (pte_2, PageSize::Size4KiB),
+                        }
+                    }
+                }
+            }
+        };
+
+        // Check mapping
+        if !pte.is_valid() {
+            let token = p_pts_1.unlock(token);
+            return Err((MemoryError::NoSuchAddress, token));
+        }
+
+        let phys_addr = pte.get_physical_page();
+        let (readable, writable, executable) =
+            (pte.is_readable(), pte.is_writable(), pte.is_executable());
+        let protection = match Protection::from_flags(readable, writable, executable) {
+            Some(protection) => protection,
+            None => panic!(
+                "Invalid memory protection: Readable? {} Writable? {} Executable? {}",
+                readable, writable, executable
+            ),
+        };
+        let mode = match pte.is_user_accessible() {
+            true => Mode::User,
+            false => Mode::Kernel,
+        };
+
+        // Unlock mapping
+        let token = p_pts_1.unlock(token);
+        Ok((phys_addr, protection, mode, page_size, token))
+    }
+
+    /// Check if `virt_addr` is readable for kernel-space.
+    pub fn is_kernel_readable(
+        &self,
+        virt_addr: VirtualAddress<c_void>,
+        token: LevelMapping,
+    ) -> (bool, LevelMapping) {
+        match self.lookup(virt_addr, token) {
+            Ok((_, protection, mode, _, token)) => {
+                return (mode == Mode::Kernel && protection.is_readable(), token);
+            }
+            Err((_, token)) => {
+                return (false, token);
+            }
+        }
+    }
+
+    /// Check if `virt_addr` is writable for kernel-space.
+    pub fn is_kernel_writable(
+        &self,
+        virt_addr: VirtualAddress<c_void>,
+        token: LevelMapping,
+    ) -> (bool, LevelMapping) {
+        match self.lookup(virt_addr, token) {
+            Ok((_, protection, mode, _, token)) => {
+                return (mode == Mode::Kernel && protection.is_writable(), token);
             }
             Err((_, token)) => {
                 return (false, token);
@@ -730,7 +2152,7 @@ impl VirtualMemorySystem {
         token: LevelMapping,
     ) -> (bool, LevelMapping) {
         match self.lookup(virt_addr, token) {
-            Ok((_, protection, mode, token)) => {
+            Ok((_, protection, mode, _, token)) => {
                 return (mode == Mode::Kernel && protection.is_executable(), token);
             }
             Err((_, token)) => {
@@ -746,7 +2168,7 @@ impl VirtualMemorySystem {
         token: LevelMapping,
     ) -> (bool, LevelMapping) {
         match self.lookup(virt_addr, token) {
-            Ok((_, protection, mode, token)) => {
+            Ok((_, protection, mode, _, token)) => {
                 return (mode == Mode::User && protection.is_readable(), token);
             }
             Err((_, token)) => {
@@ -762,7 +2184,7 @@ impl VirtualMemorySystem {
         token: LevelMapping,
     ) -> (bool, LevelMapping) {
         match self.lookup(virt_addr, token) {
-            Ok((_, protection, mode, token)) => {
+            Ok((_, protection, mode, _, token)) => {
                 return (mode == Mode::User && protection.is_writable(), token);
             }
             Err((_, token)) => {
@@ -778,7 +2200,7 @@ impl VirtualMemorySystem {
         token: LevelMapping,
     ) -> (bool, LevelMapping) {
         match self.lookup(virt_addr, token) {
-            Ok((_, protection, mode, token)) => {
+            Ok((_, protection, mode, _, token)) => {
                 return (mode == Mode::User && protection.is_executable(), token);
             }
             Err((_, token)) => {
@@ -787,15 +2209,962 @@ impl VirtualMemorySystem {
         }
     }
 
-    /// Get offset first, second and third page table (respective `level`s: `0`, `1` and `2`).
-    pub fn offset<T>(virt_addr: VirtualAddress<T>, level: usize) -> usize {
-        let result = match level {
-            0 => virt_addr.addr() >> 30,
-            1 => virt_addr.addr() >> 21,
-            2 => virt_addr.addr() >> 12,
-            _ => panic!("Unsupported level {} for 39bit paging", level),
+    /// Print all mappings via `printk!`, coalesced into ranges of contiguous mappings with
+    /// identical attributes.
+    pub fn dump(&self, token: LevelMapping) -> LevelMapping {
+        let mut dumper = Dumper::new();
+        let token = self.walk(&mut |entry| dumper.visit(entry), token);
+        dumper.finish();
+        token
+    }
+
+    /// Check page tables for writable and executable mappings, user-accessible kernel pages and
+    /// inner page table entries with `R`/`W`/`X` bits (see [`Violation`]).
+    ///
+    /// Each violation is reported via `printk!`, while their total number is returned.
+    pub fn check(&self, token: LevelMapping) -> (usize, LevelMapping) {
+        let mut violations = 0;
+        let token = self.walk(
+            &mut |entry| {
+                let kernel_space = entry.virt_addr >= KERNEL_SPACE_START;
+                if let Some(violation) = Violation::check(entry, kernel_space) {
+                    printk!(
+                        LogLevel::Warn,
+                        "Page table violation at {:#x}: {}\n",
+                        entry.virt_addr,
+                        violation
+                    );
+                    violations += 1;
+                }
+            },
+            token,
+        );
+        (violations, token)
+    }
+
+    /// Walk page tables of user and kernel space and `visit` every valid entry (in ascending order
+    /// of virtual addresses, with inner entries preceding the entries of their page table).
+    fn walk(&self, visit: &mut dyn FnMut(&WalkEntry), token: LevelMapping) -> LevelMapping {
+        let mut token = token;
+        'spaces: for (space, pts_1) in [
+            (USER_SPACE_START, &self.user_pts_1),
+            (KERNEL_SPACE_START, self.kernel_pts_1),
+        ] {
+            let space_addr: VirtualAddress<c_void> = VirtualAddress::new(space as *mut c_void);
+
+            // Step 1: Visit upper page tables (only present for Sv48 and Sv57)
+            let mut p_pt = *self.root;
+            for level in 0..PageSize::Size1GiB.level() {
+                let v_pt = PageFrameAllocator::phys_to_virt(p_pt);
+                let vpn = Self::offset(space_addr, level);
+                let pte = unsafe { v_pt.add(vpn).as_ptr().as_ref().unwrap() };
+                if !pte.is_valid() {
+                    continue 'spaces;
+                }
+
+                let shift = 9 * (PageSize::Size1GiB.level() - level);
+                visit(&WalkEntry {
+                    virt_addr: space,
+                    size: PageSize::Size1GiB.size() << shift,
+                    pte,
+                    is_inner: true,
+                });
+                p_pt = pte.get_physical_page();
+            }
+
+            // Step 2: Visit first page table (i.e. `1GiB` entries)
+            let v_pt_0 = PageFrameAllocator::phys_to_virt(p_pt);
+            let vpn_start = Self::offset(space_addr, PageSize::Size1GiB.level());
+            let (page_tables, t) = pts_1.lock(token);
+            for (i, p_pt_1) in page_tables.0.iter().enumerate() {
+                let virt_addr_0 = space + i * PageSize::Size1GiB.size();
+                let pte_0 = unsafe { v_pt_0.add(vpn_start + i).as_ptr().as_ref().unwrap() };
+                if !pte_0.is_valid() {
+                    continue;
+                }
+                visit(&WalkEntry {
+                    virt_addr: virt_addr_0,
+                    size: PageSize::Size1GiB.size(),
+                    pte: pte_0,
+                    is_inner: !p_pt_1.is_null(),
+                });
+                if p_pt_1.is_null() {
+                    continue;
+                }
+
+                // Step 3: Visit second page table (i.e. `2MiB` entries)
+                let v_pt_1 = PageFrameAllocator::phys_to_virt(*p_pt_1);
+                for vpn_1 in 0..NUM_PAGE_TABLE_ENTRIES {
+                    let virt_addr_1 = virt_addr_0 + vpn_1 * PageSize::Size2MiB.size();
+                    let pte_1 = unsafe { v_pt_1.add(vpn_1).as_ptr().as_ref().unwrap() };
+                    if !pte_1.is_valid() {
+                        continue;
+                    }
+                    visit(&WalkEntry {
+                        virt_addr: virt_addr_1,
+                        size: PageSize::Size2MiB.size(),
+                        pte: pte_1,
+                        is_inner: pte_1.is_inner_page_table(),
+                    });
+                    if !pte_1.is_inner_page_table() {
+                        continue;
+                    }
+
+                    // Step 4: Visit third page table (i.e. `4KiB` entries)
+                    let v_pt_2: VirtualAddress<PageTableEntry> =
+                        PageFrameAllocator::phys_to_virt(pte_1.get_physical_page());
+                    for vpn_2 in 0..NUM_PAGE_TABLE_ENTRIES {
+                        let pte_2 = unsafe { v_pt_2.add(vpn_2).as_ptr().as_ref().unwrap() };
+                        if !pte_2.is_valid() {
+                            continue;
+                        }
+                        visit(&WalkEntry {
+                            virt_addr: virt_addr_1 + vpn_2 * PageSize::Size4KiB.size(),
+                            size: PageSize::Size4KiB.size(),
+                            pte: pte_2,
+                            is_inner: false,
+                        });
+                    }
+                }
+            }
+            token = page_tables.unlock(t);
+        }
+
+        token
+    }
+
+    /// Add virtual memory `area`, which is populated lazily on first access (see
+    /// [`handle_page_fault`](Self::handle_page_fault)).
+    ///
+    /// Kernel-space areas are shared by all virtual memory systems and thus may only be added to
+    /// [`KERNEL_VIRTUAL_MEMORY_SYSTEM`].
+    pub fn map_area(
+        &self,
+        area: VirtualMemoryArea,
+        token: LevelMemory,
+    ) -> Result<LevelMemory, (MemoryError, LevelMemory)> {
+        // Check address space of area
+        let last = area.end().addr().wrapping_sub(1);
+        let valid = match area.mode() {
+            Mode::User => last >> 32 == USER_SPACE_START >> 32,
+            Mode::Kernel => {
+                area.start().addr() >= KERNEL_SPACE_START
+                    && ptr::eq(self, KERNEL_VIRTUAL_MEMORY_SYSTEM.as_ref())
+            }
+        };
+        if !valid {
+            return Err((MemoryError::InvalidAddress, token));
+        }
+
+        // Insert area
+        let (mut areas, token) = self.areas.lock(token);
+        let result = areas.insert(area);
+        let token = areas.unlock(token);
+        match result {
+            Ok(()) => Ok(token),
+            Err(err) => Err((err, token)),
+        }
+    }
+
+    /// Remove virtual memory area starting at `start`.
+    ///
+    /// All pages populated for the area are unmapped and freed.
+    pub fn unmap_area(
+        &self,
+        start: VirtualAddress<c_void>,
+        token: LevelMemory,
+    ) -> Result<LevelMemory, (MemoryError, LevelMemory)> {
+        // Remove area
+        let (mut areas, token) = self.areas.lock(token);
+        let area = match areas.remove(start) {
+            Ok(area) => area,
+            Err(err) => return Err((err, areas.unlock(token))),
+        };
+
+        // Release populated pages
+        let token = self.release_area(&area, token);
+
+        let token = areas.unlock(token);
+        Ok(token)
+    }
+
+    /// Resolve page fault caused by `access` to `virt_addr` from `mode`.
+    ///
+    /// Pages of anonymous areas are allocated zero-filled on first access, while stack areas are
+    /// grown downwards (up to their maximum size) on demand. Pages shared copy-on-write (see
+    /// [`duplicate`](Self::duplicate)) are copied on first write. Faults of existing mappings
+    /// permitting the access are resolved, even if the mapping does not belong to any area.
+    pub fn handle_page_fault(
+        &self,
+        virt_addr: VirtualAddress<c_void>,
+        access: Access,
+        mode: Mode,
+        token: LevelMemory,
+    ) -> Result<LevelMemory, (PageFaultError, LevelMemory)> {
+        // Step 1: Lock areas (serializing with changes of areas and their populated pages)
+        let (mut areas, token) = self.areas.lock(token);
+        let v_page = VirtualAddress::new((virt_addr.addr() & !(cpu::page_size() - 1)) as *mut _);
+
+        // Step 2: Break copy-on-write sharing (on first write)
+        let token = match access {
+            Access::Write => match self.copy_on_write(v_page, mode, token) {
+                Ok(token) => return Ok(areas.unlock(token)),
+                Err((MemoryError::OutOfMemory, token)) => {
+                    return Err((PageFaultError::OutOfMemory, areas.unlock(token)));
+                }
+                Err((_, token)) => token,
+            },
+            Access::Read | Access::Execute => token,
+        };
+
+        // Step 3: Resolve faults of existing mappings (i.e. accessed/dirty bits managed by
+        // software), which do not necessarily belong to an area
+        let token = match self.mark_accessed(v_page, access, mode, token) {
+            (true, token) => return Ok(areas.unlock(token)),
+            (false, token) => token,
+        };
+
+        // Step 4: Find area covering `virt_addr` (or grow stack area)
+        let area = match areas.find(virt_addr) {
+            Some(area) => *area,
+            None => match areas.grow(virt_addr) {
+                Some(area) => area,
+                None => return Err((PageFaultError::NoSuchArea, areas.unlock(token))),
+            },
         };
 
-        result & 0x1ff
+        // Step 5: Check permissions
+        if mode == Mode::User && area.mode() == Mode::Kernel {
+            return Err((PageFaultError::PrivilegeViolation, areas.unlock(token)));
+        }
+        if !access.is_permitted_by(area.protection()) {
+            return Err((PageFaultError::AccessViolation, areas.unlock(token)));
+        }
+
+        // Step 6: Reclaim pages under memory pressure
+        let token = self.reclaim_under_pressure(&areas, token);
+
+        // Step 7: Allocate zero-filled page
+        let adapter = AdapterMappingPaging::new();
+        let (guard, t) = adapter.enter(token);
+        let (p_page, token) = match PAGE_FRAME_ALLOCATOR.allocate(t) {
+            Ok((p_page, t)) => (p_page, guard.leave(t)),
+            Err((_, t)) => {
+                let token = areas.unlock(guard.leave(t));
+                return Err((PageFaultError::OutOfMemory, token));
+            }
+        };
+
+        // Step 8: Read in page written out to the backing store (if any)
+        let token = match self.swap_in(v_page, p_page, &area, token) {
+            Ok((true, token)) => {
+                // Invalidate (possibly cached) invalid translation
+                self.invalidate_page(v_page);
+                return Ok(areas.unlock(token));
+            }
+            Ok((false, token)) => token,
+            Err((_, token)) => {
+                let adapter = AdapterMappingPaging::new();
+                let (guard, t) = adapter.enter(token);
+                let token = guard.leave(unsafe { PAGE_FRAME_ALLOCATOR.free(p_page, t) });
+                return Err((PageFaultError::BackingStore, areas.unlock(token)));
+            }
+        };
+
+        // Step 9: Map page (as clean, see `evict`)
+        let memory_type = MemoryType::PMA;
+        let result = self.create_page(
+            p_page,
+            v_page,
+            area.protection(),
+            area.mode(),
+            memory_type,
+            true,
+            token,
+        );
+        let (result, token) = match result {
+            Ok(token) => {
+                // Invalidate (possibly cached) invalid translation
+                self.invalidate_page(v_page);
+                (Ok(()), token)
+            }
+            Err((err, token)) => {
+                // Free unused page
+                let adapter = AdapterMappingPaging::new();
+                let (guard, t) = adapter.enter(token);
+                let token = guard.leave(unsafe { PAGE_FRAME_ALLOCATOR.free(p_page, t) });
+
+                match err {
+                    // Page was populated concurrently (i.e. spurious fault)
+                    MemoryError::AddressAlreadyInUse => {
+                        match self.mark_accessed(v_page, access, mode, token) {
+                            (true, token) => (Ok(()), token),
+                            (false, token) => (Err(PageFaultError::AccessViolation), token),
+                        }
+                    }
+                    _ => (Err(PageFaultError::OutOfMemory), token),
+                }
+            }
+        };
+
+        let token = areas.unlock(token);
+        match result {
+            Ok(()) => Ok(token),
+            Err(err) => Err((err, token)),
+        }
+    }
+
+    /// Reclaim up to `target` pages of virtual memory areas using a clock (second-chance) scan.
+    ///
+    /// Pages accessed since the last scan get their accessed bit cleared, while all other pages
+    /// are evicted (see [`reclaim`](crate::mm::reclaim)). Pages shared with other address spaces
+    /// are skipped. On success, the number of reclaimed pages is returned.
+    pub fn reclaim(&self, target: usize, token: LevelMemory) -> (usize, LevelMemory) {
+        let (areas, token) = self.areas.lock(token);
+        let (reclaimed, token) = self.reclaim_pages(&areas, target, token);
+        (reclaimed, areas.unlock(token))
+    }
+
+    /// Reclaim pages (see [`reclaim`](Self::reclaim)), if the number of free pages dropped below
+    /// the low watermark.
+    fn reclaim_under_pressure(
+        &self,
+        areas: &VirtualMemoryAreas,
+        token: LevelMapping,
+    ) -> LevelMapping {
+        let adapter = AdapterMappingPaging::new();
+        let (guard, t) = adapter.enter(token);
+        let (under_pressure, t) = reclaim::is_under_pressure(t);
+        let token = guard.leave(t);
+        if !under_pressure {
+            return token;
+        }
+
+        let (_, token) = self.reclaim_pages(areas, config::RECLAIM_BATCH, token);
+        token
+    }
+
+    /// Reclaim up to `target` pages of `areas` (see [`reclaim`](Self::reclaim)).
+    ///
+    /// Dirty pages are unmapped while scanning, but written out in batches of `WRITE_OUT_BATCH`
+    /// pages after releasing the page tables (see [`write_out`](Self::write_out)).
+    fn reclaim_pages(
+        &self,
+        areas: &VirtualMemoryAreas,
+        target: usize,
+        token: LevelMapping,
+    ) -> (usize, LevelMapping) {
+        const NUM_SLOTS: usize = 4 * NUM_PAGE_TABLE_ENTRIES * NUM_PAGE_TABLE_ENTRIES;
+        const WRITE_OUT_BATCH: usize = 8;
+        let user_space: VirtualAddress<c_void> =
+            VirtualAddress::new(USER_SPACE_START as *mut c_void);
+
+        let (mut user_pts_1, token) = self.user_pts_1.lock(token);
+        let mut token = token;
+        let mut reclaimed = 0;
+        let mut index = self.clock_hand.load(Ordering::Relaxed);
+        let mut batch: [Option<(VirtualAddress<c_void>, PageTableEntry)>; WRITE_OUT_BATCH] =
+            [const { None }; WRITE_OUT_BATCH];
+        let mut num = 0;
+
+        // Scan all user pages at most twice (i.e. pages aged during the first round may be
+        // evicted during the second one)
+        for _ in 0..2 {
+            let end = index + NUM_SLOTS;
+            let mut aged = false;
+            while index < end && reclaimed + num < target {
+                // Write out batch of unmapped dirty pages (without holding the page tables)
+                if num == WRITE_OUT_BATCH {
+                    let t = user_pts_1.unlock(token);
+                    let (written, t) = self.write_out(&mut batch, t);
+                    (user_pts_1, token) = self.user_pts_1.lock(t);
+                    reclaimed += written;
+                    num = 0;
+                }
+
+                let slot = index % NUM_SLOTS;
+                let vpn_0 = slot / (NUM_PAGE_TABLE_ENTRIES * NUM_PAGE_TABLE_ENTRIES);
+                let vpn_1 = (slot / NUM_PAGE_TABLE_ENTRIES) % NUM_PAGE_TABLE_ENTRIES;
+                let vpn_2 = slot % NUM_PAGE_TABLE_ENTRIES;
+
+                // Skip missing second and third page tables
+                let p_pt_1 = user_pts_1.0[vpn_0];
+                if p_pt_1.is_null() {
+                    index += (NUM_PAGE_TABLE_ENTRIES - vpn_1) * NUM_PAGE_TABLE_ENTRIES - vpn_2;
+                    continue;
+                }
+                let v_pt_1 = PageFrameAllocator::phys_to_virt(p_pt_1);
+                let pte_1 = unsafe { v_pt_1.add(vpn_1).as_ptr().as_ref().unwrap() };
+                if !pte_1.is_valid() || !pte_1.is_inner_page_table() {
+                    index += NUM_PAGE_TABLE_ENTRIES - vpn_2;
+                    continue;
+                }
+                let v_pt_2: VirtualAddress<PageTableEntry> =
+                    PageFrameAllocator::phys_to_virt(pte_1.get_physical_page());
+                let pte_2 = unsafe { v_pt_2.add(vpn_2).as_mut_ptr().as_mut().unwrap() };
+                index += 1;
+
+                // Only consider pages of virtual memory areas
+                let virt_addr = unsafe { user_space.byte_add(slot * cpu::page_size()) };
+                if !pte_2.is_valid() || pte_2.is_napot() || areas.find(virt_addr).is_none() {
+                    continue;
+                }
+
+                // Give accessed pages a second chance
+                if pte_2.is_accessed() {
+                    pte_2.clear_access_flag();
+                    aged = true;
+                    continue;
+                }
+
+                // Skip shared pages
+                let (references, t) =
+                    PAGE_FRAME_ALLOCATOR.references(pte_2.get_physical_page(), token);
+                token = t;
+                if references > 1 {
+                    continue;
+                }
+
+                token = match self.evict(pte_2, virt_addr, token) {
+                    Ok((None, t)) => {
+                        reclaimed += 1;
+                        t
+                    }
+                    Ok((Some(old), t)) => {
+                        batch[num] = Some((virt_addr, old));
+                        num += 1;
+                        t
+                    }
+                    Err(t) => t,
+                };
+            }
+
+            // Flush cleared accessed bits (as cached translations would not set them again)
+            if aged {
+                self.shootdown_space(user_space);
+            }
+            if reclaimed + num >= target {
+                break;
+            }
+        }
+
+        // Write out remaining dirty pages
+        self.clock_hand.store(index % NUM_SLOTS, Ordering::Relaxed);
+        let token = user_pts_1.unlock(token);
+        let (written, token) = self.write_out(&mut batch[..num], token);
+        (reclaimed + written, token)
+    }
+
+    /// Evict page mapped by `pte` at `virt_addr`.
+    ///
+    /// Clean pages (i.e. zero-filled pages never written, see [`create_page`](Self::create_page))
+    /// are freed. Dirty pages are unmapped and returned, to be written out to the backing store
+    /// after releasing the page tables (see [`write_out`](Self::write_out)). Without backing
+    /// store, the mapping of dirty pages is kept and an `Err` is returned.
+    fn evict(
+        &self,
+        pte: &mut PageTableEntry,
+        virt_addr: VirtualAddress<c_void>,
+        token: LevelPaging,
+    ) -> Result<(Option<PageTableEntry>, LevelPaging), LevelPaging> {
+        // Step 1: Keep dirty pages without backing store
+        if pte.is_dirty() && reclaim::backing_store().is_none() {
+            return Err(token);
+        }
+
+        // Step 2: Revoke mapping on all harts (before writing out, as the page could be modified
+        // otherwise)
+        let old = pte.take();
+        self.shootdown_page(virt_addr);
+
+        // Step 3: Free clean page
+        match old.is_dirty() {
+            true => Ok((Some(old), token)),
+            false => {
+                let p_page = old.get_physical_page();
+                Ok((None, unsafe { PAGE_FRAME_ALLOCATOR.release(p_page, token) }))
+            }
+        }
+    }
+
+    /// Write out unmapped dirty pages of `batch` (see [`evict`](Self::evict)) to the backing
+    /// store and get the number of written pages.
+    ///
+    /// Written pages are marked as swapped and freed afterwards, while the mappings of all others
+    /// are restored.
+    fn write_out(
+        &self,
+        batch: &mut [Option<(VirtualAddress<c_void>, PageTableEntry)>],
+        token: LevelMapping,
+    ) -> (usize, LevelMapping) {
+        // Dirty pages are only unmapped with backing store
+        let store = match reclaim::backing_store() {
+            Some(store) => store,
+            None => return (0, token),
+        };
+
+        let mut written = 0;
+        let mut token = token;
+        for (virt_addr, old) in batch.iter_mut().filter_map(Option::take) {
+            // Step 1: Write out page (without holding the page tables)
+            let p_page = old.get_physical_page();
+            let adapter = AdapterMappingPaging::new();
+            let (guard, t) = adapter.enter(token);
+            let (slot, t) = match store.write_out(p_page, t) {
+                Ok((slot, t)) => (Some(slot), guard.leave(t)),
+                Err((_, t)) => (None, guard.leave(t)),
+            };
+
+            // Step 2: Mark entry as swapped and free page (or restore mapping on failure)
+            let (user_pts_1, t) = self.user_pts_1.lock(t);
+            let pte = Self::user_page_table_entry(&user_pts_1, virt_addr).unwrap();
+            let t = match slot {
+                Some(slot) => {
+                    pte.mark_as_swapped(slot);
+                    written += 1;
+                    unsafe { PAGE_FRAME_ALLOCATOR.release(p_page, t) }
+                }
+                None => {
+                    // Invalidate (possibly cached) invalid translation
+                    *pte = old;
+                    self.invalidate_page(virt_addr);
+                    t
+                }
+            };
+            token = user_pts_1.unlock(t);
+        }
+
+        (written, token)
+    }
+
+    /// Read in page `virt_addr` written out to the backing store into `p_page` and map it
+    /// according to `area`.
+    ///
+    /// Returns whether the page was written out at all.
+    fn swap_in(
+        &self,
+        virt_addr: VirtualAddress<c_void>,
+        p_page: PhysicalAddress<c_void>,
+        area: &VirtualMemoryArea,
+        token: LevelMapping,
+    ) -> Result<(bool, LevelMapping), (MemoryError, LevelMapping)> {
+        // Step 1: Check for page written out
+        let (user_pts_1, token) = self.user_pts_1.lock(token);
+        let pte = match Self::user_page_table_entry(&user_pts_1, virt_addr) {
+            Some(pte) if pte.is_swapped() => pte,
+            _ => return Ok((false, user_pts_1.unlock(token))),
+        };
+
+        // Step 2: Read in page (dropping the reference to its slot)
+        let slot = pte.get_swap_slot();
+        let store = reclaim::backing_store().unwrap();
+        let token = match store.read_in(slot, p_page, token) {
+            Ok(token) => store.release(slot, token),
+            Err((err, token)) => return Err((err, user_pts_1.unlock(token))),
+        };
+
+        // Step 3: Map page (as dirty, as the backing store no longer holds its content)
+        pte.clear();
+        pte.set_physical_page(p_page);
+        pte.mark_as_readable(area.protection().is_readable());
+        pte.mark_as_writable(area.protection().is_writable());
+        pte.mark_as_executable(area.protection().is_executable());
+        pte.mark_as_user_accessible(area.mode() == Mode::User);
+        pte.set_access_flag();
+        pte.set_dirty_flag();
+        pte.mark_as_valid(true);
+
+        Ok((true, user_pts_1.unlock(token)))
+    }
+
+    /// Drop page `virt_addr` written out to the backing store (if any).
+    fn discard_swapped(
+        &self,
+        virt_addr: VirtualAddress<c_void>,
+        token: LevelMapping,
+    ) -> LevelMapping {
+        let (user_pts_1, token) = self.user_pts_1.lock(token);
+        let token = match Self::user_page_table_entry(&user_pts_1, virt_addr) {
+            Some(pte) if pte.is_swapped() => {
+                let slot = pte.get_swap_slot();
+                pte.clear();
+                reclaim::backing_store().unwrap().release(slot, token)
+            }
+            _ => token,
+        };
+        user_pts_1.unlock(token)
+    }
+
+    /// Set accessed (and for writes dirty) bit of the existing mapping of `virt_addr`, if it
+    /// permits `access` from `mode`.
+    ///
+    /// Hardware without automatic updates of the accessed/dirty bits raises page faults instead.
+    fn mark_accessed(
+        &self,
+        virt_addr: VirtualAddress<c_void>,
+        access: Access,
+        mode: Mode,
+        token: LevelMapping,
+    ) -> (bool, LevelMapping) {
+        // Only pages of user space are aged (i.e. get their accessed bit cleared)
+        if virt_addr.addr() >= KERNEL_SPACE_START {
+            return match self.lookup(virt_addr, token) {
+                Ok((_, protection, page_mode, _, token)) => {
+                    let privileged = mode == Mode::Kernel || page_mode == Mode::User;
+                    (privileged && access.is_permitted_by(protection), token)
+                }
+                Err((_, token)) => (false, token),
+            };
+        }
+
+        let (user_pts_1, token) = self.user_pts_1.lock(token);
+        let permitted = match Self::user_page_table_entry(&user_pts_1, virt_addr) {
+            Some(pte) if pte.is_valid() && (mode == Mode::Kernel || pte.is_user_accessible()) => {
+                let protection = Protection::from_flags(
+                    pte.is_readable(),
+                    pte.is_writable(),
+                    pte.is_executable(),
+                );
+                let permitted =
+                    protection.is_some_and(|protection| access.is_permitted_by(protection));
+                if permitted {
+                    pte.set_access_flag();
+                    if access == Access::Write {
+                        pte.set_dirty_flag();
+                    }
+                    self.invalidate_page(virt_addr);
+                }
+                permitted
+            }
+            _ => false,
+        };
+        (permitted, user_pts_1.unlock(token))
+    }
+
+    /// Get page table entry of `4KiB` user page `virt_addr` (if its third page table exists).
+    fn user_page_table_entry<'a>(
+        user_pts_1: &PageTableSubspace,
+        virt_addr: VirtualAddress<c_void>,
+    ) -> Option<&'a mut PageTableEntry> {
+        if virt_addr.addr() >> 32 != USER_SPACE_START >> 32 {
+            return None;
+        }
+
+        // Check second page table
+        let vpn_0 = Self::offset(virt_addr, PageSize::Size1GiB.level());
+        let p_pt_1 = user_pts_1.0[vpn_0];
+        if p_pt_1.is_null() {
+            return None;
+        }
+        let v_pt_1 = PageFrameAllocator::phys_to_virt(p_pt_1);
+        let vpn_1 = Self::offset(virt_addr, PageSize::Size2MiB.level());
+        let pte_1 = unsafe { v_pt_1.add(vpn_1).as_ptr().as_ref().unwrap() };
+
+        // Check third page table
+        if !pte_1.is_valid() || !pte_1.is_inner_page_table() {
+            return None;
+        }
+        let v_pt_2: VirtualAddress<PageTableEntry> =
+            PageFrameAllocator::phys_to_virt(pte_1.get_physical_page());
+        let vpn_2 = Self::offset(virt_addr, PageSize::Size4KiB.level());
+        unsafe { v_pt_2.add(vpn_2).as_mut_ptr().as_mut() }
+    }
+
+    /// Replace copy-on-write mapping of `virt_addr` by a private, writable copy (or reuse the page
+    /// if no longer shared) on a write from `mode`.
+    ///
+    /// If `virt_addr` is not mapped copy-on-write, [`MemoryError::NoSuchAddress`] is returned. If
+    /// the mapping is not accessible from `mode`, [`MemoryError::PermissionDenied`] is returned.
+    fn copy_on_write(
+        &self,
+        virt_addr: VirtualAddress<c_void>,
+        mode: Mode,
+        token: LevelMapping,
+    ) -> Result<LevelMapping, (MemoryError, LevelMapping)> {
+        // Check address (only user space is shared copy-on-write)
+        if self.gigapage_table(virt_addr).is_none() {
+            return Err((MemoryError::InvalidAddress, token));
+        }
+
+        // Check second page table
+        let vpn_0 = Self::offset(virt_addr, PageSize::Size1GiB.level());
+        let (p_pts_1, token) = match vpn_0 {
+            0 | 1 | 2 | 3 => self.user_pts_1.lock(token),
+            _ => return Err((MemoryError::InvalidAddress, token)),
+        };
+        let p_pt_1 = p_pts_1.0[vpn_0];
+        if p_pt_1.is_null() {
+            return Err((MemoryError::NoSuchAddress, p_pts_1.unlock(token)));
+        }
+        let v_pt_1 = PageFrameAllocator::phys_to_virt(p_pt_1);
+        let vpn_1 = Self::offset(virt_addr, PageSize::Size2MiB.level());
+        let pte_1 = unsafe { v_pt_1.add(vpn_1).as_ptr().as_ref().unwrap() };
+
+        // Check third page table
+        if !pte_1.is_valid() || !pte_1.is_inner_page_table() {
+            return Err((MemoryError::NoSuchAddress, p_pts_1.unlock(token)));
+        }
+        let v_pt_2: VirtualAddress<PageTableEntry> =
+            PageFrameAllocator::phys_to_virt(pte_1.get_physical_page());
+        let vpn_2 = Self::offset(virt_addr, PageSize::Size4KiB.level());
+        let pte_2 = unsafe { v_pt_2.add(vpn_2).as_mut_ptr().as_mut().unwrap() };
+        if !pte_2.is_valid() || !pte_2.is_copy_on_write() {
+            return Err((MemoryError::NoSuchAddress, p_pts_1.unlock(token)));
+        }
+        if mode == Mode::User && !pte_2.is_user_accessible() {
+            return Err((MemoryError::PermissionDenied, p_pts_1.unlock(token)));
+        }
+
+        // Reuse page if no longer shared, otherwise copy it
+        let p_page = pte_2.get_physical_page();
+        let (references, token) = PAGE_FRAME_ALLOCATOR.references(p_page, token);
+        let token = match references {
+            1 => {
+                pte_2.mark_as_copy_on_write(false);
+                pte_2.mark_as_writable(true);
+                pte_2.set_access_flag();
+                pte_2.set_dirty_flag();
+
+                // Invalidate stale (read-only) translation
+                self.invalidate_page(virt_addr);
+                token
+            }
+            _ => {
+                let (p_copy, token) = match PAGE_FRAME_ALLOCATOR.allocate(token) {
+                    Ok(result) => result,
+                    Err((err, token)) => return Err((err, p_pts_1.unlock(token))),
+                };
+                let v_page = PageFrameAllocator::phys_to_virt(p_page);
+                let mut v_copy = PageFrameAllocator::phys_to_virt(p_copy);
+                unsafe {
+                    ptr::copy_nonoverlapping(
+                        v_page.as_ptr().cast::<u8>(),
+                        v_copy.as_mut_ptr().cast::<u8>(),
+                        cpu::page_size(),
+                    )
+                };
+
+                pte_2.set_physical_page(p_copy);
+                pte_2.mark_as_copy_on_write(false);
+                pte_2.mark_as_writable(true);
+                pte_2.set_access_flag();
+                pte_2.set_dirty_flag();
+
+                // Invalidate stale translation on all harts (before dropping the shared reference)
+                self.shootdown_page(virt_addr);
+                unsafe { PAGE_FRAME_ALLOCATOR.release(p_page, token) }
+            }
+        };
+
+        // Unlock mapping
+        let token = p_pts_1.unlock(token);
+        Ok(token)
+    }
+
+    /// Unmap all pages populated for `area` and drop their references (freeing unshared pages).
+    fn release_area(&self, area: &VirtualMemoryArea, token: LevelMapping) -> LevelMapping {
+        let mut token = token;
+        let mut virt_addr = area.start();
+        while virt_addr < area.end() {
+            token = match self.lookup(virt_addr, token) {
+                Ok((p_page, _, _, _, t)) => {
+                    let t = match self.remove(virt_addr, t) {
+                        Ok(t) => t,
+                        Err((_, t)) => t,
+                    };
+
+                    let adapter = AdapterMappingPaging::new();
+                    let (guard, t) = adapter.enter(t);
+                    guard.leave(unsafe { PAGE_FRAME_ALLOCATOR.release(p_page, t) })
+                }
+                Err((_, t)) => self.discard_swapped(virt_addr, t),
+            };
+            virt_addr = unsafe { virt_addr.byte_add(cpu::page_size()) };
+        }
+
+        token
+    }
+
+    /// Turn `pte` into a valid leaf mapping `phys_addr` with specified `protection`/`mode`.
+    fn set_leaf(
+        pte: &mut PageTableEntry,
+        phys_addr: PhysicalAddress<c_void>,
+        protection: Protection,
+        mode: Mode,
+        memory_type: MemoryType,
+    ) {
+        pte.set_physical_page(phys_addr);
+        pte.mark_as_readable(protection.is_readable());
+        pte.mark_as_writable(protection.is_writable());
+        pte.mark_as_executable(protection.is_executable());
+        pte.mark_as_user_accessible(mode == Mode::User);
+        if paging_extensions().svpbmt {
+            pte.set_memory_type(memory_type.encoding());
+        }
+        pte.mark_as_valid(true);
+    }
+
+    /// Split `64KiB` mapping containing entry `vpn_2` of third page table `v_pt_2` (if any) into
+    /// sixteen regular `4KiB` mappings.
+    ///
+    /// As the translations stay the same, no invalidation is required.
+    fn split_napot(v_pt_2: VirtualAddress<PageTableEntry>, vpn_2: usize) {
+        let num_entries = PageSize::Size64KiB.size() / cpu::page_size();
+        let vpn_start = vpn_2 - vpn_2 % num_entries;
+        for i in 0..num_entries {
+            let pte = unsafe { v_pt_2.add(vpn_start + i).as_mut_ptr().as_mut().unwrap() };
+            if !pte.is_napot() {
+                continue;
+            }
+
+            // Update entry at once (as it may be used concurrently)
+            let p_page: PhysicalAddress<c_void> = pte.get_physical_page();
+            let mut entry = pte.clone();
+            entry.mark_as_napot(false);
+            entry.set_physical_page(unsafe { p_page.byte_add(i * cpu::page_size()) });
+            *pte = entry;
+        }
+    }
+
+    /// Check if page table (referenced by `v_pt`) contains only invalid entries.
+    fn is_page_table_empty(v_pt: VirtualAddress<PageTableEntry>) -> bool {
+        (0..NUM_PAGE_TABLE_ENTRIES)
+            .map(|i| unsafe { v_pt.add(i).as_ptr().as_ref().unwrap() })
+            .all(|pte| !pte.is_valid() && !pte.is_swapped())
+    }
+
+    /// Get page table (at level of [`PageSize::Size1GiB`]) covering `virt_addr` by walking the upper
+    /// page tables (only present for Sv48 and Sv57).
+    ///
+    /// Only the lower `4GiB` (user space) and the upper `4GiB` (kernel space) are managed, thus
+    /// `None` is returned for any other address.
+    fn gigapage_table(
+        &self,
+        virt_addr: VirtualAddress<c_void>,
+    ) -> Option<VirtualAddress<PageTableEntry>> {
+        if virt_addr.addr() >> 32 != USER_SPACE_START >> 32
+            && virt_addr.addr() >> 32 != KERNEL_SPACE_START >> 32
+        {
+            return None;
+        }
+
+        let mut p_pt = *self.root;
+        for level in 0..PageSize::Size1GiB.level() {
+            let v_pt = PageFrameAllocator::phys_to_virt(p_pt);
+            let vpn = Self::offset(virt_addr, level);
+            let pte = unsafe { v_pt.add(vpn).as_ptr().as_ref().unwrap() };
+            assert!(pte.is_valid() && pte.is_inner_page_table());
+
+            p_pt = pte.get_physical_page();
+        }
+
+        Some(PageFrameAllocator::phys_to_virt(p_pt))
+    }
+
+    /// Free upper page tables of user space (only present for Sv48 and Sv57) below root page table
+    /// `p_root`.
+    fn free_user_upper_page_tables(
+        p_root: PhysicalAddress<PageTableEntry>,
+        token: LevelPaging,
+    ) -> LevelPaging {
+        let user_space: VirtualAddress<c_void> =
+            VirtualAddress::new(USER_SPACE_START as *mut c_void);
+
+        let mut token = token;
+        let mut p_pt = p_root;
+        for level in 0..PageSize::Size1GiB.level() {
+            let v_pt = PageFrameAllocator::phys_to_virt(p_pt);
+            let vpn = Self::offset(user_space, level);
+            let pte = unsafe { v_pt.add(vpn).as_ptr().as_ref().unwrap() };
+            if !pte.is_valid() {
+                break;
+            }
+
+            let p_pt_next = pte.get_physical_page();
+            if p_pt != p_root {
+                token = unsafe { PAGE_FRAME_ALLOCATOR.free(p_pt.cast(), token) };
+            }
+            p_pt = p_pt_next;
+        }
+        if p_pt != p_root {
+            token = unsafe { PAGE_FRAME_ALLOCATOR.free(p_pt.cast(), token) };
+        }
+
+        token
+    }
+
+    /// Get offset within page table at `level` (with `0` being the root page table).
+    pub fn offset<T>(virt_addr: VirtualAddress<T>, level: usize) -> usize {
+        let levels = paging_mode().levels();
+        if level >= levels {
+            panic!("Unsupported level {} for {:?}", level, paging_mode());
+        }
+
+        (virt_addr.addr() >> (12 + 9 * (levels - 1 - level))) & 0x1ff
+    }
+}
+
+impl VirtualMemorySystem {
+    /// Destroy [`VirtualMemorySystem`], releasing all pages populated for virtual memory areas and
+    /// freeing all (non-shared) page tables.
+    ///
+    /// # Panic
+    /// If the virtual memory system is loaded on any hart, this function will panic!
+    pub fn destroy(mut self, token: LevelMapping) -> LevelMapping {
+        // Step 1: Check that no hart has loaded the root page table (activated virtual memory
+        // systems are never moved, see `activate`)
+        let p_root = *self.root.as_ref();
+        for active in ACTIVE_VIRTUAL_MEMORY_SYSTEMS.iter() {
+            let active = unsafe { active.load(Ordering::Relaxed).as_ref() };
+            assert!(
+                active.is_none_or(|active| *active.root.as_ref() != p_root),
+                "Destroying loaded virtual memory system"
+            );
+        }
+
+        // Step 2: Release pages populated for virtual memory areas
+        let mut token = token;
+        let areas = mem::replace(self.areas.get_mut(), VirtualMemoryAreas::new());
+        for area in areas.iter() {
+            token = self.release_area(area, token);
+        }
+        let adapter = AdapterMappingPaging::new();
+        let (guard, mut token) = adapter.enter(token);
+
+        // Step 3: Free user page tables for level 1 and 2
+        for p_pt_1 in self.user_pts_1.get_mut().0 {
+            if p_pt_1.is_null() {
+                continue;
+            }
+
+            let v_pt_1 = PageFrameAllocator::phys_to_virt(p_pt_1);
+            for vpn_1 in 0..NUM_PAGE_TABLE_ENTRIES {
+                let pte_1 = unsafe { v_pt_1.add(vpn_1).as_ptr().as_ref().unwrap() };
+                if pte_1.is_valid() && pte_1.is_inner_page_table() {
+                    token = unsafe { PAGE_FRAME_ALLOCATOR.free(pte_1.get_physical_page(), token) };
+                }
+            }
+
+            token = unsafe { PAGE_FRAME_ALLOCATOR.free(p_pt_1.cast(), token) };
+        }
+
+        // Step 4: Free upper page tables of user space
+        let token = Self::free_user_upper_page_tables(*self.root.as_ref(), token);
+
+        // Step 5: Free first (root) page table
+        //
+        // # Safety
+        // The kernel page tables (for level 1 and above) are shared and thus must not be freed.
+        let token = unsafe { PAGE_FRAME_ALLOCATOR.free(p_root.cast(), token) };
+
+        // Skip `Drop` (all resources are released)
+        mem::forget(self);
+        guard.leave(token)
+    }
+}
+
+impl Drop for VirtualMemorySystem {
+    fn drop(&mut self) {
+        panic!("Virtual memory system dropped without being destroyed");
     }
 }
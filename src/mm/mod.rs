@@ -1,6 +1,16 @@
 //! Memory Management APIs
 
+pub mod asid;
+pub mod dma;
 pub mod error;
+pub mod heap;
 pub mod mapping;
 pub mod page_allocator;
+pub mod page_fault;
+pub mod ptdump;
 pub mod pte;
+pub mod reclaim;
+pub mod tlb;
+pub mod uaccess;
+pub mod vma;
+pub mod vmap;
@@ -1,13 +1,25 @@
 //! Page-Frame Allocator.
 
 use core::ffi::c_void;
+use core::fmt::Display;
+use core::mem;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
 
+use crate::boot::device_tree::dt::DeviceTree;
+use crate::config;
 use crate::kernel::address::Address;
 use crate::kernel::address::PhysicalAddress;
 use crate::kernel::address::VirtualAddress;
+use crate::kernel::backtrace::Backtrace;
 use crate::kernel::compiler;
 use crate::kernel::cpu;
+use crate::kernel::cpu_map::LogicalCPUID;
+use crate::kernel::printer::LogLevel;
 use crate::mm::error::MemoryError;
+use crate::mm::mapping::KERNEL_VIRTUAL_MEMORY_SYSTEM;
+use crate::mm::vmap;
+use crate::printk;
 use crate::sync::level::LevelInitialization;
 use crate::sync::level::LevelPaging;
 use crate::sync::ticketlock::TicketlockPaging;
@@ -15,17 +27,583 @@ use crate::sync::ticketlock::TicketlockPaging;
 /// Global [`PageFrameAllocator`] instance.
 pub static PAGE_FRAME_ALLOCATOR: PageFrameAllocator = PageFrameAllocator::new();
 
-const MAX_SIZE: usize = 0x10000000;
+/// Maximum size of the (statically allocated) boot zone.
+const MAX_BOOT_SIZE: usize = 0x10000000;
 
-/// Page-Frame Allocator capable of managing at most 256 MiB.
+/// Maximum number of pages within the boot zone.
+const MAX_BOOT_PAGES: usize = MAX_BOOT_SIZE / cpu::page_size();
+
+/// Maximum number of disjoint physical memory regions (zones).
+const MAX_ZONES: usize = 16;
+
+/// Size of the kernel's direct map (physical memory `[0, DIRECT_MAP_SIZE)` is accessible at
+/// `phys_addr + offset`).
+const DIRECT_MAP_SIZE: usize = 0x100000000;
+
+/// Maximum supported order (i.e. the largest block consists of `2^MAX_ORDER` pages).
+pub const MAX_ORDER: usize = 18;
+
+/// Marker for the end of a free list.
+const NONE: usize = usize::MAX;
+
+/// Marker (within page metadata) for the head of a free block.
+const META_FREE: u8 = 1 << 7;
+
+/// Marker (within page metadata) for the head of an allocated block.
+const META_ALLOCATED: u8 = 1 << 6;
+
+/// Mask (within page metadata) for the order of a block.
+const META_ORDER: u8 = META_ALLOCATED - 1;
+
+/// Pattern filling free pages (see `CONFIG_DEBUG_PAGE_ALLOCATOR`).
+const POISON: u8 = 0xaa;
+
+/// Pattern filling the redzones surrounding allocated blocks (see `CONFIG_DEBUG_PAGE_ALLOCATOR`).
+const REDZONE: u8 = 0x5c;
+
+/// Additional order of blocks embedding an allocated block together with its redzones (see
+/// `CONFIG_DEBUG_PAGE_ALLOCATOR`).
+const REDZONE_ORDER: usize = 2;
+
+/// Number of pages within the boot zone with a [`Record`] (see `CONFIG_DEBUG_PAGE_ALLOCATOR`).
+const DEBUG_BOOT_PAGES: usize = match config::DEBUG_PAGE_ALLOCATOR {
+    true => MAX_BOOT_PAGES,
+    false => 0,
+};
+
+/// Links of a doubly-linked free list (stored within the first page of each free block).
+struct FreeBlock {
+    next: usize,
+    prev: usize,
+}
+
+/// Last allocation or deallocation of a block (see `CONFIG_DEBUG_PAGE_ALLOCATOR`).
+#[derive(Debug, Clone, Copy)]
+struct Record {
+    /// Hart performing the (de)allocation.
+    hart: usize,
+    /// Backtrace of the (de)allocation.
+    backtrace: Backtrace,
+}
+
+impl Record {
+    const EMPTY: Record = Record {
+        hart: 0,
+        backtrace: Backtrace::empty(),
+    };
+
+    /// Record (de)allocation performed by the calling function.
+    #[inline(always)]
+    fn capture() -> Self {
+        Self {
+            hart: cpu::current().raw(),
+            backtrace: Backtrace::capture(),
+        }
+    }
+}
+
+impl Display for Record {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "hart {} [{}]", self.hart, self.backtrace)
+    }
+}
+
+/// Contiguous range of physical memory managed by the allocator.
+struct Zone {
+    /// Page frame number of the first managed page.
+    base_pfn: usize,
+    /// Number of managed pages.
+    num_pages: usize,
+    /// Metadata of each page (only valid for the first page of a block).
+    meta: *mut u8,
+    /// Number of additional references of each (allocated) page (see
+    /// [`share`](PageFrameAllocator::share)).
+    refs: *mut u16,
+    /// Last (de)allocation of each page (only valid for the first page of a block and only with
+    /// `CONFIG_DEBUG_PAGE_ALLOCATOR`).
+    records: *mut Record,
+}
+
+impl Zone {
+    const EMPTY: Zone = Zone {
+        base_pfn: 0,
+        num_pages: 0,
+        meta: core::ptr::null_mut(),
+        refs: core::ptr::null_mut(),
+        records: core::ptr::null_mut(),
+    };
+
+    /// Check if the pages `[pfn, pfn + num_pages)` are part of the zone.
+    fn contains(&self, pfn: usize, num_pages: usize) -> bool {
+        pfn >= self.base_pfn && pfn + num_pages <= self.base_pfn + self.num_pages
+    }
+}
+
+/// State of the buddy allocator.
+struct BuddyState {
+    /// Managed zones.
+    zones: [Zone; MAX_ZONES],
+    /// Number of managed zones.
+    num_zones: usize,
+    /// Head (page frame number) of free list for each order.
+    free_lists: [usize; MAX_ORDER + 1],
+    /// Number of pages within all free lists.
+    free_pages: usize,
+    /// Metadata of boot zone.
+    boot_meta: [u8; MAX_BOOT_PAGES],
+    /// Additional references of boot zone.
+    boot_refs: [u16; MAX_BOOT_PAGES],
+    /// Records of boot zone (see `CONFIG_DEBUG_PAGE_ALLOCATOR`).
+    boot_records: [Record; DEBUG_BOOT_PAGES],
+}
+
+unsafe impl Send for BuddyState {}
+
+impl BuddyState {
+    const fn new() -> Self {
+        Self {
+            zones: [Zone::EMPTY; MAX_ZONES],
+            num_zones: 0,
+            free_lists: [NONE; MAX_ORDER + 1],
+            free_pages: 0,
+            boot_meta: [0; MAX_BOOT_PAGES],
+            boot_refs: [0; MAX_BOOT_PAGES],
+            boot_records: [Record::EMPTY; DEBUG_BOOT_PAGES],
+        }
+    }
+
+    /// Get zone containing the pages `[pfn, pfn + num_pages)`.
+    fn zone(&self, pfn: usize, num_pages: usize) -> Option<&Zone> {
+        self.zones[..self.num_zones]
+            .iter()
+            .find(|zone| zone.contains(pfn, num_pages))
+    }
+
+    /// Get metadata of page `pfn`.
+    fn meta(&mut self, pfn: usize) -> &mut u8 {
+        let zone = self.zone(pfn, 1).unwrap();
+        unsafe { zone.meta.add(pfn - zone.base_pfn).as_mut().unwrap() }
+    }
+
+    /// Get number of additional references of page `pfn` (if managed by the allocator).
+    fn refs(&mut self, pfn: usize) -> Option<&mut u16> {
+        let zone = self.zone(pfn, 1)?;
+        unsafe { zone.refs.add(pfn - zone.base_pfn).as_mut() }
+    }
+
+    /// Get [`Record`] of page `pfn` (only with `CONFIG_DEBUG_PAGE_ALLOCATOR`).
+    fn record(&mut self, pfn: usize) -> &mut Record {
+        let zone = self.zone(pfn, 1).unwrap();
+        unsafe { zone.records.add(pfn - zone.base_pfn).as_mut().unwrap() }
+    }
+
+    /// Get free list links stored within page `pfn`.
+    fn links(&mut self, pfn: usize) -> &mut FreeBlock {
+        let mut v_page: VirtualAddress<FreeBlock> = PageFrameAllocator::phys_to_virt(page(pfn));
+        unsafe { v_page.as_mut_ptr().as_mut().unwrap() }
+    }
+
+    /// Insert free block starting at page `pfn` into free list of `order`.
+    fn push(&mut self, pfn: usize, order: usize) {
+        let head = self.free_lists[order];
+        let links = self.links(pfn);
+        links.next = head;
+        links.prev = NONE;
+        if head != NONE {
+            self.links(head).prev = pfn;
+        }
+
+        self.free_lists[order] = pfn;
+        *self.meta(pfn) = META_FREE | order as u8;
+    }
+
+    /// Remove free block starting at page `pfn` from free list of `order`.
+    fn remove(&mut self, pfn: usize, order: usize) {
+        let links = self.links(pfn);
+        let (next, prev) = (links.next, links.prev);
+        if prev != NONE {
+            self.links(prev).next = next;
+        } else {
+            self.free_lists[order] = next;
+        }
+        if next != NONE {
+            self.links(next).prev = prev;
+        }
+
+        *self.meta(pfn) = 0;
+    }
+
+    /// Register zone `[base_pfn, base_pfn + num_pages)` (using `meta`/`refs`/`records` to store
+    /// metadata, reference counts and debug records) and add all its pages as free blocks (as
+    /// large as possible).
+    fn populate(
+        &mut self,
+        base_pfn: usize,
+        num_pages: usize,
+        meta: *mut u8,
+        refs: *mut u16,
+        records: *mut Record,
+    ) -> Result<(), MemoryError> {
+        // No zone descriptor left
+        if self.num_zones >= MAX_ZONES {
+            return Err(MemoryError::OutOfMemory);
+        }
+
+        // Register zone
+        unsafe { meta.write_bytes(0, num_pages) };
+        unsafe { refs.write_bytes(0, num_pages) };
+        self.zones[self.num_zones] = Zone {
+            base_pfn,
+            num_pages,
+            meta,
+            refs,
+            records,
+        };
+        self.num_zones += 1;
+
+        // Debug: Poison all pages (before storing free list links)
+        if config::DEBUG_PAGE_ALLOCATOR {
+            for i in 0..num_pages {
+                unsafe { records.add(i).write(Record::EMPTY) };
+            }
+            poison(base_pfn, num_pages);
+        }
+
+        let mut pfn = base_pfn;
+        while pfn < base_pfn + num_pages {
+            // Find largest naturally aligned block which fits into the remaining range
+            let mut order = MAX_ORDER;
+            while pfn % (1 << order) != 0 || pfn + (1 << order) > base_pfn + num_pages {
+                order -= 1;
+            }
+
+            self.push(pfn, order);
+            pfn += 1 << order;
+        }
+        self.free_pages += num_pages;
+
+        return Ok(());
+    }
+
+    /// Allocate block of `2^order` pages.
+    ///
+    /// With `CONFIG_DEBUG_PAGE_ALLOCATOR`, the block is embedded into a block of `2^(order + 2)`
+    /// pages: The preceding `2^order` and following `2^(order + 1)` pages serve as redzones, which
+    /// are checked on [`free`](BuddyState::free).
+    fn allocate(&mut self, order: usize) -> Result<PhysicalAddress<c_void>, MemoryError> {
+        let pfn = match config::DEBUG_PAGE_ALLOCATOR {
+            false => self.allocate_block(order)?,
+            true => {
+                if order + REDZONE_ORDER > MAX_ORDER {
+                    return Err(MemoryError::OutOfMemory);
+                }
+                let outer_pfn = self.allocate_block(order + REDZONE_ORDER)?;
+
+                // Debug: Check for writes to free pages
+                if let Some(addr) = find_violation(
+                    outer_pfn,
+                    1 << (order + REDZONE_ORDER),
+                    POISON,
+                    mem::size_of::<FreeBlock>(),
+                ) {
+                    panic!(
+                        "Free page modified at {} (last (de)allocated by {})",
+                        addr,
+                        self.record(outer_pfn)
+                    );
+                }
+
+                // Debug: Surround block with redzones and record allocation
+                let pfn = outer_pfn + (1 << order);
+                fill(outer_pfn, 1 << order, REDZONE, 0);
+                fill(pfn + (1 << order), 2 << order, REDZONE, 0);
+                *self.meta(outer_pfn) = 0;
+                *self.meta(pfn) = META_ALLOCATED | order as u8;
+                *self.record(pfn) = Record::capture();
+                pfn
+            }
+        };
+
+        let p_block: PhysicalAddress<c_void> = page(pfn);
+
+        // Sanity check
+        assert!(p_block.addr() % cpu::page_size() == 0);
+        assert!(self.zone(pfn, 1 << order).is_some());
+
+        return Ok(p_block);
+    }
+
+    /// Allocate block of `2^order` pages, splitting larger blocks if necessary.
+    fn allocate_block(&mut self, order: usize) -> Result<usize, MemoryError> {
+        if order > MAX_ORDER {
+            return Err(MemoryError::OutOfMemory);
+        }
+
+        // Search for smallest available block
+        let mut current = order;
+        while self.free_lists[current] == NONE {
+            current += 1;
+            if current > MAX_ORDER {
+                return Err(MemoryError::OutOfMemory);
+            }
+        }
+        let pfn = self.free_lists[current];
+        self.remove(pfn, current);
+
+        // Split block until requested order is reached (returning upper halves)
+        while current > order {
+            current -= 1;
+            self.push(pfn + (1 << current), current);
+        }
+        *self.meta(pfn) = META_ALLOCATED | order as u8;
+        self.free_pages -= 1 << order;
+
+        Ok(pfn)
+    }
+
+    /// Free block of `2^order` pages (allocated by [`allocate`](BuddyState::allocate)).
+    unsafe fn free(&mut self, block: PhysicalAddress<c_void>, order: usize) {
+        // Sanity check: Is block valid?
+        assert!(block.addr() % cpu::page_size() == 0);
+        let pfn = block.addr() / cpu::page_size();
+        assert!(self.zone(pfn, 1 << order).is_some());
+
+        // Sanity check: Was block allocated with the same order?
+        if config::DEBUG_PAGE_ALLOCATOR && *self.meta(pfn) != META_ALLOCATED | order as u8 {
+            panic!(
+                "Invalid (or double) free of {} with order {} by {} (last (de)allocated by {})",
+                block,
+                order,
+                Record::capture(),
+                self.record(pfn)
+            );
+        }
+        assert!(*self.meta(pfn) == META_ALLOCATED | order as u8);
+        *self.meta(pfn) = 0;
+
+        if !config::DEBUG_PAGE_ALLOCATOR {
+            self.free_block(pfn, order);
+            return;
+        }
+
+        // Debug: Check redzones
+        let outer_pfn = pfn - (1 << order);
+        let violation = find_violation(outer_pfn, 1 << order, REDZONE, 0)
+            .or_else(|| find_violation(pfn + (1 << order), 2 << order, REDZONE, 0));
+        if let Some(addr) = violation {
+            panic!(
+                "Redzone of {} with order {} modified at {} (allocated by {})",
+                block,
+                order,
+                addr,
+                self.record(pfn)
+            );
+        }
+
+        // Debug: Record deallocation, poison pages and free embedding block
+        let record = Record::capture();
+        *self.record(pfn) = record;
+        *self.record(outer_pfn) = record;
+        poison(outer_pfn, 1 << (order + REDZONE_ORDER));
+        self.free_block(outer_pfn, order + REDZONE_ORDER);
+    }
+
+    /// Free block of `2^order` pages, coalescing with free buddies.
+    fn free_block(&mut self, pfn: usize, order: usize) {
+        self.free_pages += 1 << order;
+
+        // Coalesce with buddies (within the same zone) as long as possible
+        let mut pfn = pfn;
+        let mut order = order;
+        while order < MAX_ORDER {
+            let buddy_pfn = pfn ^ (1 << order);
+            let zone = self.zone(pfn, 1).unwrap();
+            if !zone.contains(buddy_pfn, 1 << order) {
+                break;
+            }
+            if *self.meta(buddy_pfn) != META_FREE | order as u8 {
+                break;
+            }
+
+            self.remove(buddy_pfn, order);
+            pfn = usize::min(pfn, buddy_pfn);
+            order += 1;
+        }
+
+        self.push(pfn, order);
+    }
+}
+
+/// Get physical address of page `pfn`.
+fn page<T>(pfn: usize) -> PhysicalAddress<T> {
+    PhysicalAddress::new((pfn * cpu::page_size()) as *mut T)
+}
+
+/// Get offset between physical addresses and the kernel's direct map.
+fn direct_map_offset() -> usize {
+    compiler::pages_mem_virt_start().addr() - compiler::pages_mem_phys_start().addr()
+}
+
+/// Fill pages `[pfn, pfn + num_pages)` with [`POISON`] (except for the free list links at the
+/// start of each page).
+fn poison(pfn: usize, num_pages: usize) {
+    fill(pfn, num_pages, POISON, mem::size_of::<FreeBlock>());
+}
+
+/// Fill pages `[pfn, pfn + num_pages)` with `pattern` (starting at `offset` within each page).
+fn fill(pfn: usize, num_pages: usize, pattern: u8, offset: usize) {
+    for pfn in pfn..pfn + num_pages {
+        let mut v_page: VirtualAddress<u8> = PageFrameAllocator::phys_to_virt(page(pfn));
+        unsafe {
+            v_page
+                .as_mut_ptr()
+                .add(offset)
+                .write_bytes(pattern, cpu::page_size() - offset)
+        };
+    }
+}
+
+/// Find first byte of pages `[pfn, pfn + num_pages)` not matching `pattern` (starting at `offset`
+/// within each page).
+fn find_violation(
+    pfn: usize,
+    num_pages: usize,
+    pattern: u8,
+    offset: usize,
+) -> Option<PhysicalAddress<u8>> {
+    for pfn in pfn..pfn + num_pages {
+        let v_page: VirtualAddress<u8> = PageFrameAllocator::phys_to_virt(page(pfn));
+        let bytes = unsafe { core::slice::from_raw_parts(v_page.as_ptr(), cpu::page_size()) };
+        let position = bytes.iter().skip(offset).position(|byte| *byte != pattern);
+        if let Some(position) = position {
+            let addr = pfn * cpu::page_size() + offset + position;
+            return Some(PhysicalAddress::new(addr as *mut u8));
+        }
+    }
+
+    None
+}
+
+/// Zero block of `2^order` pages at `block`.
+fn zero(block: PhysicalAddress<c_void>, order: usize) {
+    let mut v_block = PageFrameAllocator::phys_to_virt(block);
+    unsafe {
+        v_block
+            .as_mut_ptr()
+            .write_bytes(0, cpu::page_size() << order)
+    };
+}
+
+/// Per-hart cache (magazine) of free pages in front of the global buddy allocator.
+struct PageCache {
+    /// Cached (not necessarily zeroed) pages.
+    pages: [PhysicalAddress<c_void>; PAGE_CACHE_SIZE],
+    /// Number of cached pages.
+    len: usize,
+}
+
+unsafe impl Send for PageCache {}
+
+impl PageCache {
+    const fn new() -> Self {
+        Self {
+            pages: [PhysicalAddress::new(core::ptr::null_mut()); PAGE_CACHE_SIZE],
+            len: 0,
+        }
+    }
+}
+
+/// Per-hart counters of a [`PageCache`].
+struct PageCacheCounters {
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    refills: AtomicUsize,
+    drains: AtomicUsize,
+    /// Number of currently cached pages (readable without locking the cache).
+    cached: AtomicUsize,
+}
+
+impl PageCacheCounters {
+    const fn new() -> Self {
+        Self {
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+            refills: AtomicUsize::new(0),
+            drains: AtomicUsize::new(0),
+            cached: AtomicUsize::new(0),
+        }
+    }
+
+    /// Get counters of current hart.
+    fn current() -> &'static Self {
+        &PAGE_CACHE_COUNTERS[cpu::current().raw()]
+    }
+}
+
+/// Snapshot of the counters of a per-hart page cache.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PageCacheStats {
+    /// Number of allocations served by the cache.
+    pub hits: usize,
+    /// Number of allocations requiring a refill from the global allocator.
+    pub misses: usize,
+    /// Number of pages refilled from the global allocator.
+    pub refills: usize,
+    /// Number of pages drained to the global allocator.
+    pub drains: usize,
+}
+
+impl PageCacheStats {
+    /// Get hit rate (in percent).
+    pub fn hit_rate(&self) -> usize {
+        match self.hits + self.misses {
+            0 => 0,
+            total => self.hits * 100 / total,
+        }
+    }
+}
+
+/// Number of pages cached per hart.
+const PAGE_CACHE_SIZE: usize = 64;
+
+/// Number of pages transferred between a per-hart cache and the global allocator at once.
+const PAGE_CACHE_BATCH: usize = PAGE_CACHE_SIZE / 2;
+
+/// Per-hart page caches (only locked by other harts to drain them, see
+/// [`drain_caches`](PageFrameAllocator::drain_caches)).
+static PAGE_CACHES: [TicketlockPaging<PageCache>; config::MAX_CPU_NUM] =
+    [const { TicketlockPaging::new(PageCache::new()) }; config::MAX_CPU_NUM];
+
+/// Per-hart page cache counters.
+static PAGE_CACHE_COUNTERS: [PageCacheCounters; config::MAX_CPU_NUM] = {
+    const COUNTERS: PageCacheCounters = PageCacheCounters::new();
+    [COUNTERS; config::MAX_CPU_NUM]
+};
+
+/// Buddy-based Page-Frame Allocator.
+///
+/// Free memory is organized as naturally aligned blocks of `2^order` pages, which are kept in
+/// per-order free lists. Thus, allocating and freeing a block requires at most
+/// [`MAX_ORDER`] steps (independent of the amount of managed memory).
+///
+/// Single pages are served by per-hart caches, which are refilled from (and drained to) the
+/// global allocator in batches of `PAGE_CACHE_BATCH` pages. Before running out of memory, all
+/// caches are drained (see [`drain_caches`](PageFrameAllocator::drain_caches)).
+///
+/// Allocated pages may be shared (e.g. between address spaces) using per-page reference counts
+/// (see [`share`](PageFrameAllocator::share) and [`release`](PageFrameAllocator::release)).
+///
+/// Initially, only the (statically allocated) `pages` range of at most 256 MiB is managed. Further
+/// physical memory discovered via the device tree is added by
+/// [`discover`](PageFrameAllocator::discover).
 pub struct PageFrameAllocator {
-    state: TicketlockPaging<[u64; 1024]>,
+    state: TicketlockPaging<BuddyState>,
 }
 
 impl PageFrameAllocator {
     const fn new() -> Self {
         Self {
-            state: TicketlockPaging::new([0; 1024]),
+            state: TicketlockPaging::new(BuddyState::new()),
         }
     }
 
@@ -36,52 +614,141 @@ impl PageFrameAllocator {
         let start_addr = compiler::pages_mem_phys_start();
         assert!(start_addr.addr() % cpu::page_size() == 0);
 
-        let size = usize::min(MAX_SIZE, compiler::pages_mem_size());
+        let size = usize::min(MAX_BOOT_SIZE, compiler::pages_mem_size());
         assert!(size % cpu::page_size() == 0);
 
-        let mut state = [0u64; 1024];
-        for i in 0..size / cpu::page_size() {
-            let idx = i / u64::BITS as usize;
-            let offset = i % u64::BITS as usize;
-
-            state[idx] |= 1 << offset;
-        }
-        *allocator_state = state;
-
+        let meta = allocator_state.boot_meta.as_mut_ptr();
+        let refs = allocator_state.boot_refs.as_mut_ptr();
+        let records = match config::DEBUG_PAGE_ALLOCATOR {
+            true => allocator_state.boot_records.as_mut_ptr(),
+            false => core::ptr::null_mut(),
+        };
+        allocator_state
+            .populate(
+                start_addr.addr() / cpu::page_size(),
+                size / cpu::page_size(),
+                meta,
+                refs,
+                records,
+            )
+            .unwrap();
         allocator_state.init_unlock()
     }
 
-    fn __allocate(
-        allocator_state: &mut [u64; 1024],
-    ) -> Result<PhysicalAddress<c_void>, MemoryError> {
-        for (idx, state) in allocator_state.iter_mut().enumerate() {
-            if *state == 0 {
-                continue;
-            }
-            for offset in 0..u64::BITS as usize {
-                if *state & 1 << offset != 0 {
-                    // Mark page as occupied
-                    *state &= !(1 << offset);
+    /// Discover physical memory using the device tree and manage every usable region.
+    ///
+    /// Usable regions are the `reg` ranges of all `/memory` nodes, except for the memory
+    /// reservation block, `/reserved-memory` nodes, the kernel image (including the `pages`
+    /// range) and the device tree blob. Each usable region is mapped into the kernel's direct map.
+    pub fn discover(token: LevelInitialization) -> LevelInitialization {
+        let (dt, mut token) = DeviceTree::get_dt(token);
 
-                    // Calculate address of page
-                    let page_offset = (u64::BITS as usize * idx + offset) * cpu::page_size();
-                    let mut v_page = compiler::pages_mem_virt_start().add(page_offset);
-                    let p_page = Self::virt_to_phys(v_page);
+        // Collect reserved regions
+        let (dtb_addr, dtb_size) = dt.get_blob_region();
+        let kernel_image = (
+            compiler::text_segment_phys_start().addr(),
+            compiler::pages_mem_phys_end().addr(),
+        );
+        let vmap_window = (
+            vmap::VMAP_START - direct_map_offset(),
+            vmap::VMAP_START - direct_map_offset() + vmap::VMAP_SIZE,
+        );
+        let reserved = || {
+            dt.reserved_memory_iter()
+                .map(|(addr, size)| (addr, addr.saturating_add(size)))
+                .chain(core::iter::once(kernel_image))
+                .chain(core::iter::once((
+                    dtb_addr.addr(),
+                    dtb_addr.addr() + dtb_size,
+                )))
+                // Direct map must not collide with dynamic mappings
+                .chain(core::iter::once(vmap_window))
+        };
 
-                    // Sanity check
-                    assert!(v_page.addr() % cpu::page_size() == 0);
-                    assert!(v_page >= compiler::pages_mem_virt_start());
-                    assert!(v_page < compiler::pages_mem_virt_end());
+        for (addr, size) in dt.memory_iter() {
+            // Only memory within the direct map is accessible
+            let end = usize::min(addr.saturating_add(size), DIRECT_MAP_SIZE);
 
-                    // Zero page
-                    unsafe { v_page.as_mut_ptr().write_bytes(0, cpu::page_size()) };
+            // Subtract reserved regions (in ascending order)
+            let mut cursor = addr;
+            while cursor < end {
+                let next_reserved = reserved()
+                    .filter(|(start, stop)| *start < end && *stop > cursor)
+                    .min_by_key(|(start, _)| *start);
+                let (usable_end, next) = match next_reserved {
+                    Some((start, stop)) => (usize::max(start, cursor), stop),
+                    None => (end, end),
+                };
 
-                    return Ok(p_page);
-                }
+                token = Self::early_add_region(cursor, usable_end, token);
+                cursor = next;
             }
         }
 
-        Err(MemoryError::OutOfMemory)
+        token
+    }
+
+    /// Map usable region `[start, end)` into direct map and add it to the allocator.
+    fn early_add_region(
+        start: usize,
+        end: usize,
+        token: LevelInitialization,
+    ) -> LevelInitialization {
+        // Only manage whole pages
+        let start = (start + cpu::page_size() - 1) & !(cpu::page_size() - 1);
+        let end = end & !(cpu::page_size() - 1);
+        if start >= end {
+            return token;
+        }
+        let num_pages = (end - start) / cpu::page_size();
+
+        // Reserve leading pages for metadata (one byte), reference counts (two bytes) and debug
+        // records per page
+        let record_size = match config::DEBUG_PAGE_ALLOCATOR {
+            true => mem::size_of::<Record>(),
+            false => 0,
+        };
+        let meta_size = num_pages * (mem::size_of::<u8>() + mem::size_of::<u16>() + record_size)
+            + 1
+            + mem::align_of::<Record>();
+        let meta_pages = (meta_size + cpu::page_size() - 1) / cpu::page_size();
+        if num_pages <= meta_pages {
+            return token;
+        }
+
+        // Map region into direct map
+        let phys_addr = page::<c_void>(start / cpu::page_size());
+        let token = match KERNEL_VIRTUAL_MEMORY_SYSTEM.as_ref().early_create_direct(
+            phys_addr,
+            end - start,
+            token,
+        ) {
+            Ok(token) => token,
+            Err((err, _)) => panic!("Unable to map physical memory {}: {}", phys_addr, err),
+        };
+
+        // Register zone
+        let mut allocator_state = PAGE_FRAME_ALLOCATOR.state.init_lock(token);
+        let meta: *mut u8 = Self::phys_to_virt(phys_addr).as_mut_ptr().cast();
+        let refs: *mut u16 = unsafe { meta.add(num_pages + num_pages % 2).cast() };
+        let records = match config::DEBUG_PAGE_ALLOCATOR {
+            true => unsafe {
+                let end: *mut u8 = refs.add(num_pages).cast();
+                end.add(end.align_offset(mem::align_of::<Record>())).cast()
+            },
+            false => core::ptr::null_mut(),
+        };
+        if let Err(err) = allocator_state.populate(
+            start / cpu::page_size() + meta_pages,
+            num_pages - meta_pages,
+            meta,
+            refs,
+            records,
+        ) {
+            panic!("Unable to manage physical memory {}: {}", phys_addr, err);
+        }
+
+        allocator_state.init_unlock()
     }
 
     /// Try to allocate a new page
@@ -89,21 +756,150 @@ impl PageFrameAllocator {
         &self,
         token: LevelPaging,
     ) -> Result<(PhysicalAddress<c_void>, LevelPaging), (MemoryError, LevelPaging)> {
+        return self.allocate_order(0, token);
+    }
+
+    /// Try to allocate `2^order` physically contiguous pages (aligned to their size).
+    pub fn allocate_order(
+        &self,
+        order: usize,
+        token: LevelPaging,
+    ) -> Result<(PhysicalAddress<c_void>, LevelPaging), (MemoryError, LevelPaging)> {
+        // Serve single pages by per-hart cache (bypassed for debugging)
+        if order == 0 && !config::DEBUG_PAGE_ALLOCATOR {
+            return self.allocate_cached(token);
+        }
+
         // Lock allocator
         let (mut allocator_state, token) = self.state.lock(token);
 
-        // Search for available page
-        let result = Self::__allocate(&mut allocator_state);
+        // Search for available block
+        let result = allocator_state.allocate(order);
 
         // Unlock allocator
         let token = allocator_state.unlock(token);
 
+        // Retry after draining per-hart caches (allowing cached pages to coalesce)
+        let (result, token) = match result {
+            Err(MemoryError::OutOfMemory) => match self.drain_caches(token) {
+                (0, token) => (result, token),
+                (_, token) => {
+                    let (mut allocator_state, token) = self.state.lock(token);
+                    let result = allocator_state.allocate(order);
+                    (result, allocator_state.unlock(token))
+                }
+            },
+            result => (result, token),
+        };
+
         match result {
-            Ok(phys_addr) => Ok((phys_addr, token)),
+            Ok(phys_addr) => {
+                zero(phys_addr, order);
+                Ok((phys_addr, token))
+            }
             Err(err) => Err((err, token)),
         }
     }
 
+    /// Try to allocate a single page using the per-hart cache (refilling it if necessary).
+    fn allocate_cached(
+        &self,
+        token: LevelPaging,
+    ) -> Result<(PhysicalAddress<c_void>, LevelPaging), (MemoryError, LevelPaging)> {
+        let counters = PageCacheCounters::current();
+        let page_cache = &PAGE_CACHES[cpu::current().raw()];
+
+        // Step 1: Lookup per-hart cache
+        let (cache, token) = page_cache.lock(token);
+        let (mut cache, token) = if cache.len != 0 {
+            counters.hits.fetch_add(1, Ordering::Relaxed);
+            (cache, token)
+        } else {
+            counters.misses.fetch_add(1, Ordering::Relaxed);
+
+            // Step 2: Refill batch from global allocator (without holding the per-hart cache),
+            // draining the caches of all harts if the global allocator ran out of pages
+            let token = cache.unlock(token);
+            let mut batch = [PhysicalAddress::null(); PAGE_CACHE_BATCH];
+            let (num, token) = match self.refill(&mut batch, token) {
+                (0, token) => match self.drain_caches(token) {
+                    (0, token) => (0, token),
+                    (_, token) => self.refill(&mut batch, token),
+                },
+                result => result,
+            };
+            if num == 0 {
+                return Err((MemoryError::OutOfMemory, token));
+            }
+            counters.refills.fetch_add(num, Ordering::Relaxed);
+
+            // Step 3: Insert batch into per-hart cache
+            let (mut cache, token) = page_cache.lock(token);
+            for page in &batch[..num] {
+                let len = cache.len;
+                cache.pages[len] = *page;
+                cache.len += 1;
+            }
+            (cache, token)
+        };
+
+        // Step 4: Take page from per-hart cache
+        cache.len -= 1;
+        let page = cache.pages[cache.len];
+        counters.cached.store(cache.len, Ordering::Relaxed);
+        let token = cache.unlock(token);
+
+        zero(page, 0);
+        Ok((page, token))
+    }
+
+    /// Allocate single pages from the global allocator into `batch` (as many as available).
+    fn refill(
+        &self,
+        batch: &mut [PhysicalAddress<c_void>],
+        token: LevelPaging,
+    ) -> (usize, LevelPaging) {
+        let (mut allocator_state, token) = self.state.lock(token);
+        let mut num = 0;
+        while num < batch.len() {
+            match allocator_state.allocate(0) {
+                Ok(page) => batch[num] = page,
+                Err(_) => break,
+            }
+            num += 1;
+        }
+
+        (num, allocator_state.unlock(token))
+    }
+
+    /// Drain the per-hart caches of all harts to the global allocator (e.g. before running out of
+    /// memory) and get the number of drained pages.
+    fn drain_caches(&self, token: LevelPaging) -> (usize, LevelPaging) {
+        let mut drained = 0;
+        let mut token = token;
+        for (page_cache, counters) in PAGE_CACHES.iter().zip(PAGE_CACHE_COUNTERS.iter()) {
+            // Step 1: Empty cache
+            let (mut cache, t) = page_cache.lock(token);
+            let mut batch = [PhysicalAddress::null(); PAGE_CACHE_SIZE];
+            let num = cache.len;
+            batch[..num].copy_from_slice(&cache.pages[..num]);
+            cache.len = 0;
+            counters.cached.store(0, Ordering::Relaxed);
+            let t = cache.unlock(t);
+
+            // Step 2: Return pages to global allocator (without holding the cache)
+            let (mut allocator_state, t) = self.state.lock(t);
+            for page in &batch[..num] {
+                unsafe { allocator_state.free(*page, 0) };
+            }
+            token = allocator_state.unlock(t);
+            counters.drains.fetch_add(num, Ordering::Relaxed);
+            drained += num;
+        }
+
+        (drained, token)
+    }
+
     /// Try to allocate a new page during initialization
     pub fn early_allocate(
         &self,
@@ -114,56 +910,243 @@ impl PageFrameAllocator {
         let mut allocator_state = self.state.init_lock(token);
 
         // Search for available page
-        let result = Self::__allocate(&mut allocator_state);
+        let result = allocator_state.allocate(0);
 
         // Unlock allocator
         let token = allocator_state.init_unlock();
 
         match result {
-            Ok(phys_addr) => Ok((phys_addr, token)),
+            Ok(phys_addr) => {
+                zero(phys_addr, 0);
+                Ok((phys_addr, token))
+            }
             Err(err) => Err((err, token)),
         }
     }
 
-    unsafe fn __free(allocator_state: &mut [u64; 1024], page: PhysicalAddress<c_void>) {
-        let p_page = page;
-        let v_page = Self::phys_to_virt(p_page);
-
-        // Sanity check: Is page valid?
-        assert!(v_page.addr() % cpu::page_size() == 0);
-        assert!(v_page >= compiler::pages_mem_virt_start());
-        assert!(v_page < compiler::pages_mem_virt_end());
-
-        // Calculate offset
-        let page_offset = v_page.addr() - compiler::pages_mem_virt_start().addr();
-        let idx = (page_offset / cpu::page_size()) / u64::BITS as usize;
-        let offset = (page_offset / cpu::page_size()) % u64::BITS as usize;
-
-        // Lock allocator
-        // Sanity check: Was page allocated
-        assert!(allocator_state[idx] & 1 << offset == 0);
-
-        // Mark page as free
-        allocator_state[idx] |= 1 << offset;
-    }
-
     /// Free allocated page
     ///
     /// # Safety
     /// This function is unsafe because undefined behavior can result if ...
     /// - `ptr` refers to a block of memory currently allocated via this allocator.
     /// - the references page is still in use.
-    pub unsafe fn free(self, page: PhysicalAddress<c_void>, token: LevelPaging) -> LevelPaging {
+    pub unsafe fn free(&self, page: PhysicalAddress<c_void>, token: LevelPaging) -> LevelPaging {
+        return self.free_order(page, 0, token);
+    }
+
+    /// Free `2^order` allocated pages
+    ///
+    /// # Safety
+    /// This function is unsafe because undefined behavior can result if ...
+    /// - `block` refers to a block of memory currently allocated via
+    ///   [`allocate_order`](PageFrameAllocator::allocate_order) with the same `order`.
+    /// - the references pages are still in use.
+    pub unsafe fn free_order(
+        &self,
+        block: PhysicalAddress<c_void>,
+        order: usize,
+        token: LevelPaging,
+    ) -> LevelPaging {
+        // Return single pages to per-hart cache (bypassed for debugging)
+        if order == 0 && !config::DEBUG_PAGE_ALLOCATOR {
+            return self.free_cached(block, token);
+        }
+
         // Lock allocator
         let (mut allocator_state, token) = self.state.lock(token);
 
-        Self::__free(&mut allocator_state, page);
+        allocator_state.free(block, order);
 
         // Unlock allocator
         let token = allocator_state.unlock(token);
         return token;
     }
 
+    /// Free a single page using the per-hart cache (draining it if necessary).
+    ///
+    /// Without `CONFIG_DEBUG_PAGE_ALLOCATOR`, double frees are detected (without locking the global
+    /// allocator) while the page is still cached on this hart, or once it is drained to the global
+    /// allocator.
+    unsafe fn free_cached(&self, page: PhysicalAddress<c_void>, token: LevelPaging) -> LevelPaging {
+        let counters = PageCacheCounters::current();
+        let page_cache = &PAGE_CACHES[cpu::current().raw()];
+
+        // Step 1: Lookup per-hart cache (cached pages are still marked as allocated)
+        assert!(page.addr() % cpu::page_size() == 0);
+        let (cache, token) = page_cache.lock(token);
+        if cache.pages[..cache.len].contains(&page) {
+            panic!("Double free of {} with order 0", page);
+        }
+        let (mut cache, token) = if cache.len != PAGE_CACHE_SIZE {
+            (cache, token)
+        } else {
+            // Step 2: Take batch from per-hart cache
+            let mut cache = cache;
+            let mut batch = [PhysicalAddress::null(); PAGE_CACHE_BATCH];
+            for entry in batch.iter_mut() {
+                cache.len -= 1;
+                *entry = cache.pages[cache.len];
+            }
+            let token = cache.unlock(token);
+
+            // Step 3: Drain batch to global allocator (without holding the per-hart cache)
+            let (mut allocator_state, token) = self.state.lock(token);
+            for page in batch {
+                allocator_state.free(page, 0);
+            }
+            let token = allocator_state.unlock(token);
+            counters
+                .drains
+                .fetch_add(PAGE_CACHE_BATCH, Ordering::Relaxed);
+
+            page_cache.lock(token)
+        };
+
+        // Step 4: Insert page into per-hart cache
+        let len = cache.len;
+        cache.pages[len] = page;
+        cache.len += 1;
+        counters.cached.store(cache.len, Ordering::Relaxed);
+        cache.unlock(token)
+    }
+
+    /// Get number of free pages (including pages cached per hart).
+    pub fn free_pages(&self, token: LevelPaging) -> (usize, LevelPaging) {
+        let (allocator_state, token) = self.state.lock(token);
+        let free_pages = allocator_state.free_pages;
+        let token = allocator_state.unlock(token);
+
+        let cached: usize = PAGE_CACHE_COUNTERS
+            .iter()
+            .map(|counters| counters.cached.load(Ordering::Relaxed))
+            .sum();
+        (free_pages + cached, token)
+    }
+
+    /// Add a reference to allocated `page` (e.g. for sharing it between address spaces).
+    ///
+    /// Pages with additional references are only freed by [`release`](PageFrameAllocator::release)
+    /// once the last reference is dropped. Pages not managed by the allocator are rejected with
+    /// [`MemoryError::NoSuchAddress`].
+    pub fn share(
+        &self,
+        page: PhysicalAddress<c_void>,
+        token: LevelPaging,
+    ) -> Result<LevelPaging, (MemoryError, LevelPaging)> {
+        let (mut allocator_state, token) = self.state.lock(token);
+        let result = match allocator_state.refs(page.addr() / cpu::page_size()) {
+            Some(refs) => {
+                *refs = refs.checked_add(1).expect("Too many references to page");
+                Ok(())
+            }
+            None => Err(MemoryError::NoSuchAddress),
+        };
+        let token = allocator_state.unlock(token);
+
+        match result {
+            Ok(()) => Ok(token),
+            Err(err) => Err((err, token)),
+        }
+    }
+
+    /// Get number of references to allocated `page` (`1` if not shared).
+    pub fn references(
+        &self,
+        page: PhysicalAddress<c_void>,
+        token: LevelPaging,
+    ) -> (usize, LevelPaging) {
+        let (mut allocator_state, token) = self.state.lock(token);
+        let refs = match allocator_state.refs(page.addr() / cpu::page_size()) {
+            Some(refs) => *refs as usize + 1,
+            None => 1,
+        };
+        let token = allocator_state.unlock(token);
+
+        (refs, token)
+    }
+
+    /// Drop a reference to allocated `page`, freeing it once the last reference is dropped.
+    ///
+    /// # Safety
+    /// This function is unsafe because undefined behavior can result if ...
+    /// - `page` refers to a page currently allocated via this allocator.
+    /// - the reference is still in use.
+    pub unsafe fn release(&self, page: PhysicalAddress<c_void>, token: LevelPaging) -> LevelPaging {
+        // Drop additional reference (if any)
+        let (mut allocator_state, token) = self.state.lock(token);
+        let shared = match allocator_state.refs(page.addr() / cpu::page_size()) {
+            Some(refs) if *refs != 0 => {
+                *refs -= 1;
+                true
+            }
+            _ => false,
+        };
+        let token = allocator_state.unlock(token);
+
+        // Free page (if unshared)
+        match shared {
+            true => token,
+            false => self.free(page, token),
+        }
+    }
+
+    /// Print all allocated blocks together with the hart and backtrace of their allocation (e.g.
+    /// to find leaks).
+    ///
+    /// Allocations are only tracked with `CONFIG_DEBUG_PAGE_ALLOCATOR`.
+    pub fn dump_allocations(&self, token: LevelPaging) -> LevelPaging {
+        if !config::DEBUG_PAGE_ALLOCATOR {
+            printk!(
+                LogLevel::Warn,
+                "Page allocations are not tracked (see CONFIG_DEBUG_PAGE_ALLOCATOR)\n"
+            );
+            return token;
+        }
+
+        let (allocator_state, token) = self.state.lock(token);
+        let mut num_blocks = 0;
+        let mut num_pages = 0;
+        for zone in allocator_state.zones[..allocator_state.num_zones].iter() {
+            for i in 0..zone.num_pages {
+                let meta = unsafe { *zone.meta.add(i) };
+                if meta & META_ALLOCATED == 0 {
+                    continue;
+                }
+
+                let order = (meta & META_ORDER) as usize;
+                let record = unsafe { *zone.records.add(i) };
+                printk!(
+                    LogLevel::Info,
+                    "{} ({} pages) allocated by {}\n",
+                    page::<c_void>(zone.base_pfn + i),
+                    1 << order,
+                    record
+                );
+                num_blocks += 1;
+                num_pages += 1 << order;
+            }
+        }
+        printk!(
+            LogLevel::Info,
+            "{} blocks ({} pages) allocated\n",
+            num_blocks,
+            num_pages
+        );
+
+        allocator_state.unlock(token)
+    }
+
+    /// Get statistics of the page cache of hart `logical_id`.
+    pub fn page_cache_stats(logical_id: LogicalCPUID) -> PageCacheStats {
+        let counters = &PAGE_CACHE_COUNTERS[logical_id.raw()];
+        PageCacheStats {
+            hits: counters.hits.load(Ordering::Relaxed),
+            misses: counters.misses.load(Ordering::Relaxed),
+            refills: counters.refills.load(Ordering::Relaxed),
+            drains: counters.drains.load(Ordering::Relaxed),
+        }
+    }
+
     /// Free allocated page during initialization
     ///
     /// # Safety
@@ -178,43 +1161,33 @@ impl PageFrameAllocator {
         // Lock allocator
         let mut allocator_state = self.state.init_lock(token);
 
-        Self::__free(&mut allocator_state, page);
+        allocator_state.free(page, 0);
 
         // Unlock allocator
         let token = allocator_state.init_unlock();
         return token;
     }
 
-    /// Convert [`VirtualAddress`] returned by
+    /// Convert [`VirtualAddress`] (within the kernel's direct map) returned by
     /// [`allocate`](crate::mm::page_allocator::PageFrameAllocator::allocate), to a [`PhysicalAddress`].
     pub fn virt_to_phys<T>(virt_addr: VirtualAddress<T>) -> PhysicalAddress<T> {
-        // Sanity check: Refers virt_addr a valid page?
+        // Sanity check: Refers virt_addr a valid page within the direct map?
         assert!(virt_addr.addr() % cpu::page_size() == 0);
-        assert!(virt_addr >= unsafe { compiler::pages_mem_virt_start().cast() });
-        assert!(virt_addr < unsafe { compiler::pages_mem_virt_end().cast() });
+        assert!(virt_addr.addr() >= direct_map_offset());
+        assert!(virt_addr.addr() - direct_map_offset() < DIRECT_MAP_SIZE);
 
-        let byte_offset = virt_addr.addr() - compiler::pages_mem_virt_start().addr();
-        unsafe {
-            compiler::pages_mem_phys_start()
-                .byte_add(byte_offset)
-                .cast()
-        }
+        PhysicalAddress::new((virt_addr.addr() - direct_map_offset()) as *mut T)
     }
 
     /// Convert [`PhysicalAddress`] returned by
-    /// [`allocate`](crate::mm::page_allocator::PageFrameAllocator::allocate), to a [`VirtualAddress`].
+    /// [`allocate`](crate::mm::page_allocator::PageFrameAllocator::allocate), to a [`VirtualAddress`]
+    /// (within the kernel's direct map).
     pub fn phys_to_virt<T>(phys_addr: PhysicalAddress<T>) -> VirtualAddress<T> {
-        // Sanity check: Refers phys_addr a valid page?
+        // Sanity check: Refers phys_addr a valid page within the direct map?
         assert!(phys_addr.addr() % cpu::page_size() == 0);
-        assert!(phys_addr >= unsafe { compiler::pages_mem_phys_start().cast() });
-        assert!(phys_addr < unsafe { compiler::pages_mem_phys_end().cast() });
+        assert!(phys_addr.addr() < DIRECT_MAP_SIZE);
 
-        let byte_offset = phys_addr.addr() - compiler::pages_mem_phys_start().addr();
-        unsafe {
-            compiler::pages_mem_virt_start()
-                .byte_add(byte_offset)
-                .cast()
-        }
+        VirtualAddress::new((phys_addr.addr() + direct_map_offset()) as *mut T)
     }
 }
 
@@ -0,0 +1,186 @@
+//! Handling of instruction/load/store page faults.
+//!
+//! Page faults are resolved lazily within the `epilogue` by the [`VirtualMemorySystem`] currently
+//! loaded on the faulting hart (see [`VirtualMemorySystem::handle_page_fault`]). Unresolvable
+//! faults are reported with a diagnostic and lead to a `panic`, except for accesses to user memory
+//! by the kernel (see [`uaccess`](crate::mm::uaccess)), which resume at their fixup, and faults of
+//! user mode, which terminate the faulting thread (see [`user::exit`]).
+
+use core::ffi::c_void;
+use core::fmt::Display;
+
+use crate::arch::csr::CSR;
+use crate::arch::scause::SCause;
+use crate::arch::sepc::SEPC;
+use crate::arch::sstatus::{SStatus, SStatusPrivLevel};
+use crate::arch::stval::STVal;
+use crate::drivers::driver::{Driver, DriverError};
+use crate::kernel::address::{Address, VirtualAddress};
+use crate::kernel::cpu;
+use crate::kernel::printer::LogLevel;
+use crate::mm::mapping::{Mode, VirtualMemorySystem};
+use crate::mm::uaccess;
+use crate::mm::vma::Access;
+use crate::printk;
+use crate::sync::epilogue;
+use crate::sync::level::{Adapter, AdapterEpilogueMemory, AdapterGuard};
+use crate::sync::level::{LevelEpilogue, LevelInitialization, LevelPrologue};
+use crate::trap::cause::{Exception, Trap};
+use crate::trap::handler_interface::TrapContext;
+use crate::trap::handlers::{PrologueResult, TrapHandler, TrapHandlers};
+use crate::trap::user;
+
+/// Global page fault handler.
+pub static PAGE_FAULT_HANDLER: PageFaultHandler = PageFaultHandler {};
+
+/// Handler for instruction/load/store page faults.
+pub struct PageFaultHandler {}
+
+/// Description of a page fault.
+struct PageFault {
+    access: Access,
+    virt_addr: VirtualAddress<c_void>,
+    sepc: u64,
+    mode: Mode,
+}
+
+impl PageFault {
+    /// Decode page fault from trap registers.
+    fn new(scause: SCause, stval: STVal, sepc: SEPC, sstatus: SStatus) -> Self {
+        let access = match Trap::from(scause) {
+            Trap::Exception(Exception::InstructionPageFault) => Access::Execute,
+            Trap::Exception(Exception::LoadPageFault) => Access::Read,
+            Trap::Exception(Exception::StorePageFault) => Access::Write,
+            trap => panic!("Unexpected trap \"{}\" in page fault handler", trap),
+        };
+        let mode = match sstatus.get_spp() {
+            SStatusPrivLevel::UserMode => Mode::User,
+            SStatusPrivLevel::SupervisorMode => Mode::Kernel,
+        };
+
+        Self {
+            access,
+            virt_addr: VirtualAddress::new(stval.raw() as *mut c_void),
+            sepc: sepc.inner(),
+            mode,
+        }
+    }
+
+    /// Report unresolvable page fault (caused by `reason`).
+    fn report(&self, reason: &dyn Display) -> ! {
+        panic!("Unresolvable page fault: {}: {}", self, reason);
+    }
+}
+
+impl Display for PageFault {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} access to {:#x} from {:?} mode (sepc: {:#x})",
+            self.access,
+            self.virt_addr.addr(),
+            self.mode,
+            self.sepc
+        )
+    }
+}
+
+impl Driver for PageFaultHandler {
+    fn initiailize(
+        token: LevelInitialization,
+    ) -> Result<LevelInitialization, (DriverError, LevelInitialization)>
+    where
+        Self: Sized,
+    {
+        // Register handler
+        let mut token = token;
+        for exception in [
+            Exception::InstructionPageFault,
+            Exception::LoadPageFault,
+            Exception::StorePageFault,
+        ] {
+            token = TrapHandlers::register(Trap::Exception(exception), &PAGE_FAULT_HANDLER, token);
+        }
+
+        return Ok(token);
+    }
+}
+
+impl TrapHandler for PageFaultHandler {
+    fn cause() -> Trap
+    where
+        Self: Sized,
+    {
+        Trap::Exception(Exception::LoadPageFault)
+    }
+
+    fn prologue(&self, token: LevelPrologue) -> (PrologueResult, LevelPrologue) {
+        // Faults are resolved within the epilogue. If the epilogue level is already held by this
+        // hart, the epilogue would be deferred and the faulting instruction would re-fault forever.
+        if epilogue::is_entered() {
+            let mut scause = SCause::new(0);
+            scause.read();
+            let mut stval = STVal::new(0);
+            stval.read();
+            let mut sepc = SEPC::new(0);
+            sepc.read();
+            let mut sstatus = SStatus::new(0);
+            sstatus.read();
+
+            PageFault::new(scause, stval, sepc, sstatus).report(&"Fault within epilogue level");
+        }
+
+        (PrologueResult::EpilogueRequired, token)
+    }
+
+    fn epilogue(&self, state: Option<&mut TrapContext>, token: LevelEpilogue) -> LevelEpilogue {
+        // Step 1: Decode page fault
+        let state = match state {
+            Some(state) => state,
+            None => panic!("Page fault epilogue requires the trap context"),
+        };
+        let fault = PageFault::new(
+            state.get_scause(),
+            state.get_stval(),
+            state.get_sepc(),
+            state.get_sstatus(),
+        );
+
+        // Step 2: Check for access to user memory by the kernel (resolved on behalf of user mode)
+        let fixup = match fault.mode {
+            Mode::Kernel => uaccess::fixup(fault.sepc as usize),
+            Mode::User => None,
+        };
+        let mode = match fixup {
+            Some(_) => Mode::User,
+            None => fault.mode,
+        };
+
+        // Step 3: Resolve page fault using the loaded virtual memory system
+        let adapter = AdapterEpilogueMemory::new();
+        let (guard, token) = adapter.enter(token);
+        let vms = VirtualMemorySystem::active();
+        match (
+            vms.handle_page_fault(fault.virt_addr, fault.access, mode, token),
+            fixup,
+        ) {
+            (Ok(token), _) => guard.leave(token),
+            (Err((_, token)), Some(target)) => {
+                state.set_sepc(SEPC::new(target as u64));
+                guard.leave(token)
+            }
+            // Terminate faulting user thread (instead of the kernel)
+            (Err((err, token)), None) if fault.mode == Mode::User => {
+                printk!(
+                    LogLevel::Error,
+                    "Core {}: Terminating user thread on unresolvable page fault: {}: {}\n",
+                    cpu::current(),
+                    fault,
+                    err
+                );
+                user::exit(guard.leave(token))
+            }
+            (Err((err, _)), None) => fault.report(&err),
+        }
+    }
+}
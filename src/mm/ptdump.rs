@@ -0,0 +1,229 @@
+//! Dump and verification of page tables (see [`VirtualMemorySystem::dump`] and
+//! [`VirtualMemorySystem::check`]).
+//!
+//! [`VirtualMemorySystem::dump`]: crate::mm::mapping::VirtualMemorySystem::dump
+//! [`VirtualMemorySystem::check`]: crate::mm::mapping::VirtualMemorySystem::check
+
+use core::fmt::Display;
+
+use crate::kernel::address::Address;
+use crate::kernel::printer::LogLevel;
+use crate::mm::mapping::{MemoryType, Mode, PageSize, Protection};
+use crate::mm::pte::PageTableEntry;
+use crate::printk;
+
+/// Valid page table entry visited during a page-table walk.
+pub struct WalkEntry<'a> {
+    /// First virtual address covered by the entry.
+    pub virt_addr: usize,
+    /// Number of bytes covered by the entry.
+    pub size: usize,
+    /// Page table entry.
+    pub pte: &'a PageTableEntry,
+    /// Entry is expected to reference a page table of the next level (**inner page table**).
+    pub is_inner: bool,
+}
+
+/// Contiguous range of leaf mappings with identical attributes.
+struct Region {
+    virt_start: usize,
+    virt_end: usize,
+    phys_start: usize,
+    protection: Option<Protection>,
+    mode: Mode,
+    memory_type: Option<MemoryType>,
+    global: bool,
+    accessed: bool,
+    dirty: bool,
+    page_size: Option<PageSize>,
+}
+
+impl Region {
+    /// Create region of leaf mapping `entry`.
+    fn new(entry: &WalkEntry) -> Self {
+        let pte = entry.pte;
+
+        // Entries of `64KiB` mappings share the first physical page
+        let mut phys_start = pte.get_physical_page::<u8>().addr();
+        let mut page_size = entry.size;
+        if pte.is_napot() {
+            page_size = PageSize::Size64KiB.size();
+            phys_start += entry.virt_addr % page_size;
+        }
+
+        Self {
+            virt_start: entry.virt_addr,
+            virt_end: entry.virt_addr + entry.size,
+            phys_start,
+            protection: Protection::from_flags(
+                pte.is_readable(),
+                pte.is_writable(),
+                pte.is_executable(),
+            ),
+            mode: match pte.is_user_accessible() {
+                true => Mode::User,
+                false => Mode::Kernel,
+            },
+            memory_type: MemoryType::from_encoding(pte.get_memory_type()),
+            global: pte.is_global(),
+            accessed: pte.is_accessed(),
+            dirty: pte.is_dirty(),
+            page_size: [
+                PageSize::Size4KiB,
+                PageSize::Size64KiB,
+                PageSize::Size2MiB,
+                PageSize::Size1GiB,
+            ]
+            .into_iter()
+            .find(|size| size.size() == page_size),
+        }
+    }
+
+    /// Try to extend region by `other` (only possible if contiguous in virtual and physical
+    /// memory and with identical attributes).
+    fn extend(&mut self, other: &Region) -> bool {
+        let extendable = self.virt_end == other.virt_start
+            && self.phys_start + (self.virt_end - self.virt_start) == other.phys_start
+            && self.protection == other.protection
+            && self.mode == other.mode
+            && self.memory_type == other.memory_type
+            && self.global == other.global
+            && self.accessed == other.accessed
+            && self.dirty == other.dirty
+            && self.page_size == other.page_size;
+        if extendable {
+            self.virt_end = other.virt_end;
+        }
+
+        extendable
+    }
+}
+
+impl Display for Region {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let protection = match self.protection {
+            Some(Protection::R) => "r--",
+            Some(Protection::RW) => "rw-",
+            Some(Protection::X) => "--x",
+            Some(Protection::RX) => "r-x",
+            Some(Protection::RWX) => "rwx",
+            None => "???",
+        };
+        let page_size = match self.page_size {
+            Some(PageSize::Size4KiB) => "4KiB",
+            Some(PageSize::Size64KiB) => "64KiB",
+            Some(PageSize::Size2MiB) => "2MiB",
+            Some(PageSize::Size1GiB) => "1GiB",
+            None => "?",
+        };
+
+        write!(
+            f,
+            "{:#018x}-{:#018x} -> {:#014x}-{:#014x} {} {:<6} {:<3} {}{}{} {}",
+            self.virt_start,
+            self.virt_end,
+            self.phys_start,
+            self.phys_start + (self.virt_end - self.virt_start),
+            protection,
+            match self.mode {
+                Mode::Kernel => "Kernel",
+                Mode::User => "User",
+            },
+            match self.memory_type {
+                Some(MemoryType::PMA) => "PMA",
+                Some(MemoryType::NC) => "NC",
+                Some(MemoryType::IO) => "IO",
+                None => "???",
+            },
+            if self.global { 'G' } else { '-' },
+            if self.accessed { 'A' } else { '-' },
+            if self.dirty { 'D' } else { '-' },
+            page_size
+        )
+    }
+}
+
+/// Printer of range-coalesced leaf mappings.
+pub struct Dumper {
+    region: Option<Region>,
+}
+
+impl Dumper {
+    /// Create a new [`Dumper`].
+    pub const fn new() -> Self {
+        Self { region: None }
+    }
+
+    /// Add entry (visited in ascending order of virtual addresses).
+    pub fn visit(&mut self, entry: &WalkEntry) {
+        if entry.is_inner {
+            return;
+        }
+
+        let region = Region::new(entry);
+        if let Some(current) = &mut self.region {
+            if current.extend(&region) {
+                return;
+            }
+        }
+
+        self.flush();
+        self.region = Some(region);
+    }
+
+    /// Print last pending region.
+    pub fn finish(mut self) {
+        self.flush();
+    }
+
+    /// Print pending region.
+    fn flush(&mut self) {
+        if let Some(region) = self.region.take() {
+            printk!(LogLevel::Info, "{}\n", region);
+        }
+    }
+}
+
+/// Inconsistency of a page table entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    /// Leaf mapping is both writable and executable.
+    WritableExecutable,
+    /// Leaf mapping within kernel space is user-accessible.
+    UserAccessibleKernelPage,
+    /// Inner page table entry has `R`/`W`/`X` bits set (and is thus interpreted as leaf).
+    InnerWithPermissions,
+}
+
+impl Violation {
+    /// Check `entry` (within kernel space, if `kernel_space` is set) for a [`Violation`].
+    pub fn check(entry: &WalkEntry, kernel_space: bool) -> Option<Self> {
+        let pte = entry.pte;
+        if entry.is_inner {
+            if !pte.is_inner_page_table() {
+                return Some(Violation::InnerWithPermissions);
+            }
+            return None;
+        }
+
+        if pte.is_writable() && pte.is_executable() {
+            return Some(Violation::WritableExecutable);
+        }
+        if kernel_space && pte.is_user_accessible() {
+            return Some(Violation::UserAccessibleKernelPage);
+        }
+        None
+    }
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Violation::WritableExecutable => write!(f, "Writable and executable mapping"),
+            Violation::UserAccessibleKernelPage => write!(f, "User-accessible kernel page"),
+            Violation::InnerWithPermissions => {
+                write!(f, "Inner page table entry with R/W/X bits")
+            }
+        }
+    }
+}
@@ -2,12 +2,17 @@
 //!
 //! For more details, see Section `4.4.1 Addressing and Memory Protection` of `Volume II: RISC-V Privileged Architectures`
 
+use core::sync::atomic::{AtomicU64, Ordering};
+
 use crate::kernel::address::Address;
 use crate::kernel::address::PhysicalAddress;
 use crate::kernel::cpu;
 
 const PHYSICAL_PAGE_NUMBER_SIZE: u64 = 1 << 44;
 
+/// Number of low `PPN` bits encoding the size of a NAPOT mapping (only `64KiB` is defined).
+const NAPOT_BITS: u64 = 4;
+
 #[derive(Debug)]
 enum Offset {
     V = 0,
@@ -18,11 +23,15 @@ enum Offset {
     G = 5,
     A = 6,
     D = 7,
+    COW = 8,
+    SWAP = 9,
     PPN = 10,
+    PBMT = 61,
+    N = 63,
 }
 
 /// Abstraction of a page table entry.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PageTableEntry(u64);
 
 impl PageTableEntry {
@@ -125,9 +134,20 @@ impl PageTableEntry {
         (self.0 & (1 << Offset::A as u64)) != 0
     }
 
+    /// Set access flag for page-table entry (`A` bit).
+    ///
+    /// The flag is set atomically, as the hardware may update the `A`/`D` bits concurrently.
+    pub fn set_access_flag(&mut self) {
+        unsafe { AtomicU64::from_ptr(&mut self.0) }
+            .fetch_or(1 << Offset::A as u64, Ordering::Relaxed);
+    }
+
     /// Clear access flag for page-table entry (`A` bit).
+    ///
+    /// The flag is cleared atomically, as the hardware may update the `A`/`D` bits concurrently.
     pub fn clear_access_flag(&mut self) {
-        self.0 &= !(1 << Offset::A as u64);
+        unsafe { AtomicU64::from_ptr(&mut self.0) }
+            .fetch_and(!(1 << Offset::A as u64), Ordering::Relaxed);
     }
 
     /// Check if page-table entry is dirty (`D` bit).
@@ -135,14 +155,107 @@ impl PageTableEntry {
         (self.0 & (1 << Offset::D as u64)) != 0
     }
 
+    /// Set dirty flag for page-table entry (`D` bit).
+    ///
+    /// The flag is set atomically, as the hardware may update the `A`/`D` bits concurrently.
+    pub fn set_dirty_flag(&mut self) {
+        unsafe { AtomicU64::from_ptr(&mut self.0) }
+            .fetch_or(1 << Offset::D as u64, Ordering::Relaxed);
+    }
+
     /// Clear dirty flag for page-table entry (`D` bit).
     pub fn clear_dirty_flag(&mut self) {
         self.0 &= !(1 << Offset::D as u64);
     }
 
+    /// Check if page-table entry is shared copy-on-write (first of the `RSW` bits).
+    pub const fn is_copy_on_write(&self) -> bool {
+        (self.0 & (1 << Offset::COW as u64)) != 0
+    }
+
+    /// Mark page-table entry as (not) shared copy-on-write (first of the `RSW` bits).
+    pub fn mark_as_copy_on_write(&mut self, copy_on_write: bool) {
+        match copy_on_write {
+            true => self.0 |= 1 << Offset::COW as u64,
+            false => self.0 &= !(1 << Offset::COW as u64),
+        };
+    }
+
+    /// Check if (invalid) page-table entry refers to a page written out to the backing store
+    /// (second of the `RSW` bits).
+    pub const fn is_swapped(&self) -> bool {
+        !self.is_valid() && (self.0 & (1 << Offset::SWAP as u64)) != 0
+    }
+
+    /// Turn page-table entry into an invalid reference to `slot` of the backing store (stored
+    /// within the `PPN` bits).
+    pub fn mark_as_swapped(&mut self, slot: usize) {
+        if slot as u64 >= PHYSICAL_PAGE_NUMBER_SIZE {
+            panic!("Only 44-bits backing store slots are supported!");
+        }
+
+        self.0 = (slot as u64) << Offset::PPN as u64 | 1 << Offset::SWAP as u64;
+    }
+
+    /// Get backing store slot of swapped page-table entry (`PPN` bits).
+    pub fn get_swap_slot(&self) -> usize {
+        ((self.0 >> Offset::PPN as u64) & (PHYSICAL_PAGE_NUMBER_SIZE - 1)) as usize
+    }
+
+    /// Get page-based memory type of page-table entry (`PBMT` bits, requires `Svpbmt`).
+    pub const fn get_memory_type(&self) -> u64 {
+        (self.0 >> Offset::PBMT as u64) & 0b11
+    }
+
+    /// Set page-based memory type of page-table entry (`PBMT` bits, requires `Svpbmt`).
+    pub fn set_memory_type(&mut self, memory_type: u64) {
+        assert!(memory_type < 0b11);
+
+        self.0 &= !(0b11 << Offset::PBMT as u64);
+        self.0 |= memory_type << Offset::PBMT as u64;
+    }
+
+    /// Check if page-table entry is part of a naturally aligned `64KiB` mapping (`N` bit).
+    pub const fn is_napot(&self) -> bool {
+        (self.0 & (1 << Offset::N as u64)) != 0
+    }
+
+    /// Mark page-table entry as (not) part of a naturally aligned `64KiB` mapping (`N` bit,
+    /// requires `Svnapot`).
+    ///
+    /// The low `PPN` bits are replaced by the encoded size, thus the physical page has to be set
+    /// (again) when unmarking.
+    pub fn mark_as_napot(&mut self, napot: bool) {
+        let mask = (1 << NAPOT_BITS) - 1 << Offset::PPN as u64;
+        match napot {
+            true => {
+                self.0 &= !mask;
+                self.0 |= 1 << (NAPOT_BITS - 1) << Offset::PPN as u64;
+                self.0 |= 1 << Offset::N as u64;
+            }
+            false => self.0 &= !(1 << Offset::N as u64),
+        };
+    }
+
+    /// Clear page-table entry (all bits).
+    pub fn clear(&mut self) {
+        self.0 = 0;
+    }
+
+    /// Clear page-table entry atomically (as the hardware may update the `A`/`D` bits
+    /// concurrently) and return its previous value.
+    pub fn take(&mut self) -> PageTableEntry {
+        PageTableEntry(unsafe { AtomicU64::from_ptr(&mut self.0) }.swap(0, Ordering::Relaxed))
+    }
+
     /// Get physical page of page-table entry (`PPN` bits)
+    ///
+    /// For NAPOT mappings, the first physical page of the `64KiB` mapping is returned.
     pub fn get_physical_page<T>(&self) -> PhysicalAddress<T> {
-        let ppn = (self.0 >> Offset::PPN as u64) & (PHYSICAL_PAGE_NUMBER_SIZE - 1);
+        let mut ppn = (self.0 >> Offset::PPN as u64) & (PHYSICAL_PAGE_NUMBER_SIZE - 1);
+        if self.is_napot() {
+            ppn &= !((1 << NAPOT_BITS) - 1);
+        }
         PhysicalAddress::new((ppn as usize * cpu::page_size()) as *mut T)
     }
 
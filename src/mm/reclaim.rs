@@ -0,0 +1,82 @@
+//! Reclamation of user pages under memory pressure.
+//!
+//! Pages of virtual memory areas are aged by a clock (second-chance) scan over the accessed bits
+//! of their page table entries (see [`VirtualMemorySystem::reclaim`]). Pages not accessed since
+//! their accessed bit was cleared are evicted: Clean pages are dropped (and zero-filled again on
+//! the next fault), whereas dirty pages are written out to the registered [`BackingStore`] (and
+//! read in again on the next fault).
+//!
+//! [`VirtualMemorySystem::reclaim`]: crate::mm::mapping::VirtualMemorySystem::reclaim
+
+use core::ffi::c_void;
+
+use crate::config;
+use crate::kernel::address::PhysicalAddress;
+use crate::mm::error::MemoryError;
+use crate::mm::page_allocator::PAGE_FRAME_ALLOCATOR;
+use crate::sync::init_cell::InitCell;
+use crate::sync::level::{LevelInitialization, LevelPaging};
+
+/// Registered backing store (if any).
+static BACKING_STORE: InitCell<Option<&'static dyn BackingStore>> = InitCell::new();
+
+/// Storage for dirty pages evicted from memory.
+///
+/// All operations are performed at [`LevelPaging`] (mostly while holding the page tables of the
+/// affected address space), and thus must not block.
+pub trait BackingStore: Sync {
+    /// Write out `page` (accessible via the kernel's direct map) and return the slot holding its
+    /// content.
+    ///
+    /// The page is already unmapped and the page tables are not held meanwhile.
+    ///
+    /// Slots must fit into the `44` bits of a page table entry's physical page number.
+    fn write_out(
+        &self,
+        page: PhysicalAddress<c_void>,
+        token: LevelPaging,
+    ) -> Result<(usize, LevelPaging), (MemoryError, LevelPaging)>;
+
+    /// Read content of `slot` into `page` (accessible via the kernel's direct map).
+    fn read_in(
+        &self,
+        slot: usize,
+        page: PhysicalAddress<c_void>,
+        token: LevelPaging,
+    ) -> Result<LevelPaging, (MemoryError, LevelPaging)>;
+
+    /// Add a reference to `slot` (e.g. for sharing it between address spaces).
+    fn share(
+        &self,
+        slot: usize,
+        token: LevelPaging,
+    ) -> Result<LevelPaging, (MemoryError, LevelPaging)>;
+
+    /// Drop a reference to `slot` (freeing it once the last reference is dropped).
+    fn release(&self, slot: usize, token: LevelPaging) -> LevelPaging;
+}
+
+/// Initialize reclamation using backing `store` for dirty pages (if any).
+///
+/// Without a backing store, only clean pages are reclaimed.
+pub fn initialize(
+    store: Option<&'static dyn BackingStore>,
+    token: LevelInitialization,
+) -> LevelInitialization {
+    let mut backing_store = BACKING_STORE.get_mut(token);
+    *backing_store = store;
+    let token = backing_store.destroy();
+    unsafe { BACKING_STORE.finanlize(token) }
+}
+
+/// Get registered backing store (if any).
+pub fn backing_store() -> Option<&'static dyn BackingStore> {
+    *BACKING_STORE.as_ref()
+}
+
+/// Check if the number of free pages dropped below the low watermark (see
+/// `CONFIG_RECLAIM_LOW_WATERMARK`).
+pub fn is_under_pressure(token: LevelPaging) -> (bool, LevelPaging) {
+    let (free_pages, token) = PAGE_FRAME_ALLOCATOR.free_pages(token);
+    (free_pages < config::RECLAIM_LOW_WATERMARK, token)
+}
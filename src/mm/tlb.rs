@@ -0,0 +1,115 @@
+//! Synchronization of address-translation caches across all online harts (*TLB shootdown*).
+//!
+//! Local translations are invalidated using `SFENCE.VMA`, whereas remote harts are instructed
+//! using the SBI RFENCE extension.
+
+use core::ffi::c_void;
+
+use crate::arch::sfence;
+use crate::kernel::address::{Address, VirtualAddress};
+use crate::kernel::cpu;
+use crate::kernel::cpu_map;
+use crate::kernel::sbi;
+use crate::kernel::sbi::HartMask;
+
+/// Invalidate translation of the leaf page table entry corresponding to `virt_addr` on all
+/// online harts.
+pub fn shootdown_page(virt_addr: VirtualAddress<c_void>) {
+    // Invalidate local translation
+    sfence::sfence_vma_addr(virt_addr);
+
+    // Invalidate remote translations
+    shootdown_remote(virt_addr, cpu::page_size());
+}
+
+/// Invalidate all translations (including non-leaf page table entries) on all online harts.
+pub fn shootdown_all() {
+    // Invalidate local translations
+    sfence::sfence_vma();
+
+    // Invalidate remote translations
+    shootdown_remote(VirtualAddress::null(), usize::MAX);
+}
+
+/// Invalidate translation of the leaf page table entry corresponding to `virt_addr` within
+/// address space `asid` on all online harts.
+pub fn shootdown_page_asid(virt_addr: VirtualAddress<c_void>, asid: usize) {
+    // Invalidate local translation
+    sfence::sfence_vma_addr_asid(virt_addr, asid);
+
+    // Invalidate remote translations
+    shootdown_remote_asid(virt_addr, cpu::page_size(), Some(asid));
+}
+
+/// Invalidate all translations (including non-leaf page table entries) of address space `asid`
+/// on all online harts.
+pub fn shootdown_asid(asid: usize) {
+    // Invalidate local translations
+    sfence::sfence_vma_asid(asid);
+
+    // Invalidate remote translations
+    shootdown_remote_asid(VirtualAddress::null(), usize::MAX, Some(asid));
+}
+
+/// Instruct all online harts (except the current one) to invalidate `[virt_addr, virt_addr + size)`.
+fn shootdown_remote(virt_addr: VirtualAddress<c_void>, size: usize) {
+    shootdown_remote_asid(virt_addr, size, None);
+}
+
+/// Instruct all online harts (except the current one) to invalidate `[virt_addr, virt_addr + size)`
+/// (restricted to address space `asid`, if given).
+///
+/// # Panics
+/// If the SBI implementation fails to perform the remote fence, `panic` will be called.
+fn shootdown_remote_asid(virt_addr: VirtualAddress<c_void>, size: usize, asid: Option<usize>) {
+    // Step 0: Application processors are not started before the CPU map is initialized
+    if !cpu_map::is_initialized() {
+        return;
+    }
+
+    // Step 1: Collect remote harts (in order of their logical ID) into hart masks, starting a new
+    // mask whenever a hart ID does not fit into the current one
+    let current = cpu::current();
+    let mut hart_mask: Option<HartMask> = None;
+    for (logical_id, hart_id) in cpu_map::iter() {
+        if logical_id == current {
+            continue;
+        }
+
+        if let Some(mut mask) = hart_mask {
+            if mask.insert(hart_id) {
+                hart_mask = Some(mask);
+                continue;
+            }
+
+            // Step 2: Flush full hart mask
+            remote_sfence_vma(mask, virt_addr, size, asid);
+        }
+
+        let mut mask = HartMask::new(hart_id);
+        mask.insert(hart_id);
+        hart_mask = Some(mask);
+    }
+
+    // Step 3: Flush remaining hart mask
+    if let Some(mask) = hart_mask {
+        remote_sfence_vma(mask, virt_addr, size, asid);
+    }
+}
+
+/// Perform remote `SFENCE.VMA` (restricted to address space `asid`, if given) on all harts within
+/// `hart_mask`.
+fn remote_sfence_vma(
+    hart_mask: HartMask,
+    virt_addr: VirtualAddress<c_void>,
+    size: usize,
+    asid: Option<usize>,
+) {
+    let result = match asid {
+        Some(asid) => sbi::remote_sfence_vma_asid(hart_mask, virt_addr, size, asid),
+        None => sbi::remote_sfence_vma(hart_mask, virt_addr, size),
+    };
+    if let Err(error) = result {
+        panic!("Unable to perform remote SFENCE.VMA: {}", error);
+    }
+}
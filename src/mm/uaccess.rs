@@ -0,0 +1,221 @@
+//! Access to user memory from the kernel.
+//!
+//! User memory is accessed by dedicated assembly routines (see `uaccess.S`) with `sstatus.SUM`
+//! set. Each of their accesses to user memory is listed in an exception fixup table: Faults which
+//! cannot be resolved (see [`VirtualMemorySystem::handle_page_fault`]) resume at the fixup of the
+//! routine, which reports an error instead of causing a kernel panic.
+//!
+//! Page faults are usually resolved within the `epilogue`. As user memory is only accessed on the
+//! epilogue level (e.g. by a system call), which is thus held by the faulting code already, faults
+//! of these accesses are resolved synchronously on the trap instead (see [`try_fixup`]).
+//!
+//! [`VirtualMemorySystem::handle_page_fault`]: crate::mm::mapping::VirtualMemorySystem::handle_page_fault
+
+use core::ffi::c_void;
+use core::ptr;
+use core::slice;
+
+use crate::arch::csr::CSR;
+use crate::arch::sepc::SEPC;
+use crate::arch::sstatus::{SStatus, SStatusPrivLevel};
+use crate::kernel::address::{Address, VirtualAddress};
+use crate::mm::error::MemoryError;
+use crate::mm::mapping::{Mode, VirtualMemorySystem, USER_SPACE_END};
+use crate::mm::vma::Access;
+use crate::sync::epilogue;
+use crate::sync::level::{Adapter, AdapterEpilogueMemory, AdapterGuard, Level, LevelEpilogue};
+use crate::trap::cause::{Exception, Trap};
+use crate::trap::handler_interface::TrapContext;
+
+extern "C" {
+    fn __uaccess_copy(dst: *mut u8, src: *const u8, len: usize) -> usize;
+    fn __uaccess_strncpy(dst: *mut u8, src: *const u8, len: usize) -> isize;
+
+    static __uaccess_fixups_start: Fixup;
+    static __uaccess_fixups_end: Fixup;
+}
+
+/// Entry of the exception fixup table.
+#[repr(C)]
+struct Fixup {
+    /// Address of instruction accessing user memory.
+    insn: usize,
+    /// Address to resume execution at on fault.
+    target: usize,
+}
+
+/// Get address to resume execution at, if instruction `sepc` accesses user memory.
+pub fn fixup(sepc: usize) -> Option<usize> {
+    let fixups = unsafe {
+        let start = ptr::addr_of!(__uaccess_fixups_start);
+        let end = ptr::addr_of!(__uaccess_fixups_end);
+        slice::from_raw_parts(start, end.offset_from(start) as usize)
+    };
+
+    fixups
+        .iter()
+        .find(|fixup| fixup.insn == sepc)
+        .map(|fixup| fixup.target)
+}
+
+/// Resume faulting access to user memory of the kernel at its fixup, if the fault cannot be
+/// resolved (i.e. for exceptions other than page faults or if resolving fails).
+///
+/// Page faults are left to the page fault handler, unless the epilogue level is held by the
+/// interrupted code. In this case, they are resolved synchronously by the loaded virtual memory
+/// system on behalf of the interrupted code (which holds no locks while accessing user memory, as
+/// guaranteed by the [`LevelEpilogue`] token of [`copy_from_user`], [`copy_to_user`] and
+/// [`strncpy_from_user`]).
+///
+/// Returns whether the trap was handled.
+pub fn try_fixup(state: &mut TrapContext) -> bool {
+    // Step 1: Only consider exceptions of the kernel
+    if let SStatusPrivLevel::UserMode = state.get_sstatus().get_spp() {
+        return false;
+    }
+    let exception = match Trap::from(state.get_scause()) {
+        Trap::Exception(exception) => exception,
+        Trap::Interrupt(_) => return false,
+    };
+
+    // Step 2: Find fixup of faulting instruction
+    let target = match fixup(state.get_sepc().inner() as usize) {
+        Some(target) => target,
+        None => return false,
+    };
+
+    // Step 3: Leave page faults to the page fault handler (if possible), otherwise resolve them
+    // synchronously (resuming the faulting instruction)
+    let access = match exception {
+        Exception::LoadPageFault => Some(Access::Read),
+        Exception::StorePageFault => Some(Access::Write),
+        _ => None,
+    };
+    if let Some(access) = access {
+        if !epilogue::is_entered() {
+            return false;
+        }
+        if resolve(state, access) {
+            return true;
+        }
+    }
+
+    // Step 4: Resume at fixup
+    state.set_sepc(SEPC::new(target as u64));
+    true
+}
+
+/// Resolve page fault of an `access` to user memory synchronously (while the epilogue level is
+/// held by the interrupted code).
+///
+/// Returns whether the fault was resolved.
+fn resolve(state: &TrapContext, access: Access) -> bool {
+    // Borrow epilogue level of the interrupted code (holding no locks, see `copy_from_user`)
+    let token = unsafe { LevelEpilogue::create() };
+
+    let virt_addr = VirtualAddress::new(state.get_stval().raw() as *mut c_void);
+    let adapter = AdapterEpilogueMemory::new();
+    let (guard, token) = adapter.enter(token);
+    let vms = VirtualMemorySystem::active();
+    let (resolved, token) = match vms.handle_page_fault(virt_addr, access, Mode::User, token) {
+        Ok(token) => (true, token),
+        Err((_, token)) => (false, token),
+    };
+
+    // Return epilogue level to the interrupted code
+    let token = guard.leave(token);
+    let _ = token;
+    resolved
+}
+
+/// Copy `dst.len()` bytes from user memory at `src` into `dst`.
+///
+/// The epilogue level is required, as page faults are resolved on behalf of the caller (see
+/// [`try_fixup`]).
+pub fn copy_from_user(
+    dst: &mut [u8],
+    src: VirtualAddress<u8>,
+    token: LevelEpilogue,
+) -> Result<LevelEpilogue, (MemoryError, LevelEpilogue)> {
+    if !is_user_range(src.addr(), dst.len()) {
+        return Err((MemoryError::InvalidAddress, token));
+    }
+
+    let remaining =
+        with_user_access(|| unsafe { __uaccess_copy(dst.as_mut_ptr(), src.as_ptr(), dst.len()) });
+    match remaining {
+        0 => Ok(token),
+        _ => Err((MemoryError::NoSuchAddress, token)),
+    }
+}
+
+/// Copy `src` into user memory at `dst`.
+///
+/// The epilogue level is required, as page faults are resolved on behalf of the caller (see
+/// [`try_fixup`]).
+pub fn copy_to_user(
+    mut dst: VirtualAddress<u8>,
+    src: &[u8],
+    token: LevelEpilogue,
+) -> Result<LevelEpilogue, (MemoryError, LevelEpilogue)> {
+    if !is_user_range(dst.addr(), src.len()) {
+        return Err((MemoryError::InvalidAddress, token));
+    }
+
+    let remaining =
+        with_user_access(|| unsafe { __uaccess_copy(dst.as_mut_ptr(), src.as_ptr(), src.len()) });
+    match remaining {
+        0 => Ok(token),
+        _ => Err((MemoryError::NoSuchAddress, token)),
+    }
+}
+
+/// Copy NUL-terminated string from user memory at `src` into `dst` (including the NUL byte, if it
+/// fits).
+///
+/// On success, the length of the string (excluding the NUL byte) is returned. If no NUL byte is
+/// found within `dst.len()` bytes (or before the end of user space), the number of copied bytes is
+/// returned instead (and `dst` is not NUL-terminated). As for [`copy_from_user`], the epilogue
+/// level is required.
+pub fn strncpy_from_user(
+    dst: &mut [u8],
+    src: VirtualAddress<u8>,
+    token: LevelEpilogue,
+) -> Result<(usize, LevelEpilogue), (MemoryError, LevelEpilogue)> {
+    // Stop at end of user space (instead of rejecting strings close to it)
+    if !is_user_range(src.addr(), 0) {
+        return Err((MemoryError::InvalidAddress, token));
+    }
+    let len = usize::min(dst.len(), USER_SPACE_END - src.addr());
+
+    let length =
+        with_user_access(|| unsafe { __uaccess_strncpy(dst.as_mut_ptr(), src.as_ptr(), len) });
+    match usize::try_from(length) {
+        Ok(length) => Ok((length, token)),
+        Err(_) => Err((MemoryError::NoSuchAddress, token)),
+    }
+}
+
+/// Check if `[addr, addr + len)` lies within user space.
+fn is_user_range(addr: usize, len: usize) -> bool {
+    match addr.checked_add(len) {
+        Some(end) => end <= USER_SPACE_END,
+        None => false,
+    }
+}
+
+/// Execute `f` with access to user memory enabled (`sstatus.SUM`).
+fn with_user_access<R>(f: impl FnOnce() -> R) -> R {
+    let mut sstatus = SStatus::new(0);
+    sstatus.read();
+    let previous = sstatus.get_sum();
+    sstatus.set_sum(true);
+    sstatus.write();
+
+    let result = f();
+
+    sstatus.read();
+    sstatus.set_sum(previous);
+    sstatus.write();
+    result
+}
@@ -0,0 +1,226 @@
+//! Virtual memory areas describing the (lazily populated) regions of an address space.
+
+use core::ffi::c_void;
+use core::fmt::Display;
+
+use crate::kernel::address::{Address, VirtualAddress};
+use crate::kernel::cpu;
+use crate::mm::error::MemoryError;
+use crate::mm::mapping::{Mode, Protection};
+
+/// Maximum number of [`VirtualMemoryArea`]s per address space.
+pub const MAX_AREAS: usize = 32;
+
+/// Kind of memory access (e.g. causing a page fault).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Read access (i.e. load).
+    Read,
+    /// Write access (i.e. store/AMO).
+    Write,
+    /// Instruction fetch.
+    Execute,
+}
+
+impl Access {
+    /// Check if `protection` permits [`Access`].
+    pub fn is_permitted_by(self, protection: Protection) -> bool {
+        match self {
+            Access::Read => protection.is_readable(),
+            Access::Write => protection.is_writable(),
+            Access::Execute => protection.is_executable(),
+        }
+    }
+}
+
+impl Display for Access {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Access::Read => write!(f, "Read"),
+            Access::Write => write!(f, "Write"),
+            Access::Execute => write!(f, "Execute"),
+        }
+    }
+}
+
+/// Backing of a [`VirtualMemoryArea`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backing {
+    /// Anonymous memory (zero-filled on first access).
+    Anonymous,
+    /// Anonymous memory growing downwards on demand up to `max_size` bytes (e.g. stacks).
+    Stack {
+        /// Maximum size of the area in bytes.
+        max_size: usize,
+    },
+}
+
+/// Virtual memory area `[start, end)` with common `protection`/`mode` and `backing`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VirtualMemoryArea {
+    start: usize,
+    end: usize,
+    protection: Protection,
+    mode: Mode,
+    backing: Backing,
+}
+
+impl VirtualMemoryArea {
+    /// Create a new [`VirtualMemoryArea`] covering `size` bytes starting at `start`.
+    pub fn new(
+        start: VirtualAddress<c_void>,
+        size: usize,
+        protection: Protection,
+        mode: Mode,
+        backing: Backing,
+    ) -> Self {
+        Self {
+            start: start.addr(),
+            end: start.addr() + size,
+            protection,
+            mode,
+            backing,
+        }
+    }
+
+    /// Get first virtual address of [`VirtualMemoryArea`].
+    pub fn start(&self) -> VirtualAddress<c_void> {
+        VirtualAddress::new(self.start as *mut c_void)
+    }
+
+    /// Get first virtual address behind [`VirtualMemoryArea`].
+    pub fn end(&self) -> VirtualAddress<c_void> {
+        VirtualAddress::new(self.end as *mut c_void)
+    }
+
+    /// Get size of [`VirtualMemoryArea`] in bytes.
+    pub fn size(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Get protection of [`VirtualMemoryArea`].
+    pub fn protection(&self) -> Protection {
+        self.protection
+    }
+
+    /// Get mode of [`VirtualMemoryArea`].
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Get backing of [`VirtualMemoryArea`].
+    pub fn backing(&self) -> Backing {
+        self.backing
+    }
+
+    /// Check if [`VirtualMemoryArea`] contains `virt_addr`.
+    pub fn contains(&self, virt_addr: VirtualAddress<c_void>) -> bool {
+        self.start <= virt_addr.addr() && virt_addr.addr() < self.end
+    }
+
+    /// Check if [`VirtualMemoryArea`] overlaps with `[start, end)`.
+    fn overlaps(&self, start: usize, end: usize) -> bool {
+        self.start < end && start < self.end
+    }
+}
+
+/// Region list of (non-overlapping) [`VirtualMemoryArea`]s of an address space.
+#[derive(Clone)]
+pub struct VirtualMemoryAreas {
+    areas: [Option<VirtualMemoryArea>; MAX_AREAS],
+}
+
+impl VirtualMemoryAreas {
+    /// Create an empty region list.
+    pub const fn new() -> Self {
+        Self {
+            areas: [None; MAX_AREAS],
+        }
+    }
+
+    /// Insert `area`.
+    ///
+    /// The area must be page-aligned, non-empty and must not overlap with existing areas.
+    pub fn insert(&mut self, area: VirtualMemoryArea) -> Result<(), MemoryError> {
+        // Step 1: Check area
+        if area.start % cpu::page_size() != 0
+            || area.end % cpu::page_size() != 0
+            || area.start >= area.end
+        {
+            return Err(MemoryError::InvalidAddress);
+        }
+        if let Backing::Stack { max_size } = area.backing {
+            if max_size < area.size() || max_size % cpu::page_size() != 0 {
+                return Err(MemoryError::InvalidAddress);
+            }
+        }
+        if self
+            .iter()
+            .any(|other| other.overlaps(area.start, area.end))
+        {
+            return Err(MemoryError::AddressAlreadyInUse);
+        }
+
+        // Step 2: Insert area into free slot
+        match self.areas.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some(area);
+                return Ok(());
+            }
+            None => return Err(MemoryError::OutOfMemory),
+        }
+    }
+
+    /// Remove area starting at `start`.
+    pub fn remove(
+        &mut self,
+        start: VirtualAddress<c_void>,
+    ) -> Result<VirtualMemoryArea, MemoryError> {
+        let slot = self
+            .areas
+            .iter_mut()
+            .find(|slot| matches!(slot, Some(area) if area.start == start.addr()));
+        match slot {
+            Some(slot) => return Ok(slot.take().unwrap()),
+            None => return Err(MemoryError::NoSuchAddress),
+        }
+    }
+
+    /// Find area containing `virt_addr`.
+    pub fn find(&self, virt_addr: VirtualAddress<c_void>) -> Option<&VirtualMemoryArea> {
+        self.iter().find(|area| area.contains(virt_addr))
+    }
+
+    /// Grow a stack area (see [`Backing::Stack`]) downwards to cover `virt_addr`.
+    ///
+    /// The stack may neither exceed its maximum size nor overlap with other areas. On success, the
+    /// grown area is returned.
+    pub fn grow(&mut self, virt_addr: VirtualAddress<c_void>) -> Option<VirtualMemoryArea> {
+        let start = virt_addr.addr() & !(cpu::page_size() - 1);
+
+        // Step 1: Find stack area above `virt_addr` which may grow down to `start`
+        let idx = self.areas.iter().position(|slot| match slot {
+            Some(area) => match area.backing {
+                Backing::Stack { max_size } => start < area.start && area.end - start <= max_size,
+                Backing::Anonymous => false,
+            },
+            None => false,
+        })?;
+        let stack_start = self.areas[idx].unwrap().start;
+
+        // Step 2: Check for collisions with other areas
+        if self.iter().any(|other| other.overlaps(start, stack_start)) {
+            return None;
+        }
+
+        // Step 3: Grow area
+        let area = self.areas[idx].as_mut().unwrap();
+        area.start = start;
+        Some(*area)
+    }
+
+    /// Iterate over all areas.
+    pub fn iter(&self) -> impl Iterator<Item = &VirtualMemoryArea> {
+        self.areas.iter().filter_map(|slot| slot.as_ref())
+    }
+}
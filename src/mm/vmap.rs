@@ -0,0 +1,346 @@
+//! Kernel virtual-range allocator for dynamic mappings after boot.
+//!
+//! A dedicated window of the kernel space (`[VMAP_START, VMAP_START + VMAP_SIZE)`) is managed in
+//! page granularity. Device regions are mapped by [`ioremap`](VirtualRangeAllocator::ioremap),
+//! while non-contiguous page frames are mapped into a contiguous range by
+//! [`vmalloc`](VirtualRangeAllocator::vmalloc). Each range is followed by an unmapped guard page.
+
+use core::ffi::c_void;
+
+use crate::kernel::address::{Address, PhysicalAddress, VirtualAddress};
+use crate::kernel::cpu;
+use crate::mm::error::MemoryError;
+use crate::mm::mapping::{MemoryType, Mode, Protection, KERNEL_VIRTUAL_MEMORY_SYSTEM};
+use crate::mm::page_allocator::PAGE_FRAME_ALLOCATOR;
+use crate::sync::level::{Adapter, AdapterGuard, AdapterMappingPaging, LevelMapping};
+use crate::sync::ticketlock::TicketlockMapping;
+
+/// First virtual address of the window for dynamic mappings.
+///
+/// The window shadows the physical range `[1GiB, 2GiB)` within the kernel's direct map, which
+/// thus must not be used for physical memory (see
+/// [`discover`](crate::mm::page_allocator::PageFrameAllocator::discover)).
+pub const VMAP_START: usize = 0xffff_ffff_4000_0000;
+
+/// Size of the window for dynamic mappings.
+pub const VMAP_SIZE: usize = 0x4000_0000;
+
+/// Maximum number of (concurrently) allocated ranges.
+const MAX_RANGES: usize = 256;
+
+/// Global [`VirtualRangeAllocator`] instance.
+pub static KERNEL_VIRTUAL_RANGES: VirtualRangeAllocator = VirtualRangeAllocator::new();
+
+/// Kind of mapping of a [`Range`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RangeKind {
+    /// Device region (see [`ioremap`](VirtualRangeAllocator::ioremap)).
+    Device,
+    /// Page frames owned by the range (see [`vmalloc`](VirtualRangeAllocator::vmalloc)).
+    Memory,
+}
+
+/// Allocated range of `num_pages` pages (followed by a guard page) starting at `start`.
+#[derive(Debug, Clone, Copy)]
+struct Range {
+    start: usize,
+    num_pages: usize,
+    kind: RangeKind,
+}
+
+impl Range {
+    /// Get first virtual address behind range (including the guard page).
+    fn end(&self) -> usize {
+        self.start + (self.num_pages + 1) * cpu::page_size()
+    }
+
+    /// Check if range (including the guard page) overlaps with `[start, end)`.
+    fn overlaps(&self, start: usize, end: usize) -> bool {
+        self.start < end && start < self.end()
+    }
+}
+
+/// Table of allocated [`Range`]s.
+struct Ranges([Option<Range>; MAX_RANGES]);
+
+impl Ranges {
+    /// Reserve range of `num_pages` pages (plus guard page) using first fit.
+    fn reserve(&mut self, num_pages: usize, kind: RangeKind) -> Result<usize, MemoryError> {
+        let size = (num_pages + 1) * cpu::page_size();
+
+        // Step 1: Find free range (either at the window start or behind an allocated range)
+        let mut candidates =
+            core::iter::once(VMAP_START).chain(self.0.iter().flatten().map(|range| range.end()));
+        let start = candidates.find(|start| {
+            *start + size <= VMAP_START + VMAP_SIZE
+                && !self
+                    .0
+                    .iter()
+                    .flatten()
+                    .any(|range| range.overlaps(*start, *start + size))
+        });
+        let start = match start {
+            Some(start) => start,
+            None => return Err(MemoryError::OutOfMemory),
+        };
+
+        // Step 2: Record range
+        match self.0.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some(Range {
+                    start,
+                    num_pages,
+                    kind,
+                });
+                return Ok(start);
+            }
+            None => return Err(MemoryError::OutOfMemory),
+        }
+    }
+
+    /// Find range starting at `start`.
+    fn find(&self, start: usize) -> Option<Range> {
+        self.0
+            .iter()
+            .flatten()
+            .find(|range| range.start == start)
+            .copied()
+    }
+
+    /// Release range starting at `start`.
+    fn release(&mut self, start: usize) {
+        for slot in self.0.iter_mut() {
+            if matches!(slot, Some(range) if range.start == start) {
+                *slot = None;
+            }
+        }
+    }
+}
+
+/// Allocator of kernel virtual ranges for dynamic mappings (usable at [`LevelMapping`]).
+pub struct VirtualRangeAllocator {
+    ranges: TicketlockMapping<Ranges>,
+}
+
+impl VirtualRangeAllocator {
+    const fn new() -> Self {
+        Self {
+            ranges: TicketlockMapping::new(Ranges([None; MAX_RANGES])),
+        }
+    }
+
+    /// Map device region `[phys_addr, phys_addr + size)` (e.g. memory-mapped IO) into the kernel
+    /// space.
+    ///
+    /// The region is readable/writable for the kernel, but never executable, and is mapped as
+    /// [`MemoryType::IO`] (if `Svpbmt` is supported). The returned address preserves the offset of
+    /// `phys_addr` within its page.
+    pub fn ioremap(
+        &self,
+        phys_addr: PhysicalAddress<c_void>,
+        size: usize,
+        token: LevelMapping,
+    ) -> Result<(VirtualAddress<c_void>, LevelMapping), (MemoryError, LevelMapping)> {
+        self.remap(phys_addr, size, MemoryType::IO, token)
+    }
+
+    /// Map physical region `[phys_addr, phys_addr + size)` into the kernel space using
+    /// `memory_type` (e.g. [`MemoryType::NC`] for buffers shared with non-coherent devices).
+    ///
+    /// Like [`ioremap`](Self::ioremap), the region is readable/writable for the kernel (but never
+    /// executable) and has to be revoked by [`iounmap`](Self::iounmap), as the page frames are not
+    /// owned by the range.
+    pub fn remap(
+        &self,
+        phys_addr: PhysicalAddress<c_void>,
+        size: usize,
+        memory_type: MemoryType,
+        token: LevelMapping,
+    ) -> Result<(VirtualAddress<c_void>, LevelMapping), (MemoryError, LevelMapping)> {
+        // Step 1: Calculate page-aligned region
+        let offset = phys_addr.addr() % cpu::page_size();
+        let phys_start = phys_addr.addr() - offset;
+        let num_pages = (offset + size + cpu::page_size() - 1) / cpu::page_size();
+        if size == 0 {
+            return Err((MemoryError::InvalidAddress, token));
+        }
+
+        // Step 2: Reserve range
+        let (start, token) = self.reserve(num_pages, RangeKind::Device, token)?;
+
+        // Step 3: Map device region
+        let mut token = token;
+        for i in 0..num_pages {
+            let phys_addr =
+                PhysicalAddress::new((phys_start + i * cpu::page_size()) as *mut c_void);
+            let virt_addr = VirtualAddress::new((start + i * cpu::page_size()) as *mut c_void);
+            token = match KERNEL_VIRTUAL_MEMORY_SYSTEM.as_ref().create(
+                phys_addr,
+                virt_addr,
+                Protection::RW,
+                Mode::Kernel,
+                memory_type,
+                token,
+            ) {
+                Ok(token) => token,
+                Err((err, token)) => {
+                    let token = Self::unmap(start, i, RangeKind::Device, token);
+                    return Err((err, self.release(start, token)));
+                }
+            };
+        }
+
+        let virt_addr = VirtualAddress::new((start + offset) as *mut c_void);
+        Ok((virt_addr, token))
+    }
+
+    /// Revoke device mapping created by [`ioremap`](Self::ioremap) (or [`remap`](Self::remap)) at
+    /// `virt_addr`.
+    pub fn iounmap(
+        &self,
+        virt_addr: VirtualAddress<c_void>,
+        token: LevelMapping,
+    ) -> Result<LevelMapping, (MemoryError, LevelMapping)> {
+        self.unmap_range(virt_addr, RangeKind::Device, token)
+    }
+
+    /// Allocate `size` bytes of (zeroed) memory, which are backed by (not necessarily contiguous)
+    /// page frames but mapped into a contiguous kernel range.
+    pub fn vmalloc(
+        &self,
+        size: usize,
+        token: LevelMapping,
+    ) -> Result<(VirtualAddress<c_void>, LevelMapping), (MemoryError, LevelMapping)> {
+        // Step 1: Reserve range
+        let num_pages = (size + cpu::page_size() - 1) / cpu::page_size();
+        if num_pages == 0 {
+            return Err((MemoryError::InvalidAddress, token));
+        }
+        let (start, token) = self.reserve(num_pages, RangeKind::Memory, token)?;
+
+        // Step 2: Allocate and map page frames
+        let mut token = token;
+        for i in 0..num_pages {
+            let virt_addr = VirtualAddress::new((start + i * cpu::page_size()) as *mut c_void);
+
+            let adapter = AdapterMappingPaging::new();
+            let (guard, t) = adapter.enter(token);
+            let (p_page, t) = match PAGE_FRAME_ALLOCATOR.allocate(t) {
+                Ok((p_page, t)) => (p_page, guard.leave(t)),
+                Err((err, t)) => {
+                    let t = Self::unmap(start, i, RangeKind::Memory, guard.leave(t));
+                    return Err((err, self.release(start, t)));
+                }
+            };
+
+            token = match KERNEL_VIRTUAL_MEMORY_SYSTEM.as_ref().create(
+                p_page,
+                virt_addr,
+                Protection::RW,
+                Mode::Kernel,
+                MemoryType::PMA,
+                t,
+            ) {
+                Ok(token) => token,
+                Err((err, t)) => {
+                    let adapter = AdapterMappingPaging::new();
+                    let (guard, t) = adapter.enter(t);
+                    let t = guard.leave(unsafe { PAGE_FRAME_ALLOCATOR.free(p_page, t) });
+
+                    let t = Self::unmap(start, i, RangeKind::Memory, t);
+                    return Err((err, self.release(start, t)));
+                }
+            };
+        }
+
+        let virt_addr = VirtualAddress::new(start as *mut c_void);
+        Ok((virt_addr, token))
+    }
+
+    /// Free memory allocated by [`vmalloc`](Self::vmalloc) at `virt_addr`.
+    pub fn vfree(
+        &self,
+        virt_addr: VirtualAddress<c_void>,
+        token: LevelMapping,
+    ) -> Result<LevelMapping, (MemoryError, LevelMapping)> {
+        self.unmap_range(virt_addr, RangeKind::Memory, token)
+    }
+
+    /// Reserve range of `num_pages` pages.
+    fn reserve(
+        &self,
+        num_pages: usize,
+        kind: RangeKind,
+        token: LevelMapping,
+    ) -> Result<(usize, LevelMapping), (MemoryError, LevelMapping)> {
+        let (mut ranges, token) = self.ranges.lock(token);
+        let result = ranges.reserve(num_pages, kind);
+        let token = ranges.unlock(token);
+
+        match result {
+            Ok(start) => Ok((start, token)),
+            Err(err) => Err((err, token)),
+        }
+    }
+
+    /// Release range starting at `start`.
+    fn release(&self, start: usize, token: LevelMapping) -> LevelMapping {
+        let (mut ranges, token) = self.ranges.lock(token);
+        ranges.release(start);
+        ranges.unlock(token)
+    }
+
+    /// Unmap and release range of `kind` containing `virt_addr` (within its first page).
+    fn unmap_range(
+        &self,
+        virt_addr: VirtualAddress<c_void>,
+        kind: RangeKind,
+        token: LevelMapping,
+    ) -> Result<LevelMapping, (MemoryError, LevelMapping)> {
+        // Step 1: Find range
+        let start = virt_addr.addr() & !(cpu::page_size() - 1);
+        let (ranges, token) = self.ranges.lock(token);
+        let range = ranges.find(start);
+        let token = ranges.unlock(token);
+        let range = match range {
+            Some(range) if range.kind == kind => range,
+            _ => return Err((MemoryError::NoSuchAddress, token)),
+        };
+
+        // Step 2: Unmap pages (before releasing the range for reuse)
+        let token = Self::unmap(range.start, range.num_pages, range.kind, token);
+
+        // Step 3: Release range
+        Ok(self.release(range.start, token))
+    }
+
+    /// Unmap first `num_pages` pages of range starting at `start` (freeing the page frames of
+    /// [`RangeKind::Memory`]).
+    fn unmap(start: usize, num_pages: usize, kind: RangeKind, token: LevelMapping) -> LevelMapping {
+        let vms = KERNEL_VIRTUAL_MEMORY_SYSTEM.as_ref();
+
+        let mut token = token;
+        for i in 0..num_pages {
+            let virt_addr = VirtualAddress::new((start + i * cpu::page_size()) as *mut c_void);
+            token = match (kind, vms.lookup(virt_addr, token)) {
+                (RangeKind::Device, Ok((_, _, _, _, t))) => match vms.remove(virt_addr, t) {
+                    Ok(t) => t,
+                    Err((_, t)) => t,
+                },
+                (RangeKind::Memory, Ok((p_page, _, _, _, t))) => {
+                    let t = match vms.remove(virt_addr, t) {
+                        Ok(t) => t,
+                        Err((_, t)) => t,
+                    };
+
+                    let adapter = AdapterMappingPaging::new();
+                    let (guard, t) = adapter.enter(t);
+                    guard.leave(unsafe { PAGE_FRAME_ALLOCATOR.free(p_page, t) })
+                }
+                (_, Err((_, t))) => t,
+            };
+        }
+
+        token
+    }
+}
@@ -38,6 +38,11 @@ pub fn try_enter() -> Option<LevelEpilogue> {
     }
 }
 
+/// Check if `epilogue` level is currently held by this hart.
+pub fn is_entered() -> bool {
+    EPILOGUE_STATE[cpu::current().raw()].load(Ordering::Relaxed)
+}
+
 /// Leave `epilogue` level.
 pub fn leave(token: LevelEpilogue) {
     // Disable interrupts
@@ -45,9 +50,9 @@ pub fn leave(token: LevelEpilogue) {
 
     // Execute prologue
     let mut epilogue_token = Some(token);
-    while let (Some(trap), token) = TrapHandlers::dequeue(epilogue_token.take().unwrap()) {
+    while let (Some((trap, index)), token) = TrapHandlers::dequeue(epilogue_token.take().unwrap()) {
         // Get corresponding handler
-        let (handler, token) = TrapHandlers::get(trap, token);
+        let (handler, token) = TrapHandlers::get(trap, index, token);
         epilogue_token = Some(token);
 
         // Enable interrupts
@@ -37,6 +37,11 @@ impl<T> InitCell<T> {
         value
     }
 
+    /// Check if initialization was finalized.
+    pub fn is_initialized(&self) -> bool {
+        unsafe { *self.initialized.get().as_ref().unwrap() }
+    }
+
     /// Finanlize initialization routine
     pub unsafe fn finanlize(&self, token: LevelInitialization) -> LevelInitialization {
         let initialized = unsafe { self.initialized.get().as_mut().unwrap() };
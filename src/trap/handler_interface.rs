@@ -3,11 +3,13 @@
 use crate::arch::csr::CSR;
 use crate::arch::register::Register;
 use crate::kernel::cpu;
+use crate::mm::uaccess;
 
 use crate::arch::scause::SCause;
 use crate::arch::sepc::SEPC;
 use crate::arch::sscratch::SScratch;
 use crate::arch::sstatus::SStatus;
+use crate::arch::sstatus::SStatusPrivLevel;
 use crate::arch::stval::STVal;
 use crate::sync::epilogue;
 use crate::sync::level::Level;
@@ -16,8 +18,10 @@ use crate::trap::cause::Interrupt;
 use crate::trap::cause::Trap;
 use crate::trap::handlers::TrapHandlers;
 use crate::trap::intc::INTERRUPT_CONTROLLER;
+use crate::trap::user::save_unit_state;
 
 /// Context object passed by low-level (assembly) trap entry.
+#[repr(C)]
 pub struct TrapContext([u64; 36]);
 
 impl TrapContext {
@@ -363,22 +367,22 @@ impl TrapContext {
 
     /// Set register `sscratch` of [`TrapContext`].
     pub fn set_sscratch(&mut self, sscratch: SScratch) {
-        self.0[31] = sscratch.raw();
+        self.0[32] = sscratch.raw();
     }
 
     /// Set register `sepc` of [`TrapContext`].
     pub fn set_sepc(&mut self, sepc: SEPC) {
-        self.0[31] = sepc.inner();
+        self.0[33] = sepc.inner();
     }
 
     /// Set register `scause` of [`TrapContext`].
     pub fn set_scause(&mut self, scause: SCause) {
-        self.0[31] = scause.raw();
+        self.0[34] = scause.raw();
     }
 
     /// Set register `stval` of [`TrapContext`].
     pub fn set_stval(&mut self, stval: STVal) {
-        self.0[31] = stval.raw();
+        self.0[35] = stval.raw();
     }
 }
 
@@ -390,8 +394,18 @@ extern "C" fn trap_handler(state: *mut TrapContext, user: usize) {
     // Create reference to register
     let state = unsafe { state.as_mut().unwrap() };
 
-    // Check origin of trap
-    assert!(user == 0, "Currently, no user traps are supported!");
+    // Check origin of trap (user traps are handled on the kernel stack of the user thread)
+    assert!((user != 0) == (state.get_sstatus().get_spp() == SStatusPrivLevel::UserMode));
+
+    // Save floating-point/vector state of interrupted user thread (if modified)
+    if user != 0 {
+        unsafe { save_unit_state(state) };
+    }
+
+    // Resume unresolvable faults of user-memory accesses at their fixup
+    if uaccess::try_fixup(state) {
+        return;
+    }
 
     // Get scause
     let sscause = state.get_scause();
@@ -407,11 +421,9 @@ extern "C" fn trap_handler(state: *mut TrapContext, user: usize) {
         Trap::Exception(_) => (trap, prologue_token),
     };
 
-    // Get corresponding handler
-    let (handler, token) = TrapHandlers::get(trap, prologue_token);
-
-    // Execute prologue
-    let (epilogue_required, prologue_token) = handler.prologue(token);
+    // Execute prologues of all corresponding handlers
+    let (claims, prologue_token) = TrapHandlers::prologue(trap, prologue_token);
+    let epilogue_required = !claims.is_empty();
 
     // Send end of interrupt if necessary
     let prologue_token = match trap {
@@ -430,8 +442,11 @@ extern "C" fn trap_handler(state: *mut TrapContext, user: usize) {
             // Enable interrupts
             unsafe { cpu::enable_interrupts() };
 
-            // Execute pending epilogue
-            let epilogue_token = handler.epilogue(Some(state), epilogue_token);
+            // Execute epilogues of claiming handlers
+            let mut epilogue_token = epilogue_token;
+            for (_, handler) in claims.iter() {
+                epilogue_token = handler.epilogue(Some(&mut *state), epilogue_token);
+            }
 
             // Disable interrupts
             unsafe { cpu::disable_interrupts() };
@@ -443,8 +458,12 @@ extern "C" fn trap_handler(state: *mut TrapContext, user: usize) {
         assert!(!cpu::interrupts_enabled());
         epilogue::leave(epilogue_token);
     } else {
+        // Defer epilogues of claiming handlers (each occurrence is counted)
         assert!(!cpu::interrupts_enabled());
-        TrapHandlers::enqueue(trap, prologue_token);
+        let mut prologue_token = prologue_token;
+        for (index, _) in claims.iter() {
+            prologue_token = TrapHandlers::enqueue(trap, index, prologue_token);
+        }
     }
     assert!(!cpu::interrupts_enabled());
 }
@@ -1,38 +1,79 @@
 //! Software-Abstractions for trap handlers.
 
+use core::ptr;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering;
+
+use crate::config;
 use crate::drivers::panic::PANIC;
+use crate::kernel::cpu;
 use crate::sync::init_cell::InitCell;
 use crate::sync::level::LevelEpilogue;
 use crate::sync::level::LevelInitialization;
 use crate::sync::level::LevelLockedPrologue;
 use crate::sync::level::LevelPrologue;
 use crate::sync::per_core::PerCore;
-use crate::trap::cause::Exception;
-use crate::trap::cause::Interrupt;
 use crate::trap::cause::Trap;
 use crate::trap::handler_interface::TrapContext;
+use crate::trap::pending::{EpilogueStatistics, PendingEpilogues};
 
 const NUM_EXCEPTION_HANDLERS: usize = 256;
 const NUM_INTERRUPT_HANDLERS: usize = 256;
 
+/// Maximum number of handlers sharing a single [`Trap`].
+pub const MAX_SHARED_HANDLERS: usize = config::MAX_SHARED_HANDLERS;
+
 /// Instance for registering/requesting [`TrapHandler`]s.
 pub static TRAP_HANDLERS: InitCell<TrapHandlers> = InitCell::new();
 
+/// Whether each hart currently executes `prologue`s.
+static IN_PROLOGUE: [AtomicBool; config::MAX_CPU_NUM] =
+    [const { AtomicBool::new(false) }; config::MAX_CPU_NUM];
+
 /// Convientent wrapper for dealing with shared references to handlers.
 pub type HandlerRef = &'static dyn TrapHandler;
 
+/// Handlers registered for the same [`Trap`] (in order of registration).
+pub type HandlerChain = [Option<HandlerRef>; MAX_SHARED_HANDLERS];
+
+/// Result of a `prologue`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrologueResult {
+    /// The trap was not caused by the device of the handler (e.g. on shared interrupt lines).
+    Unclaimed,
+    /// The trap was handled completely.
+    Handled,
+    /// The trap was claimed, but requires the `epilogue` of the handler.
+    EpilogueRequired,
+}
+
+/// Handlers which claimed a [`Trap`] and require their `epilogue`.
+#[derive(Clone, Copy)]
+pub struct Claims {
+    handlers: [Option<(usize, HandlerRef)>; MAX_SHARED_HANDLERS],
+}
+
+impl Claims {
+    /// Check if no `epilogue` is required.
+    pub fn is_empty(&self) -> bool {
+        self.handlers.iter().all(|handler| handler.is_none())
+    }
+
+    /// Iterate over claiming handlers (with their index within the [`HandlerChain`]).
+    pub fn iter(&self) -> impl Iterator<Item = (usize, HandlerRef)> + '_ {
+        self.handlers.iter().flatten().copied()
+    }
+}
+
 /// Abstraction of trap handlers
 pub struct TrapHandlers {
     /// Register [`TrapHandlers`] for [`Trap::Interrupt`].
-    pub(in crate::trap::handlers) exception_handlers: [HandlerRef; NUM_EXCEPTION_HANDLERS],
+    pub(in crate::trap::handlers) exception_handlers: [HandlerChain; NUM_EXCEPTION_HANDLERS],
     /// Register [`TrapHandlers`] handlers for [`Trap::Exception`]
-    pub(in crate::trap::handlers) interrupt_handlers: [HandlerRef; NUM_INTERRUPT_HANDLERS],
-    /// Pending [`Trap::Interrupt`]s.
-    pub(in crate::trap::handlers) pending_interrupts:
-        PerCore<[bool; NUM_INTERRUPT_HANDLERS], LevelPrologue, LevelLockedPrologue>,
-    /// Pending [`Trap::Exception`]s.
-    pub(in crate::trap::handlers) pending_exceptions:
-        PerCore<[bool; NUM_EXCEPTION_HANDLERS], LevelPrologue, LevelLockedPrologue>,
+    pub(in crate::trap::handlers) interrupt_handlers: [HandlerChain; NUM_INTERRUPT_HANDLERS],
+    /// Pending epilogues (of [`Trap::Interrupt`]s and [`Trap::Exception`]s).
+    pub(in crate::trap::handlers) pending:
+        PerCore<PendingEpilogues, LevelPrologue, LevelLockedPrologue>,
 }
 
 impl TrapHandlers {
@@ -42,20 +83,22 @@ impl TrapHandlers {
         let mut handlers = TRAP_HANDLERS.get_mut(token);
 
         // Initialize members
-        let panic: HandlerRef = &PANIC;
-        handlers.exception_handlers = [panic; NUM_EXCEPTION_HANDLERS];
-        handlers.interrupt_handlers = [panic; NUM_INTERRUPT_HANDLERS];
+        handlers.exception_handlers = [[None; MAX_SHARED_HANDLERS]; NUM_EXCEPTION_HANDLERS];
+        handlers.interrupt_handlers = [[None; MAX_SHARED_HANDLERS]; NUM_INTERRUPT_HANDLERS];
 
-        handlers.pending_interrupts = PerCore::new_copy([false; NUM_INTERRUPT_HANDLERS]);
-        handlers.pending_exceptions = PerCore::new_copy([false; NUM_EXCEPTION_HANDLERS]);
+        handlers.pending = PerCore::new_copy(PendingEpilogues::new());
 
         handlers.destroy()
     }
 
     /// Register `handler` for `trap`
     ///
+    /// Multiple handlers may share the same `trap` (e.g. devices on a shared interrupt line). Their
+    /// `prologue`s are executed in order of registration.
+    ///
     /// # Panic
-    /// If another `handler` is already register for `trap`, this function will panic!
+    /// If `handler` is already registered for `trap` or [`MAX_SHARED_HANDLERS`] are exceeded, this
+    /// function will panic!
     pub fn register(
         trap: Trap,
         handler: HandlerRef,
@@ -63,28 +106,35 @@ impl TrapHandlers {
     ) -> LevelInitialization {
         let mut handlers = TRAP_HANDLERS.get_mut(token);
 
-        let panic: HandlerRef = &PANIC;
-        match trap {
+        // Get corresponding chain
+        let chain = match trap {
             Trap::Interrupt(interrupt) => {
                 let index: usize = interrupt.into();
-                if handlers.interrupt_handlers[index] as *const _ != panic as *const _ {
-                    panic!(
-                        "Unable to overwrite handler for {} at trap handlers interface",
-                        interrupt
-                    );
-                }
-                handlers.interrupt_handlers[index] = handler;
+                &mut handlers.interrupt_handlers[index]
             }
             Trap::Exception(exception) => {
                 let index: usize = exception.into();
-                if handlers.exception_handlers[index] as *const _ != panic as *const _ {
-                    panic!(
-                        "Unable to overwrite handler for {} at trap handlers interface",
-                        exception
-                    );
-                }
-                handlers.exception_handlers[index] = handler;
+                &mut handlers.exception_handlers[index]
             }
+        };
+
+        // Append handler
+        if chain
+            .iter()
+            .flatten()
+            .any(|registered| ptr::addr_eq(*registered, handler))
+        {
+            panic!(
+                "Unable to register handler twice for {} at trap handlers interface",
+                trap
+            );
+        }
+        match chain.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => *slot = Some(handler),
+            None => panic!(
+                "Unable to register more than {} handlers for {} at trap handlers interface",
+                MAX_SHARED_HANDLERS, trap
+            ),
         }
 
         handlers.destroy()
@@ -97,95 +147,112 @@ impl TrapHandlers {
         token
     }
 
-    /// Get corresponding [`HandlerRef`] for [`Trap`].
-    pub fn get(trap: Trap, token: LevelPrologue) -> (HandlerRef, LevelPrologue) {
-        let handler = match trap {
+    /// Get corresponding [`HandlerChain`] for [`Trap`].
+    pub fn chain(trap: Trap, token: LevelPrologue) -> (&'static HandlerChain, LevelPrologue) {
+        let chain = match trap {
             Trap::Interrupt(interrupt) => {
                 let index: usize = interrupt.into();
-                TRAP_HANDLERS.as_ref().interrupt_handlers[index]
+                &TRAP_HANDLERS.as_ref().interrupt_handlers[index]
             }
             Trap::Exception(exception) => {
                 let index: usize = exception.into();
-                TRAP_HANDLERS.as_ref().exception_handlers[index]
+                &TRAP_HANDLERS.as_ref().exception_handlers[index]
             }
         };
 
-        (handler, token)
+        (chain, token)
     }
 
-    /// Enqueue a pending [`Trap`].
+    /// Get handler `index` of the [`HandlerChain`] for [`Trap`].
     ///
-    /// If a [`Trap`] interrupts an other currently running `epilogue` with its own corresponding
-    /// `prologue`, the corresponding [`Trap`] is enqueue and executed later on.
-    pub fn enqueue(trap: Trap, token: LevelPrologue) -> LevelPrologue {
-        let token = match trap {
-            Trap::Interrupt(interrupt) => {
-                let index: usize = interrupt.into();
-                let (mut pending_interrupt, token) =
-                    TRAP_HANDLERS.as_ref().pending_interrupts.get_mut(token);
-                pending_interrupt[index] = true;
-                pending_interrupt.destroy(token)
-            }
-            Trap::Exception(exception) => {
-                let index: usize = exception.into();
-                let (mut pending_exception, token) =
-                    TRAP_HANDLERS.as_ref().pending_exceptions.get_mut(token);
-                pending_exception[index] = true;
-                pending_exception.destroy(token)
-            }
+    /// # Panic
+    /// If no handler is registered at `index`, this function will panic!
+    pub fn get(trap: Trap, index: usize, token: LevelPrologue) -> (HandlerRef, LevelPrologue) {
+        let (chain, token) = Self::chain(trap, token);
+        let handler = match chain.get(index) {
+            Some(Some(handler)) => *handler,
+            _ => panic!("No handler {} registered for {}", index, trap),
         };
 
-        token
+        (handler, token)
     }
 
-    /// Dequeue a pending [`Trap`].
+    /// Execute `prologue`s of all handlers registered for `trap`.
     ///
-    /// If a [`Trap`] interrupts an other currently running `epilogue` with its own corresponding
-    /// `prologue`, the corresponding [`Trap`] is enqueue and dequeued later on.
-    pub fn dequeue(token: LevelPrologue) -> (Option<Trap>, LevelPrologue) {
-        let mut trap = None;
-
-        // Check for pending interrupt
-        let (mut pending_interrupts, token) =
-            TRAP_HANDLERS.as_ref().pending_interrupts.get_mut(token);
-        for (i, pending) in pending_interrupts.iter().enumerate() {
-            if *pending {
-                let interrupt = Interrupt::from(i);
-                trap = Some(Trap::Interrupt(interrupt));
-                break;
-            }
-        }
-        if let Some(Trap::Interrupt(interrupt)) = trap {
-            // Mark interrupt as processed
-            let index: usize = interrupt.into();
-            pending_interrupts[index] = false;
+    /// Every handler of the [`HandlerChain`] is asked, as devices on shared interrupt lines may
+    /// signal simultaneously. If no handler claims `trap`, it is unexpected and handed to
+    /// [`PANIC`].
+    pub fn prologue(trap: Trap, token: LevelPrologue) -> (Claims, LevelPrologue) {
+        let (chain, mut token) = Self::chain(trap, token);
 
-            // Return pending interrupt
-            return (trap, pending_interrupts.destroy(token));
-        }
-        let token = pending_interrupts.destroy(token);
-
-        // Check for pending exception
-        let (mut pending_exceptions, token) =
-            TRAP_HANDLERS.as_ref().pending_exceptions.get_mut(token);
-        for (i, pending) in pending_exceptions.iter().enumerate() {
-            if *pending {
-                let exception = Exception::from(i);
-                trap = Some(Trap::Exception(exception));
-                break;
+        let mut claims = Claims {
+            handlers: [None; MAX_SHARED_HANDLERS],
+        };
+        let mut claimed = false;
+        IN_PROLOGUE[cpu::current().raw()].store(true, Ordering::Relaxed);
+        for (index, handler) in chain.iter().enumerate() {
+            let handler = match handler {
+                Some(handler) => *handler,
+                None => continue,
+            };
+
+            let (result, t) = handler.prologue(token);
+            token = t;
+            match result {
+                PrologueResult::Unclaimed => {}
+                PrologueResult::Handled => claimed = true,
+                PrologueResult::EpilogueRequired => {
+                    claimed = true;
+                    claims.handlers[index] = Some((index, handler));
+                }
             }
         }
-        if let Some(Trap::Exception(exception)) = trap {
-            // Mark exception as processed
-            let index: usize = exception.into();
-            pending_exceptions[index] = false;
 
-            // Return pending exception
-            return (trap, pending_exceptions.destroy(token));
+        IN_PROLOGUE[cpu::current().raw()].store(false, Ordering::Relaxed);
+
+        // Unexpected trap
+        if !claimed {
+            let (_, t) = PANIC.prologue(token);
+            token = t;
         }
-        let token = pending_exceptions.destroy(token);
 
-        (None, token)
+        (claims, token)
+    }
+
+    /// Check if the current hart executes a `prologue`.
+    pub fn in_prologue() -> bool {
+        IN_PROLOGUE[cpu::current().raw()].load(Ordering::Relaxed)
+    }
+
+    /// Enqueue a pending [`Trap`].
+    ///
+    /// If a [`Trap`] interrupts an other currently running `epilogue` with its own corresponding
+    /// `prologue`, the `epilogue` of handler `index` (of its [`HandlerChain`]) is enqueued and
+    /// executed later on. Every occurrence is counted, unless the queue is full (see
+    /// [`EpilogueStatistics::lost`]).
+    pub fn enqueue(trap: Trap, index: usize, token: LevelPrologue) -> LevelPrologue {
+        let (mut pending, token) = TRAP_HANDLERS.as_ref().pending.get_mut(token);
+        pending.push(trap, index);
+        pending.destroy(token)
+    }
+
+    /// Dequeue a pending [`Trap`].
+    ///
+    /// If a [`Trap`] interrupts an other currently running `epilogue` with its own corresponding
+    /// `prologue`, the corresponding [`Trap`] (and the index of the claiming handler) is enqueue
+    /// and dequeued later on. Traps with a configured priority are dequeued first, all others in
+    /// FIFO order.
+    pub fn dequeue(token: LevelPrologue) -> (Option<(Trap, usize)>, LevelPrologue) {
+        let (mut pending, token) = TRAP_HANDLERS.as_ref().pending.get_mut(token);
+        let trap = pending.pop();
+        (trap, pending.destroy(token))
+    }
+
+    /// Get statistics about pending epilogues of the current hart.
+    pub fn statistics(token: LevelPrologue) -> (EpilogueStatistics, LevelPrologue) {
+        let (pending, token) = TRAP_HANDLERS.as_ref().pending.get(token);
+        let statistics = pending.statistics();
+        (statistics, pending.destroy(token))
     }
 }
 
@@ -207,8 +274,11 @@ pub trait TrapHandler: Sync {
     /// enables low-latency interrupt handling, but in turn implies the strict requirements for the
     /// handler: It *must* be as short as possible as interrupts are disabled during execution.
     /// Thus, no locking/blocking/waiting/... is allowed! For such tasks, an optional `epilogue`
-    /// can be requested by return `true`.
-    fn prologue(&self, token: LevelPrologue) -> (bool, LevelPrologue);
+    /// can be requested by returning [`PrologueResult::EpilogueRequired`].
+    ///
+    /// As interrupt lines may be shared, the `prologue` has to check whether its device caused the
+    /// trap and otherwise return [`PrologueResult::Unclaimed`].
+    fn prologue(&self, token: LevelPrologue) -> (PrologueResult, LevelPrologue);
 
     /// Low-priority task of Prologue/Epilogue model.
     ///
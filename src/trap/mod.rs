@@ -4,3 +4,7 @@ pub mod cause;
 pub mod handler_interface;
 pub mod handlers;
 pub mod intc;
+pub mod pending;
+pub mod syscall;
+pub mod user;
+pub mod user_test;
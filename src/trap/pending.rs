@@ -0,0 +1,158 @@
+//! Queue of pending epilogues (per hart).
+//!
+//! If a [`Trap`] interrupts another currently running `epilogue`, the `epilogue`s of its claiming
+//! handlers are deferred by enqueuing the trap together with the index of each handler. Repeated
+//! occurrences of an already pending epilogue are merged into its entry, whereby each occurrence
+//! is counted and thus executes the `epilogue` once more later on.
+//!
+//! Pending epilogues are executed in FIFO order, except for the interrupts listed in
+//! [`config::EPILOGUE_PRIORITIES`], whose epilogues take precedence (in the given order).
+
+use core::fmt::Display;
+
+use crate::config;
+use crate::trap::cause::Trap;
+
+/// Pending epilogue of handler `handler` for `trap` (with number of occurrences).
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    trap: Trap,
+    handler: usize,
+    count: usize,
+    sequence: u64,
+}
+
+/// Statistics about deferred epilogues of a hart.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EpilogueStatistics {
+    /// Number of deferred occurrences.
+    pub enqueued: usize,
+    /// Number of occurrences merged into an entry of the same (already pending) epilogue.
+    pub merged: usize,
+    /// Number of occurrences lost due to a full queue.
+    pub lost: usize,
+    /// Number of executed deferred epilogues.
+    pub executed: usize,
+    /// Maximum number of simultaneously pending occurrences.
+    pub max_pending: usize,
+}
+
+impl Display for EpilogueStatistics {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "enqueued: {}, merged: {}, lost: {}, executed: {}, max. pending: {}",
+            self.enqueued, self.merged, self.lost, self.executed, self.max_pending
+        )
+    }
+}
+
+/// Queue of pending epilogues.
+#[derive(Debug, Clone, Copy)]
+pub struct PendingEpilogues {
+    entries: [Option<Entry>; config::EPILOGUE_QUEUE_SIZE],
+    /// Sequence number of the next new entry (to preserve FIFO order).
+    sequence: u64,
+    /// Number of pending occurrences.
+    pending: usize,
+    statistics: EpilogueStatistics,
+}
+
+impl PendingEpilogues {
+    /// Create an empty queue.
+    pub const fn new() -> Self {
+        Self {
+            entries: [None; config::EPILOGUE_QUEUE_SIZE],
+            sequence: 0,
+            pending: 0,
+            statistics: EpilogueStatistics {
+                enqueued: 0,
+                merged: 0,
+                lost: 0,
+                executed: 0,
+                max_pending: 0,
+            },
+        }
+    }
+
+    /// Enqueue occurrence of `trap` claimed by handler `handler`.
+    pub fn push(&mut self, trap: Trap, handler: usize) {
+        // Step 1: Merge with pending occurrences of the same epilogue
+        if let Some(entry) = self
+            .entries
+            .iter_mut()
+            .flatten()
+            .find(|e| e.trap == trap && e.handler == handler)
+        {
+            entry.count += 1;
+            self.statistics.merged += 1;
+            self.account_enqueued();
+            return;
+        }
+
+        // Step 2: Append new entry (or drop occurrence, if the queue is full)
+        match self.entries.iter_mut().find(|e| e.is_none()) {
+            Some(slot) => {
+                *slot = Some(Entry {
+                    trap,
+                    handler,
+                    count: 1,
+                    sequence: self.sequence,
+                });
+                self.sequence += 1;
+                self.account_enqueued();
+            }
+            None => self.statistics.lost += 1,
+        }
+    }
+
+    /// Dequeue next occurrence (highest priority first, otherwise in FIFO order).
+    pub fn pop(&mut self) -> Option<(Trap, usize)> {
+        // Step 1: Select entry
+        let slot = self
+            .entries
+            .iter_mut()
+            .filter(|e| e.is_some())
+            .max_by_key(|e| {
+                let entry = e.unwrap();
+                (priority(entry.trap), u64::MAX - entry.sequence)
+            })?;
+
+        // Step 2: Consume one occurrence
+        let entry = slot.as_mut().unwrap();
+        let trap = entry.trap;
+        let handler = entry.handler;
+        entry.count -= 1;
+        if entry.count == 0 {
+            *slot = None;
+        }
+        self.pending -= 1;
+        self.statistics.executed += 1;
+
+        Some((trap, handler))
+    }
+
+    /// Get statistics.
+    pub fn statistics(&self) -> EpilogueStatistics {
+        self.statistics
+    }
+
+    /// Account enqueued occurrence.
+    fn account_enqueued(&mut self) {
+        self.pending += 1;
+        self.statistics.enqueued += 1;
+        self.statistics.max_pending = usize::max(self.statistics.max_pending, self.pending);
+    }
+}
+
+/// Get priority of `trap` (`0` for traps without configured priority).
+fn priority(trap: Trap) -> usize {
+    let priorities = config::EPILOGUE_PRIORITIES;
+    match trap {
+        Trap::Interrupt(interrupt) => match priorities.iter().position(|p| *p == interrupt) {
+            Some(position) => priorities.len() - position,
+            None => 0,
+        },
+        Trap::Exception(_) => 0,
+    }
+}
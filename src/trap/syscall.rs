@@ -0,0 +1,376 @@
+//! System calls of user-mode code.
+//!
+//! User threads request services of the kernel by `ecall` (i.e. [`Exception::EnvCallUser`]): The
+//! number of the system call is passed in `a7`, its arguments in `a0`..`a6`. System calls are
+//! executed within the `epilogue` and return their result in `a0`, whereby errors are reported as
+//! negative [`Errno`] values.
+//!
+//! As the `epilogue` level is held, page faults of system calls accessing user memory are resolved
+//! synchronously (see [`uaccess`]).
+
+use core::fmt::Display;
+
+use crate::arch::csr::CSR;
+use crate::arch::register::Register;
+use crate::arch::sepc::SEPC;
+use crate::arch::time::Time;
+use crate::config;
+use crate::drivers::driver::{Driver, DriverError};
+use crate::drivers::timer::TIMER;
+use crate::kernel::address::{Address, VirtualAddress};
+use crate::kernel::cpu;
+use crate::kernel::printer::{LogLevel, PRINTER};
+use crate::mm::error::MemoryError;
+use crate::mm::uaccess;
+use crate::printk;
+use crate::sync::init_cell::InitCell;
+use crate::sync::level::{LevelEpilogue, LevelInitialization, LevelPrologue};
+use crate::trap::cause::{Exception, Trap};
+use crate::trap::handler_interface::TrapContext;
+use crate::trap::handlers::{PrologueResult, TrapHandler, TrapHandlers};
+use crate::trap::{user, user_test};
+
+const NUM_SYSCALLS: usize = 256;
+
+/// Maximum number of arguments of a system call (`a0`..`a6`).
+const MAX_ARGS: usize = 7;
+
+/// Size of the kernel buffer used to copy user data for output.
+const WRITE_CHUNK_SIZE: usize = 128;
+
+/// Numbers of the initial system calls.
+///
+/// The numbers follow the Linux ABI for RISC-V, although `GET_TIME` returns the time directly
+/// instead of filling a `timespec`.
+pub mod number {
+    /// Write `a2` bytes at `a1` to file descriptor `a0` (only `stdout`/`stderr`).
+    pub const WRITE: usize = 64;
+    /// Terminate calling thread with exit status `a0`.
+    pub const EXIT: usize = 93;
+    /// Get time since boot in nanoseconds.
+    pub const GET_TIME: usize = 113;
+    /// Yield hart to other work.
+    pub const YIELD: usize = 124;
+}
+
+/// Instance for registering/requesting system calls.
+pub static SYSCALLS: InitCell<Syscalls> = InitCell::new();
+
+/// Global handler of environment calls from user mode.
+pub static SYSCALL_HANDLER: SyscallHandler = SyscallHandler {};
+
+/// Implementation of a system call.
+pub type SyscallFn = fn(&SyscallArgs, LevelEpilogue) -> (Result<usize, Errno>, LevelEpilogue);
+
+/// Error numbers returned (negated) by system calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Errno {
+    /// Input/output error.
+    Io = 5,
+    /// Bad file descriptor.
+    BadFileDescriptor = 9,
+    /// Out of memory.
+    NoMemory = 12,
+    /// Bad address.
+    Fault = 14,
+    /// Invalid argument.
+    InvalidArgument = 22,
+    /// Function not implemented.
+    NoSys = 38,
+}
+
+impl Display for Errno {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Errno::Io => write!(f, "Input/output error"),
+            Errno::BadFileDescriptor => write!(f, "Bad file descriptor"),
+            Errno::NoMemory => write!(f, "Out of memory"),
+            Errno::Fault => write!(f, "Bad address"),
+            Errno::InvalidArgument => write!(f, "Invalid argument"),
+            Errno::NoSys => write!(f, "Function not implemented"),
+        }
+    }
+}
+
+impl From<MemoryError> for Errno {
+    fn from(err: MemoryError) -> Self {
+        match err {
+            MemoryError::OutOfMemory => Errno::NoMemory,
+            MemoryError::AddressAlreadyInUse => Errno::InvalidArgument,
+            MemoryError::NoSuchAddress => Errno::Fault,
+            MemoryError::InvalidAddress => Errno::Fault,
+            MemoryError::PermissionDenied => Errno::Fault,
+        }
+    }
+}
+
+/// Conversion of a raw argument register into a typed argument.
+pub trait SyscallArg {
+    /// Convert raw register value.
+    fn from_raw(raw: u64) -> Self;
+}
+
+impl SyscallArg for u64 {
+    fn from_raw(raw: u64) -> Self {
+        raw
+    }
+}
+
+impl SyscallArg for i64 {
+    fn from_raw(raw: u64) -> Self {
+        raw as i64
+    }
+}
+
+impl SyscallArg for usize {
+    fn from_raw(raw: u64) -> Self {
+        raw as usize
+    }
+}
+
+impl SyscallArg for isize {
+    fn from_raw(raw: u64) -> Self {
+        raw as isize
+    }
+}
+
+impl SyscallArg for u32 {
+    fn from_raw(raw: u64) -> Self {
+        raw as u32
+    }
+}
+
+impl SyscallArg for i32 {
+    fn from_raw(raw: u64) -> Self {
+        raw as i32
+    }
+}
+
+impl<T> SyscallArg for VirtualAddress<T> {
+    fn from_raw(raw: u64) -> Self {
+        VirtualAddress::new(raw as usize as *mut T)
+    }
+}
+
+/// Number and arguments of a system call (as passed in `a7` and `a0`..`a6`).
+pub struct SyscallArgs {
+    number: usize,
+    args: [u64; MAX_ARGS],
+}
+
+impl SyscallArgs {
+    /// Extract system call from user context `state`.
+    fn new(state: &TrapContext) -> Self {
+        Self {
+            number: state.get_x17().raw() as usize,
+            args: [
+                state.get_x10().raw(),
+                state.get_x11().raw(),
+                state.get_x12().raw(),
+                state.get_x13().raw(),
+                state.get_x14().raw(),
+                state.get_x15().raw(),
+                state.get_x16().raw(),
+            ],
+        }
+    }
+
+    /// Get number of system call.
+    pub fn number(&self) -> usize {
+        self.number
+    }
+
+    /// Get argument `index` (i.e. register `a<index>`) converted to `T`.
+    ///
+    /// # Panic
+    /// If `index` exceeds the maximum number of arguments, this function will panic!
+    pub fn get<T: SyscallArg>(&self, index: usize) -> T {
+        T::from_raw(self.args[index])
+    }
+}
+
+/// Table of system calls (indexed by their number).
+pub struct Syscalls {
+    handlers: [Option<SyscallFn>; NUM_SYSCALLS],
+}
+
+impl Syscalls {
+    /// Prepare [`SYSCALLS`] with the initial system calls.
+    pub fn initialize(token: LevelInitialization) -> LevelInitialization {
+        let mut syscalls = SYSCALLS.get_mut(token);
+        syscalls.handlers = [None; NUM_SYSCALLS];
+        let token = syscalls.destroy();
+
+        let token = Self::register(number::WRITE, sys_write, token);
+        let token = Self::register(number::EXIT, sys_exit, token);
+        let token = Self::register(number::GET_TIME, sys_get_time, token);
+        let token = Self::register(number::YIELD, sys_yield, token);
+        token
+    }
+
+    /// Register `handler` for system call `number`.
+    ///
+    /// # Panic
+    /// If `number` is out of range or another `handler` is already registered for `number`, this
+    /// function will panic!
+    pub fn register(
+        number: usize,
+        handler: SyscallFn,
+        token: LevelInitialization,
+    ) -> LevelInitialization {
+        let mut syscalls = SYSCALLS.get_mut(token);
+
+        if number >= NUM_SYSCALLS {
+            panic!("System call number {} is out of range", number);
+        }
+        if syscalls.handlers[number].is_some() {
+            panic!("Unable to overwrite handler for system call {}", number);
+        }
+        syscalls.handlers[number] = Some(handler);
+
+        syscalls.destroy()
+    }
+
+    /// Finish initialization of [`SYSCALLS`] after all system calls are registered.
+    pub fn finalize(token: LevelInitialization) -> LevelInitialization {
+        let token = unsafe { SYSCALLS.finanlize(token) };
+        token
+    }
+
+    /// Execute system call `args`.
+    fn dispatch(args: &SyscallArgs, token: LevelEpilogue) -> (Result<usize, Errno>, LevelEpilogue) {
+        match SYSCALLS.as_ref().handlers.get(args.number()) {
+            Some(Some(handler)) => handler(args, token),
+            _ => (Err(Errno::NoSys), token),
+        }
+    }
+}
+
+/// Handler for environment calls from user mode.
+pub struct SyscallHandler {}
+
+impl Driver for SyscallHandler {
+    fn initiailize(
+        token: LevelInitialization,
+    ) -> Result<LevelInitialization, (DriverError, LevelInitialization)>
+    where
+        Self: Sized,
+    {
+        let token = Syscalls::initialize(token);
+        let token = TrapHandlers::register(
+            Trap::Exception(Exception::EnvCallUser),
+            &SYSCALL_HANDLER,
+            token,
+        );
+
+        return Ok(token);
+    }
+}
+
+impl TrapHandler for SyscallHandler {
+    fn cause() -> Trap
+    where
+        Self: Sized,
+    {
+        Trap::Exception(Exception::EnvCallUser)
+    }
+
+    fn prologue(&self, token: LevelPrologue) -> (PrologueResult, LevelPrologue) {
+        (PrologueResult::EpilogueRequired, token)
+    }
+
+    fn epilogue(&self, state: Option<&mut TrapContext>, token: LevelEpilogue) -> LevelEpilogue {
+        let state = match state {
+            Some(state) => state,
+            None => panic!("System call epilogue requires the trap context"),
+        };
+
+        // Step 1: Resume behind `ecall`
+        state.set_sepc(SEPC::new(state.get_sepc().inner() + 4));
+
+        // Step 2: Execute system call
+        let args = SyscallArgs::new(state);
+        let (result, token) = Syscalls::dispatch(&args, token);
+
+        // Step 3: Return result (or negated error number)
+        let ret = match result {
+            Ok(value) => value as u64,
+            Err(errno) => (-(errno as i64)) as u64,
+        };
+        state.set_x10(Register::new(ret));
+
+        token
+    }
+}
+
+/// Write buffer `a1` of `a2` bytes to `stdout` (`1`) or `stderr` (`2`) given by `a0`.
+fn sys_write(args: &SyscallArgs, token: LevelEpilogue) -> (Result<usize, Errno>, LevelEpilogue) {
+    let fd: usize = args.get(0);
+    let buf: VirtualAddress<u8> = args.get(1);
+    let len: usize = args.get(2);
+    if fd != 1 && fd != 2 {
+        return (Err(Errno::BadFileDescriptor), token);
+    }
+
+    let mut chunk = [0u8; WRITE_CHUNK_SIZE];
+    let mut written = 0;
+    let mut token = token;
+    while written < len {
+        let size = usize::min(len - written, WRITE_CHUNK_SIZE);
+        let src = VirtualAddress::new(buf.addr().wrapping_add(written) as *mut u8);
+        token = match uaccess::copy_from_user(&mut chunk[..size], src, token) {
+            Ok(token) => token,
+            Err((err, token)) => return (Err(Errno::from(err)), token),
+        };
+        // Report output failures (or the number of bytes written before)
+        if PRINTER.as_ref().write_bytes(&chunk[..size]).is_err() {
+            return match written {
+                0 => (Err(Errno::Io), token),
+                _ => (Ok(written), token),
+            };
+        }
+        written += size;
+    }
+
+    (Ok(written), token)
+}
+
+/// Terminate calling thread with exit status `a0`.
+///
+/// Without a scheduler, the hart is left idle afterwards (still serving interrupts).
+fn sys_exit(args: &SyscallArgs, token: LevelEpilogue) -> (Result<usize, Errno>, LevelEpilogue) {
+    let status: i32 = args.get(0);
+    printk!(
+        LogLevel::Info,
+        "Core {}: User thread exited with status {}\n",
+        cpu::current(),
+        status
+    );
+
+    // Report result of the user test program (the only user thread, if configured)
+    if config::USER_TEST {
+        user_test::report(status);
+    }
+
+    user::exit(token)
+}
+
+/// Get time since boot in nanoseconds.
+fn sys_get_time(args: &SyscallArgs, token: LevelEpilogue) -> (Result<usize, Errno>, LevelEpilogue) {
+    let _ = args;
+
+    let mut time = Time::new(0);
+    time.read();
+    let ns = time.inner() as usize * 1000 / TIMER.as_ref().ticks_per_us();
+
+    (Ok(ns), token)
+}
+
+/// Yield hart to other work.
+///
+/// Without a scheduler, only pending epilogues are executed before the thread resumes.
+fn sys_yield(args: &SyscallArgs, token: LevelEpilogue) -> (Result<usize, Errno>, LevelEpilogue) {
+    let _ = args;
+
+    (Ok(0), token)
+}
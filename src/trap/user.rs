@@ -0,0 +1,394 @@
+//! Execution of user-mode code.
+//!
+//! Each [`UserThread`] owns a kernel stack, whose top holds the state of the thread: Its unit state
+//! (see below), the kernel `tp` (i.e. the logical CPU ID of the executing hart) and the user
+//! [`TrapContext`] of the thread (in descending order of addresses). While the
+//! thread executes in user mode, `sscratch` refers to this context: On a trap, `__trap_entry`
+//! saves all user registers into it, restores the kernel `tp` and handles the trap on the kernel
+//! stack below the context (see `entry.S`). Returning from the trap resumes the thread.
+//!
+//! The state of the floating-point and vector units (i.e. the unit state) is kept per thread and
+//! handled lazily using the dirty tracking of `sstatus.FS`/`sstatus.VS`: It is only saved on traps
+//! if modified by the thread (see [`save_unit_state`]) and only restored when entering user mode,
+//! if the registers of the hart hold the state of another thread (see [`return_to_user`]).
+//!
+//! A thread terminates by [`exit`], which frees its kernel stack and leaves the hart idle.
+
+use core::arch::asm;
+use core::ffi::c_void;
+use core::mem;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+use crate::arch::csr::CSR;
+use crate::arch::register::Register;
+use crate::arch::sepc::SEPC;
+use crate::arch::sscratch::SScratch;
+use crate::arch::sstatus::{SStatus, SStatusPrivLevel, SStatusUnitStatus};
+use crate::arch::units::{self, NUM_FP_REGISTERS, NUM_VECTOR_CSRS, NUM_VECTOR_REGISTERS};
+use crate::config;
+use crate::kernel::address::{Address, VirtualAddress};
+use crate::kernel::cpu;
+use crate::mm::error::MemoryError;
+use crate::mm::mapping::USER_SPACE_END;
+use crate::mm::page_allocator::{PageFrameAllocator, PAGE_FRAME_ALLOCATOR};
+use crate::sync::epilogue;
+use crate::sync::init_cell::InitCell;
+use crate::sync::level::{
+    Adapter, AdapterEpilogueMapping, AdapterGuard, AdapterMappingPaging, Level, LevelEpilogue,
+    LevelInitialization, LevelMapping,
+};
+use crate::trap::handler_interface::TrapContext;
+
+extern "C" {
+    fn __return_to_user(context: *mut TrapContext) -> !;
+}
+
+/// Order of kernel stacks (i.e. `16KiB` per thread).
+const KERNEL_STACK_ORDER: usize = 2;
+
+/// Bytes reserved at the top of kernel stacks for the kernel `tp` (keeping the stack pointer
+/// aligned to `16` bytes).
+const KERNEL_TP_SIZE: usize = 16;
+
+/// Floating-point and vector units (as detected during boot).
+static UNITS: InitCell<Units> = InitCell::new();
+
+/// Unit state currently held by the registers of each hart (`null`, if none).
+static UNIT_OWNERS: [AtomicPtr<UnitState>; config::MAX_CPU_NUM] = {
+    const NONE: AtomicPtr<UnitState> = AtomicPtr::new(ptr::null_mut());
+    [NONE; config::MAX_CPU_NUM]
+};
+
+/// Kernel stack of the thread executed by each hart (`null`, if none).
+static CURRENT_THREADS: [AtomicPtr<c_void>; config::MAX_CPU_NUM] =
+    [const { AtomicPtr::new(ptr::null_mut()) }; config::MAX_CPU_NUM];
+
+/// Stack pointer of the caller of [`return_to_user`] on each hart (whose stack is abandoned and
+/// thus reused by [`exit`]).
+static IDLE_STACKS: [AtomicUsize; config::MAX_CPU_NUM] =
+    [const { AtomicUsize::new(0) }; config::MAX_CPU_NUM];
+
+/// Floating-point and vector units implemented by the harts.
+struct Units {
+    /// Floating-point unit is implemented.
+    fp: bool,
+    /// Length of vector registers in bytes (`None`, if the vector unit is not implemented).
+    vlenb: Option<usize>,
+}
+
+/// State of the floating-point and vector units of a thread.
+#[repr(C, align(16))]
+struct UnitState {
+    /// Registers `f0`..`f31` followed by `fcsr`.
+    fp: [u64; NUM_FP_REGISTERS],
+    /// Registers `vstart`, `vl`, `vtype` and `vcsr`.
+    vector_csrs: [u64; NUM_VECTOR_CSRS],
+    /// Registers `v0`..`v31` (only allocated, if the vector unit is implemented).
+    vector: Option<VirtualAddress<c_void>>,
+}
+
+/// Detect floating-point and vector units (assuming all harts to be identical).
+pub fn initialize(token: LevelInitialization) -> LevelInitialization {
+    let (fp, vlenb) = units::probe();
+
+    let mut detected = UNITS.get_mut(token);
+    *detected = Units { fp, vlenb };
+    let token = detected.destroy();
+    unsafe { UNITS.finanlize(token) }
+}
+
+/// Get order of the buffer holding the vector registers of a thread.
+fn vector_order(vlenb: usize) -> usize {
+    let pages = (NUM_VECTOR_REGISTERS * vlenb).div_ceil(cpu::page_size());
+    pages.next_power_of_two().trailing_zeros() as usize
+}
+
+/// Thread executing a user program.
+pub struct UserThread {
+    /// Kernel stack (within the direct map).
+    kernel_stack: VirtualAddress<c_void>,
+}
+
+impl UserThread {
+    /// Create thread starting at `entry` with its stack pointer set to `user_stack`.
+    ///
+    /// The user program has to be mapped with [`Mode::User`] into the virtual memory system loaded
+    /// when entering user mode (see [`return_to_user`]).
+    ///
+    /// [`Mode::User`]: crate::mm::mapping::Mode::User
+    pub fn new(
+        entry: VirtualAddress<c_void>,
+        user_stack: VirtualAddress<c_void>,
+        token: LevelMapping,
+    ) -> Result<(Self, LevelMapping), (MemoryError, LevelMapping)> {
+        // Step 1: Check that both addresses lie within user space
+        if entry.addr() >= USER_SPACE_END || user_stack.addr() > USER_SPACE_END {
+            return Err((MemoryError::InvalidAddress, token));
+        }
+
+        // Step 2: Allocate (zeroed) kernel stack and buffer for vector registers
+        let units = UNITS.as_ref();
+        let adapter = AdapterMappingPaging::new();
+        let (guard, t) = adapter.enter(token);
+        let (kernel_stack, t) = match PAGE_FRAME_ALLOCATOR.allocate_order(KERNEL_STACK_ORDER, t) {
+            Ok((phys_addr, t)) => (PageFrameAllocator::phys_to_virt(phys_addr), t),
+            Err((err, t)) => return Err((err, guard.leave(t))),
+        };
+        let (vector, token) = match units.vlenb {
+            Some(vlenb) => match PAGE_FRAME_ALLOCATOR.allocate_order(vector_order(vlenb), t) {
+                Ok((phys_addr, t)) => {
+                    let vector = PageFrameAllocator::phys_to_virt(phys_addr);
+                    (Some(vector), guard.leave(t))
+                }
+                Err((err, t)) => {
+                    let phys_addr = PageFrameAllocator::virt_to_phys(kernel_stack);
+                    let t = unsafe {
+                        PAGE_FRAME_ALLOCATOR.free_order(phys_addr, KERNEL_STACK_ORDER, t)
+                    };
+                    return Err((err, guard.leave(t)));
+                }
+            },
+            None => (None, guard.leave(t)),
+        };
+
+        // Step 3: Prepare initial unit state (with implemented units enabled)
+        let mut thread = Self { kernel_stack };
+        let state = UnitState {
+            fp: [0; NUM_FP_REGISTERS],
+            vector_csrs: [0; NUM_VECTOR_CSRS],
+            vector,
+        };
+        unsafe { thread.unit_state_ptr().write(state) };
+
+        let mut sstatus = SStatus::new(0);
+        sstatus.set_fs(match units.fp {
+            true => SStatusUnitStatus::Initial,
+            false => SStatusUnitStatus::Off,
+        });
+        sstatus.set_vs(match units.vlenb {
+            Some(_) => SStatusUnitStatus::Initial,
+            None => SStatusUnitStatus::Off,
+        });
+
+        // Step 4: Prepare initial user context
+        let context = thread.context_mut();
+        context.set_x2(Register::new(user_stack.addr() as u64));
+        context.set_sepc(SEPC::new(entry.addr() as u64));
+        context.set_sstatus(sstatus);
+
+        Ok((thread, token))
+    }
+
+    /// Get user context (as saved by the last trap from user mode).
+    pub fn context(&self) -> &TrapContext {
+        unsafe { self.context_ptr().as_ref().unwrap() }
+    }
+
+    /// Get mutable user context (applied when returning to user mode).
+    pub fn context_mut(&mut self) -> &mut TrapContext {
+        unsafe { self.context_ptr().as_mut().unwrap() }
+    }
+
+    /// Get pointer to user context (below the unit state and the kernel `tp` at the top of the
+    /// kernel stack).
+    fn context_ptr(&self) -> *mut TrapContext {
+        let top = self.kernel_stack.addr() + (cpu::page_size() << KERNEL_STACK_ORDER);
+        let size = mem::size_of::<UnitState>() + KERNEL_TP_SIZE + mem::size_of::<TrapContext>();
+        (top - size) as *mut TrapContext
+    }
+
+    /// Get pointer to unit state.
+    fn unit_state_ptr(&self) -> *mut UnitState {
+        unit_state_of(self.context_ptr())
+    }
+
+    /// Free kernel stack of thread (which must not execute anymore).
+    pub fn free(self, token: LevelMapping) -> LevelMapping {
+        // Step 1: Forget unit state held by any hart
+        let state = self.unit_state_ptr();
+        for owner in UNIT_OWNERS.iter() {
+            let _ = owner.compare_exchange(
+                state,
+                ptr::null_mut(),
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            );
+        }
+
+        // Step 2: Free buffer for vector registers and kernel stack
+        let vector = unsafe { state.as_ref().unwrap().vector };
+        let adapter = AdapterMappingPaging::new();
+        let (guard, t) = adapter.enter(token);
+        let t = match (vector, UNITS.as_ref().vlenb) {
+            (Some(vector), Some(vlenb)) => {
+                let phys_addr = PageFrameAllocator::virt_to_phys(vector);
+                unsafe { PAGE_FRAME_ALLOCATOR.free_order(phys_addr, vector_order(vlenb), t) }
+            }
+            _ => t,
+        };
+        let phys_addr = PageFrameAllocator::virt_to_phys(self.kernel_stack);
+        let t = unsafe { PAGE_FRAME_ALLOCATOR.free_order(phys_addr, KERNEL_STACK_ORDER, t) };
+        guard.leave(t)
+    }
+}
+
+/// Get unit state of thread owning user `context` (above the kernel `tp`).
+fn unit_state_of(context: *mut TrapContext) -> *mut UnitState {
+    (context as usize + mem::size_of::<TrapContext>() + KERNEL_TP_SIZE) as *mut UnitState
+}
+
+/// Save floating-point/vector state of the interrupted user thread (only if modified, i.e.
+/// `sstatus.FS`/`sstatus.VS` being [`SStatusUnitStatus::Dirty`]).
+///
+/// # Safety
+/// `state` must be the user context of a [`UserThread`] (i.e. passed by a trap from user mode).
+pub unsafe fn save_unit_state(state: &mut TrapContext) {
+    let unit_state = unsafe { unit_state_of(state).as_mut().unwrap() };
+    let mut sstatus = state.get_sstatus();
+
+    // Step 1: Save floating-point registers (unit still enabled by sstatus of user)
+    if sstatus.get_fs() == SStatusUnitStatus::Dirty {
+        unsafe { units::save_fp(unit_state.fp.as_mut_ptr()) };
+        sstatus.set_fs(SStatusUnitStatus::Clean);
+    }
+
+    // Step 2: Save vector registers
+    if sstatus.get_vs() == SStatusUnitStatus::Dirty {
+        if let Some(vector) = unit_state.vector {
+            let regs = vector.addr() as *mut u8;
+            unsafe { units::save_vector(regs, unit_state.vector_csrs.as_mut_ptr()) };
+        }
+        sstatus.set_vs(SStatusUnitStatus::Clean);
+    }
+
+    state.set_sstatus(sstatus);
+}
+
+/// Restore floating-point/vector state of the thread owning user `context`, unless still held by
+/// the registers of the current hart.
+fn restore_unit_state(context: &TrapContext) {
+    // Step 1: Check if registers hold state of another thread
+    let unit_state = unit_state_of(context as *const TrapContext as *mut TrapContext);
+    let owner = &UNIT_OWNERS[cpu::current().raw()];
+    if owner.load(Ordering::Relaxed) == unit_state {
+        return;
+    }
+    let unit_state = unsafe { unit_state.as_ref().unwrap() };
+    let user = context.get_sstatus();
+
+    // Step 2: Enable units for kernel temporarily
+    let mut sstatus = SStatus::new(0);
+    sstatus.read();
+    let original = SStatus::new(sstatus.inner());
+    sstatus.set_fs(user.get_fs());
+    sstatus.set_vs(user.get_vs());
+    sstatus.write();
+
+    // Step 3: Restore registers of enabled units
+    if user.get_fs() != SStatusUnitStatus::Off {
+        unsafe { units::restore_fp(unit_state.fp.as_ptr()) };
+    }
+    if let (true, Some(vector)) = (user.get_vs() != SStatusUnitStatus::Off, unit_state.vector) {
+        let regs = vector.addr() as *const u8;
+        unsafe { units::restore_vector(regs, unit_state.vector_csrs.as_ptr()) };
+    }
+
+    original.write();
+    owner.store(
+        unit_state as *const UnitState as *mut UnitState,
+        Ordering::Relaxed,
+    );
+}
+
+/// Enter user mode on the current hart by resuming `thread` at its user context.
+///
+/// The epilogue level is left and the stack of the caller is abandoned: From now on, the hart
+/// only executes within the kernel on traps of `thread`, which are handled on its kernel stack.
+pub fn return_to_user(thread: UserThread, token: LevelEpilogue) -> ! {
+    // Step 1: Disable interrupts (until entering user mode) and leave epilogue level
+    unsafe { cpu::disable_interrupts() };
+    epilogue::leave(token);
+
+    // Step 2: Remember thread and the stack of the caller (reused once the thread exits)
+    let sp: usize;
+    unsafe { asm!("mv {}, sp", out(reg) sp) };
+    IDLE_STACKS[cpu::current().raw()].store(sp, Ordering::Relaxed);
+    CURRENT_THREADS[cpu::current().raw()]
+        .store(thread.kernel_stack.addr() as *mut c_void, Ordering::Relaxed);
+
+    // Step 3: Store kernel tp (restored on traps from user mode) above the user context
+    let context = thread.context_ptr();
+    unsafe { (context.add(1) as *mut u64).write(cpu::current().raw() as u64) };
+
+    // Step 4: Let sscratch refer to the user context (loaded by `__return_to_user`)
+    let sscratch = SScratch::new(context as u64);
+    let context = unsafe { context.as_mut().unwrap() };
+    context.set_sscratch(sscratch);
+
+    // Step 5: Restore floating-point/vector state (if required)
+    restore_unit_state(context);
+
+    // Step 6: Return to user mode with interrupts enabled (without access to user memory), while
+    // keeping the unit status of the thread
+    let user = context.get_sstatus();
+    let mut sstatus = SStatus::new(0);
+    sstatus.read();
+    sstatus.set_spp(SStatusPrivLevel::UserMode);
+    sstatus.set_spie(true);
+    sstatus.set_sum(false);
+    sstatus.set_fs(user.get_fs());
+    sstatus.set_vs(user.get_vs());
+    context.set_sstatus(sstatus);
+
+    // Step 7: Restore user context and `sret` (see `entry.S`)
+    unsafe { __return_to_user(context) }
+}
+
+/// Terminate the thread executed by the current hart, which idles afterwards (still serving
+/// interrupts).
+///
+/// As the kernel stack of the thread is freed, execution continues on the stack abandoned by
+/// [`return_to_user`].
+pub fn exit(token: LevelEpilogue) -> ! {
+    // Consume token (recreated on the abandoned stack)
+    let _ = token;
+
+    // Switch stacks (terminating the frame-pointer chain) and continue with `idle`
+    let stack = IDLE_STACKS[cpu::current().raw()].load(Ordering::Relaxed);
+    assert!(
+        stack != 0,
+        "No user thread executed by core {}",
+        cpu::current()
+    );
+    unsafe {
+        asm!(
+            "mv sp, {stack}",
+            "mv s0, zero",
+            "tail {idle}",
+            stack = in(reg) stack,
+            idle = sym idle,
+            options(noreturn)
+        )
+    }
+}
+
+/// Free the exited thread of the current hart and wait for interrupts (see [`exit`]).
+extern "C" fn idle() -> ! {
+    // Step 1: Free kernel stack and unit state of thread
+    let token = unsafe { LevelEpilogue::create() };
+    let kernel_stack =
+        CURRENT_THREADS[cpu::current().raw()].swap(ptr::null_mut(), Ordering::Relaxed);
+    let thread = UserThread {
+        kernel_stack: VirtualAddress::new(kernel_stack),
+    };
+    let adapter = AdapterEpilogueMapping::new();
+    let (guard, t) = adapter.enter(token);
+    let token = guard.leave(thread.free(t));
+
+    // Step 2: Leave epilogue level and idle
+    epilogue::leave(token);
+    loop {
+        unsafe { asm!("wfi") };
+    }
+}
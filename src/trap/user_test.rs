@@ -0,0 +1,157 @@
+//! User test program (see `user_test.S`), started on the boot processor if [`config::USER_TEST`]
+//! is set.
+//!
+//! The program exercises the system calls from user mode and checks their results. Its exit
+//! status (i.e. the failed step or `0`) is reported by [`report`].
+//!
+//! [`config::USER_TEST`]: crate::config::USER_TEST
+
+use alloc::boxed::Box;
+use core::arch::asm;
+use core::ffi::c_void;
+use core::ptr;
+use core::slice;
+
+use crate::kernel::address::{Address, VirtualAddress};
+use crate::kernel::cpu;
+use crate::kernel::printer::LogLevel;
+use crate::mm::heap;
+use crate::mm::mapping::{MemoryType, Mode, Protection, VirtualMemorySystem};
+use crate::mm::page_allocator::{PageFrameAllocator, PAGE_FRAME_ALLOCATOR};
+use crate::printk;
+use crate::sync::level::{
+    Adapter, AdapterEpilogueMemory, AdapterGuard, AdapterMappingPaging, AdapterMemoryMapping,
+    LevelEpilogue, LevelMapping,
+};
+use crate::trap::user::{self, UserThread};
+
+extern "C" {
+    static __user_test_start: u8;
+    static __user_test_end: u8;
+}
+
+/// Virtual address the program is mapped at.
+const PROGRAM_ADDRESS: usize = 0x10000;
+
+/// Virtual address of the (single page) user stack.
+const STACK_ADDRESS: usize = 0x20000;
+
+/// Get the program (as position-independent code within `.rodata`).
+fn program() -> &'static [u8] {
+    unsafe {
+        let start = ptr::addr_of!(__user_test_start);
+        let end = ptr::addr_of!(__user_test_end);
+        slice::from_raw_parts(start, end.offset_from(start) as usize)
+    }
+}
+
+/// Load the program into a new virtual memory system and execute it on the current hart.
+///
+/// # Panic
+/// If the program cannot be loaded, this function will panic!
+pub fn start(token: LevelEpilogue) -> ! {
+    // Step 1: Create virtual memory system (leaked, as activated ones live for the remaining
+    // runtime)
+    let adapter = AdapterEpilogueMemory::new();
+    let (guard, t) = adapter.enter(token);
+    let adapter_mapping = AdapterMemoryMapping::new();
+    let (guard_mapping, t_mapping) = adapter_mapping.enter(t);
+    let (vms, t_mapping) = match VirtualMemorySystem::new(t_mapping) {
+        Ok(result) => result,
+        Err((error, _)) => panic!("Unable to create user virtual memory system: {}!", error),
+    };
+    let t = guard_mapping.leave(t_mapping);
+    let (vms, t) = heap::scope(t, || Box::leak(Box::new(vms)));
+    let vms: &'static VirtualMemorySystem = vms;
+
+    // Step 2: Map program and stack
+    let program = program();
+    assert!(
+        program.len() <= cpu::page_size(),
+        "User test program exceeds a single page"
+    );
+    let adapter_mapping = AdapterMemoryMapping::new();
+    let (guard_mapping, t_mapping) = adapter_mapping.enter(t);
+    let t_mapping = map_page(vms, PROGRAM_ADDRESS, Protection::RX, program, t_mapping);
+    let t_mapping = map_page(vms, STACK_ADDRESS, Protection::RW, &[], t_mapping);
+
+    // Synchronize instruction fetches with the copied program
+    unsafe { asm!("fence.i") };
+
+    // Step 3: Create thread and activate virtual memory system
+    let entry = VirtualAddress::new(PROGRAM_ADDRESS as *mut c_void);
+    let user_stack = VirtualAddress::new((STACK_ADDRESS + cpu::page_size()) as *mut c_void);
+    let (thread, t_mapping) = match UserThread::new(entry, user_stack, t_mapping) {
+        Ok(result) => result,
+        Err((error, _)) => panic!("Unable to create user thread: {}!", error),
+    };
+    let t_mapping = vms.activate(t_mapping);
+    let token = guard.leave(guard_mapping.leave(t_mapping));
+
+    // Step 4: Enter user mode
+    printk!(
+        LogLevel::Info,
+        "Core {}: Starting user test program\n",
+        cpu::current()
+    );
+    user::return_to_user(thread, token)
+}
+
+/// Report result of the program given its exit `status`.
+pub fn report(status: i32) {
+    match status {
+        0 => printk!(
+            LogLevel::Info,
+            "Core {}: User test program passed\n",
+            cpu::current()
+        ),
+        step => printk!(
+            LogLevel::Error,
+            "Core {}: User test program failed at step {}\n",
+            cpu::current(),
+            step
+        ),
+    }
+}
+
+/// Map a zeroed page holding `content` at `virt_addr` of `vms` (accessible from user mode).
+fn map_page(
+    vms: &VirtualMemorySystem,
+    virt_addr: usize,
+    protection: Protection,
+    content: &[u8],
+    token: LevelMapping,
+) -> LevelMapping {
+    // Step 1: Allocate (zeroed) page
+    let adapter = AdapterMappingPaging::new();
+    let (guard, t) = adapter.enter(token);
+    let (phys_addr, t) = match PAGE_FRAME_ALLOCATOR.allocate(t) {
+        Ok(result) => result,
+        Err((error, _)) => panic!("Unable to allocate user page: {}!", error),
+    };
+    let token = guard.leave(t);
+
+    // Step 2: Copy content into page
+    let mut page = PageFrameAllocator::phys_to_virt(phys_addr);
+    unsafe {
+        ptr::copy_nonoverlapping(
+            content.as_ptr(),
+            page.as_mut_ptr() as *mut u8,
+            content.len(),
+        )
+    };
+
+    // Step 3: Map page
+    let virt_addr = VirtualAddress::new(virt_addr as *mut c_void);
+    match vms.create(
+        phys_addr,
+        virt_addr,
+        protection,
+        Mode::User,
+        MemoryType::PMA,
+        token,
+    ) {
+        Ok(token) => token,
+        Err((error, _)) => panic!("Unable to map user page: {}!", error),
+    }
+}